@@ -32,13 +32,13 @@ echo -e "${CYAN}[1/5] Installing system dependencies...${NC}"
 case $DISTRO in
     ubuntu|debian|pop|linuxmint)
         sudo apt update
-        sudo apt install -y build-essential pkg-config libgtk-4-dev libglib2.0-dev libcairo2-dev libpango1.0-dev lm-sensors curl
+        sudo apt install -y build-essential pkg-config libgtk-4-dev libglib2.0-dev libcairo2-dev libpango1.0-dev libsqlite3-dev lm-sensors curl
         ;;
     fedora)
-        sudo dnf install -y @development-tools pkg-config gtk4-devel glib2-devel cairo-devel pango-devel lm-sensors curl
+        sudo dnf install -y @development-tools pkg-config gtk4-devel glib2-devel cairo-devel pango-devel sqlite-devel lm-sensors curl
         ;;
     arch|manjaro)
-        sudo pacman -S --needed --noconfirm base-devel pkgconf gtk4 glib2 cairo pango lm_sensors curl
+        sudo pacman -S --needed --noconfirm base-devel pkgconf gtk4 glib2 cairo pango sqlite lm_sensors curl
 
         ;;
     *)
@@ -0,0 +1,203 @@
+use crate::sensors::parse_reading;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Severity {
+    #[default]
+    Normal,
+    Warm,
+    Hot,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 4] = [
+        Severity::Normal,
+        Severity::Warm,
+        Severity::Hot,
+        Severity::Critical,
+    ];
+
+    /// Class names for status bars, e.g. #custom-gamer-monitor.hot in Waybar CSS
+    pub fn key(self) -> &'static str {
+        match self {
+            Severity::Normal => "normal",
+            Severity::Warm => "warm",
+            Severity::Hot => "hot",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Severity::Normal => "severity-normal",
+            Severity::Warm => "severity-warm",
+            Severity::Hot => "severity-hot",
+            Severity::Critical => "severity-critical",
+        }
+    }
+}
+
+/// A reading is warm from `warm` upwards, hot from `hot` upwards and
+/// critical from `critical` upwards
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    pub warm: f32,
+    pub hot: f32,
+    pub critical: f32,
+}
+
+impl Thresholds {
+    pub const fn new(warm: f32, hot: f32, critical: f32) -> Self {
+        Self { warm, hot, critical }
+    }
+
+    pub fn classify(&self, value: f32) -> Severity {
+        if value >= self.critical {
+            Severity::Critical
+        } else if value >= self.hot {
+            Severity::Hot
+        } else if value >= self.warm {
+            Severity::Warm
+        } else {
+            Severity::Normal
+        }
+    }
+
+    /// Classifies a raw sensor string such as "+54.0°C" or "1200 RPM".
+    /// Blank or unparsable readings are always normal.
+    pub fn classify_text(&self, text: &str) -> Severity {
+        parse_reading(text)
+            .map(|value| self.classify(value))
+            .unwrap_or_default()
+    }
+}
+
+/// Every component can be overridden from the `[alerts]` table of the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertThresholds {
+    /// AMD Ryzen (k10temp), Tjmax 95 °C
+    pub cpu_tctl: Thresholds,
+    pub cpu_tdie: Thresholds,
+    /// Intel (coretemp), Tjmax 100 °C
+    pub cpu_package: Thresholds,
+    pub cpu_core: Thresholds,
+
+    /// amdgpu edge / fallback sensor for Intel and unknown GPUs
+    pub gpu_edge: Thresholds,
+    /// amdgpu junction, throttles at 110 °C
+    pub gpu_hotspot: Thresholds,
+    pub gpu_memory: Thresholds,
+    /// nvidia-smi temperature.gpu, slowdown around 90 °C
+    pub gpu_nvidia: Thresholds,
+
+    pub nvme: Thresholds,
+
+    pub cpu_load: Thresholds,
+    pub gpu_load: Thresholds,
+    pub vram_load: Thresholds,
+    pub ram_load: Thresholds,
+
+    pub fan_rpm: Thresholds,
+    pub fan_percent: Thresholds,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            cpu_tctl: Thresholds::new(75.0, 85.0, 95.0),
+            cpu_tdie: Thresholds::new(75.0, 85.0, 95.0),
+            cpu_package: Thresholds::new(80.0, 90.0, 100.0),
+            cpu_core: Thresholds::new(80.0, 90.0, 100.0),
+
+            gpu_edge: Thresholds::new(80.0, 90.0, 100.0),
+            gpu_hotspot: Thresholds::new(90.0, 100.0, 110.0),
+            gpu_memory: Thresholds::new(85.0, 95.0, 105.0),
+            gpu_nvidia: Thresholds::new(70.0, 80.0, 88.0),
+
+            nvme: Thresholds::new(60.0, 70.0, 80.0),
+
+            cpu_load: Thresholds::new(70.0, 90.0, 98.0),
+            gpu_load: Thresholds::new(70.0, 90.0, 98.0),
+            vram_load: Thresholds::new(75.0, 90.0, 97.0),
+            ram_load: Thresholds::new(75.0, 90.0, 97.0),
+
+            fan_rpm: Thresholds::new(2200.0, 3000.0, 3800.0),
+            fan_percent: Thresholds::new(60.0, 80.0, 95.0),
+        }
+    }
+}
+
+impl AlertThresholds {
+    /// Every threshold with its configuration key and the label the
+    /// preferences show for it
+    pub fn named(&self) -> [(&'static str, &'static str, &Thresholds); 15] {
+        [
+            ("cpu_tctl", "CPU, AMD Tctl (°C)", &self.cpu_tctl),
+            ("cpu_tdie", "CPU, AMD Tdie (°C)", &self.cpu_tdie),
+            ("cpu_package", "CPU, Intel package (°C)", &self.cpu_package),
+            ("cpu_core", "CPU, core 0 (°C)", &self.cpu_core),
+            ("gpu_edge", "GPU edge (°C)", &self.gpu_edge),
+            ("gpu_hotspot", "GPU hotspot (°C)", &self.gpu_hotspot),
+            ("gpu_memory", "GPU memory (°C)", &self.gpu_memory),
+            ("gpu_nvidia", "GPU, NVIDIA (°C)", &self.gpu_nvidia),
+            ("nvme", "NVMe (°C)", &self.nvme),
+            ("cpu_load", "CPU load (%)", &self.cpu_load),
+            ("gpu_load", "GPU load (%)", &self.gpu_load),
+            ("vram_load", "VRAM usage (%)", &self.vram_load),
+            ("ram_load", "RAM usage (%)", &self.ram_load),
+            ("fan_rpm", "Fan speed (RPM)", &self.fan_rpm),
+            ("fan_percent", "Fan speed (%)", &self.fan_percent),
+        ]
+    }
+
+    pub fn named_mut(&mut self) -> [(&'static str, &mut Thresholds); 15] {
+        [
+            ("cpu_tctl", &mut self.cpu_tctl),
+            ("cpu_tdie", &mut self.cpu_tdie),
+            ("cpu_package", &mut self.cpu_package),
+            ("cpu_core", &mut self.cpu_core),
+            ("gpu_edge", &mut self.gpu_edge),
+            ("gpu_hotspot", &mut self.gpu_hotspot),
+            ("gpu_memory", &mut self.gpu_memory),
+            ("gpu_nvidia", &mut self.gpu_nvidia),
+            ("nvme", &mut self.nvme),
+            ("cpu_load", &mut self.cpu_load),
+            ("gpu_load", &mut self.gpu_load),
+            ("vram_load", &mut self.vram_load),
+            ("ram_load", &mut self.ram_load),
+            ("fan_rpm", &mut self.fan_rpm),
+            ("fan_percent", &mut self.fan_percent),
+        ]
+    }
+
+    /// Picks the CPU thresholds matching the sensor the temperature was read from
+    pub fn cpu_temp(&self, sensor: &str) -> &Thresholds {
+        match sensor {
+            "Tctl" => &self.cpu_tctl,
+            "Tdie" => &self.cpu_tdie,
+            "Package id 0" => &self.cpu_package,
+            _ => &self.cpu_core,
+        }
+    }
+
+    /// NVIDIA reports its single temperature through the edge reading
+    pub fn gpu_temp(&self, gpu_name: &str) -> &Thresholds {
+        if gpu_name.to_lowercase().contains("nvidia") {
+            &self.gpu_nvidia
+        } else {
+            &self.gpu_edge
+        }
+    }
+
+    /// GPU fans are reported in RPM by amdgpu and as a percentage by nvidia-smi
+    pub fn fan(&self, reading: &str) -> &Thresholds {
+        if reading.trim_end().ends_with('%') {
+            &self.fan_percent
+        } else {
+            &self.fan_rpm
+        }
+    }
+}
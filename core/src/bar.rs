@@ -0,0 +1,402 @@
+use crate::alerts::Severity;
+use crate::config::Config;
+use crate::metrics::{self, Component};
+use crate::sampler::Snapshot;
+use crate::sensors::{SensorData, parse_reading};
+use serde_json::json;
+use std::fmt::Write;
+
+/// Placeholders available in bar templates. Temperatures and power follow
+/// the configured units; the rest are %, RPM, MiB and GiB.
+pub const PLACEHOLDERS: [&str; 21] = [
+    "cpu_name",
+    "cpu_temp",
+    "cpu_load",
+    "gpu_name",
+    "gpu_temp",
+    "gpu_hotspot",
+    "gpu_memory",
+    "gpu_fan",
+    "gpu_power",
+    "gpu_load",
+    "vram_used",
+    "vram_total",
+    "vram_load",
+    "nvme_temp",
+    "cpu_fan",
+    "chassis_fan1",
+    "chassis_fan2",
+    "ram_used",
+    "ram_total",
+    "ram_available",
+    "ram_load",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarFormat {
+    // One JSON object per line for a custom module with "return-type": "json"
+    Waybar,
+    // The i3bar protocol, also read by swaybar and i3status-rs wrappers
+    I3bar,
+    // Plain lines with %{F} color tags for a custom/script module with tail = true
+    Polybar,
+}
+
+impl BarFormat {
+    pub fn parse(name: &str) -> Option<BarFormat> {
+        match name {
+            "waybar" => Some(BarFormat::Waybar),
+            "i3bar" => Some(BarFormat::I3bar),
+            "polybar" => Some(BarFormat::Polybar),
+            _ => None,
+        }
+    }
+}
+
+// Template text split at its placeholders, e.g. "{cpu_temp}°" becomes
+// [Field("cpu_temp"), Text("°")]
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(String),
+}
+
+pub struct Bar {
+    format: BarFormat,
+    template: Vec<Part>,
+    tooltip: bool,
+    config: Config,
+    // The i3bar protocol starts with a header and an endless array
+    started: bool,
+}
+
+impl Bar {
+    pub fn new(format: BarFormat, template: &str, config: &Config) -> Result<Self, String> {
+        Ok(Self {
+            format,
+            template: parse_template(template)?,
+            tooltip: config.bar.tooltip,
+            config: config.clone(),
+            started: false,
+        })
+    }
+
+    /// One update for the bar, including its trailing newline
+    pub fn render(&mut self, snapshot: &Snapshot) -> String {
+        let data = &snapshot.data;
+        let (text, severity) = self.fill(data);
+        let mut out = String::new();
+
+        match self.format {
+            BarFormat::Waybar => {
+                let mut module = json!({
+                    "text": text,
+                    "class": severity.key(),
+                    "alt": severity.key(),
+                });
+                if self.tooltip {
+                    module["tooltip"] = json!(self.summary(data));
+                }
+                let _ = writeln!(out, "{}", module);
+            }
+            BarFormat::I3bar => {
+                if !self.started {
+                    out.push_str("{\"version\":1}\n[\n");
+                }
+                let mut block = json!({
+                    "name": "gamer_monitor",
+                    "full_text": text,
+                });
+                if let Some(color) = color(severity) {
+                    block["color"] = json!(color);
+                }
+                if severity == Severity::Critical {
+                    block["urgent"] = json!(true);
+                }
+                // Every array after the first is preceded by a comma
+                let _ = writeln!(out, "{}[{}]", if self.started { "," } else { "" }, block);
+            }
+            BarFormat::Polybar => match color(severity) {
+                Some(color) => {
+                    let _ = writeln!(out, "%{{F{}}}{}%{{F-}}", color, text);
+                }
+                None => {
+                    let _ = writeln!(out, "{}", text);
+                }
+            },
+        }
+
+        self.started = true;
+        out
+    }
+
+    // The template with its placeholders replaced, and the worst severity
+    // among the readings it shows
+    fn fill(&self, data: &SensorData) -> (String, Severity) {
+        let mut text = String::new();
+        let mut worst = Severity::Normal;
+
+        for part in &self.template {
+            match part {
+                Part::Text(literal) => text.push_str(literal),
+                Part::Field(name) => {
+                    let (value, severity) = field(name, data, &self.config);
+                    text.push_str(&self.escape(&value));
+                    worst = worst.max(severity);
+                }
+            }
+        }
+
+        (text, worst)
+    }
+
+    // Every reading, grouped like the text output, for the Waybar tooltip
+    fn summary(&self, data: &SensorData) -> String {
+        let units = self.config.units;
+        let readings = metrics::collect(data, &self.config.fans);
+        let mut lines = vec![format!("CPU: {}", data.cpu_name), format!("GPU: {}", data.gpu_name)];
+
+        for component in Component::ALL {
+            let section: Vec<_> = readings.iter().filter(|r| r.component == component).collect();
+            if section.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(component.title().to_string());
+            for reading in section {
+                let value = match reading.value {
+                    Some(value) => units.reading(reading.quantity, value),
+                    None => "N/A".to_string(),
+                };
+                lines.push(format!("  {}: {}", reading.title, value));
+            }
+        }
+
+        self.escape(&lines.join("\n"))
+    }
+
+    // Waybar renders text and tooltips as Pango markup
+    fn escape(&self, text: &str) -> String {
+        match self.format {
+            BarFormat::Waybar => text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// Rejects unknown placeholders up front, so a typo in the template shows
+/// up as an error instead of a literal "{cpu_tmp}" in the bar
+pub fn check_template(template: &str) -> Result<(), String> {
+    parse_template(template).map(|_| ())
+}
+
+fn parse_template(template: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+
+    loop {
+        // A stray '}' is as likely a typo as a stray '{'
+        if rest[..rest.find('{').unwrap_or(rest.len())].contains('}') {
+            return Err(format!("unmatched '}}' in template \"{}\"", template));
+        }
+        let Some(start) = rest.find('{') else {
+            break;
+        };
+        if start > 0 {
+            parts.push(Part::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in template \"{}\"", template))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder {{{}}} (available: {})",
+                name,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        parts.push(Part::Field(name.to_string()));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+
+    Ok(parts)
+}
+
+/// The value of one placeholder and its severity under the configured alerts.
+/// Values are kept short for the bar: whole degrees, percent and RPM.
+pub fn field(name: &str, data: &SensorData, config: &Config) -> (String, Severity) {
+    let alerts = &config.alerts;
+    let units = config.units;
+    let temperature = |text: &str| match parse_reading(text) {
+        Some(celsius) => format!("{:.0}", units.temperature.convert(celsius)),
+        None => "N/A".to_string(),
+    };
+    let whole = |text: &str| match parse_reading(text) {
+        Some(value) => format!("{:.0}", value),
+        None => "N/A".to_string(),
+    };
+    let is_nvidia = metrics::gpu_vendor(&data.gpu_name) == "nvidia";
+    let vram_load = match (data.gpu_vram_used.parse::<f32>(), data.gpu_vram_total.parse::<f32>()) {
+        (Ok(used), Ok(total)) if total > 0.0 => Some(used / total * 100.0),
+        _ => None,
+    };
+    let hottest_nvme = data
+        .nvme_temps
+        .iter()
+        .filter_map(|temp| parse_reading(temp))
+        .fold(None, |hottest: Option<f32>, temp| Some(hottest.map_or(temp, |h| h.max(temp))));
+
+    match name {
+        "cpu_name" => (data.cpu_name.clone(), Severity::Normal),
+        "cpu_temp" => (
+            temperature(&data.cpu_temp),
+            alerts.cpu_temp(&data.cpu_temp_sensor).classify_text(&data.cpu_temp),
+        ),
+        "cpu_load" => (format!("{:.0}", data.cpu_usage), alerts.cpu_load.classify(data.cpu_usage)),
+        "gpu_name" => (data.gpu_name.clone(), Severity::Normal),
+        "gpu_temp" => (
+            temperature(&data.gpu_edge),
+            alerts.gpu_temp(&data.gpu_name).classify_text(&data.gpu_edge),
+        ),
+        "gpu_hotspot" => (temperature(&data.gpu_hotspot), alerts.gpu_hotspot.classify_text(&data.gpu_hotspot)),
+        "gpu_memory" => (temperature(&data.gpu_memory), alerts.gpu_memory.classify_text(&data.gpu_memory)),
+        "gpu_fan" => (whole(&data.gpu_fan), alerts.fan(&data.gpu_fan).classify_text(&data.gpu_fan)),
+        "gpu_power" => match parse_reading(&data.gpu_power) {
+            Some(watts) => (format!("{:.0}", units.power.convert(watts)), Severity::Normal),
+            None => ("N/A".to_string(), Severity::Normal),
+        },
+        "gpu_load" if is_nvidia => (
+            format!("{:.0}", data.gpu_utilization),
+            alerts.gpu_load.classify(data.gpu_utilization),
+        ),
+        "gpu_load" => ("N/A".to_string(), Severity::Normal),
+        "vram_used" => (whole(&data.gpu_vram_used), Severity::Normal),
+        "vram_total" => (whole(&data.gpu_vram_total), Severity::Normal),
+        "vram_load" => match vram_load {
+            Some(load) => (format!("{:.0}", load), alerts.vram_load.classify(load)),
+            None => ("N/A".to_string(), Severity::Normal),
+        },
+        "nvme_temp" => match hottest_nvme {
+            Some(celsius) => (
+                format!("{:.0}", units.temperature.convert(celsius)),
+                alerts.nvme.classify(celsius),
+            ),
+            None => ("N/A".to_string(), Severity::Normal),
+        },
+        "cpu_fan" => (whole(&data.cpu_fan), alerts.fan_rpm.classify_text(&data.cpu_fan)),
+        "chassis_fan1" => (whole(&data.chassis_fan1), alerts.fan_rpm.classify_text(&data.chassis_fan1)),
+        "chassis_fan2" => (whole(&data.chassis_fan2), alerts.fan_rpm.classify_text(&data.chassis_fan2)),
+        "ram_used" => (format!("{:.1}", data.ram_used), Severity::Normal),
+        "ram_total" => (format!("{:.1}", data.ram_total), Severity::Normal),
+        "ram_available" => (format!("{:.1}", data.ram_available), Severity::Normal),
+        "ram_load" => (format!("{:.0}", data.ram_percent), alerts.ram_load.classify(data.ram_percent)),
+        // parse_template only lets the placeholders listed above through
+        _ => (String::new(), Severity::Normal),
+    }
+}
+
+// Same colors as the severity CSS in style.rs
+fn color(severity: Severity) -> Option<&'static str> {
+    match severity {
+        Severity::Normal => None,
+        Severity::Warm => Some("#e5a50a"),
+        Severity::Hot => Some("#e66100"),
+        Severity::Critical => Some("#c01c28"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    fn snapshot() -> Snapshot {
+        let mut data = SensorData::new();
+        data.cpu_name = "AMD Ryzen 7 5800X3D".to_string();
+        data.cpu_temp = "+91.0°C".to_string();
+        data.cpu_temp_sensor = "Tctl".to_string();
+        data.cpu_usage = 42.4;
+        data.gpu_name = "Radeon RX 6800 <XT> & co".to_string();
+        data.gpu_edge = "+54.0°C".to_string();
+        Snapshot {
+            sequence: 1,
+            timestamp: UNIX_EPOCH,
+            data,
+            processes: Arc::new(Vec::new()),
+            diagnostics: Arc::new(Vec::new()),
+        }
+    }
+
+    fn render(format: BarFormat, template: &str, count: usize) -> Vec<String> {
+        let mut config = Config::default();
+        config.bar.tooltip = false;
+        let mut bar = Bar::new(format, template, &config).unwrap();
+        let snapshot = snapshot();
+        (0..count).map(|_| bar.render(&snapshot)).collect()
+    }
+
+    #[test]
+    fn templates() {
+        use Part::*;
+        let text = |text: &str| Text(text.to_string());
+        let field = |name: &str| Field(name.to_string());
+        assert_eq!(parse_template(""), Ok(vec![]));
+        assert_eq!(parse_template("{cpu_temp}°"), Ok(vec![field("cpu_temp"), text("°")]));
+        assert_eq!(
+            parse_template("CPU {cpu_load}% GPU {gpu_load}%"),
+            Ok(vec![text("CPU "), field("cpu_load"), text("% GPU "), field("gpu_load"), text("%")])
+        );
+
+        for (template, expected) in [
+            ("{cpu_tmp}°", "unknown placeholder {cpu_tmp} (available: "),
+            ("{}", "unknown placeholder {} (available: "),
+            ("{CPU_TEMP}", "unknown placeholder {CPU_TEMP} (available: "),
+            ("{cpu_temp", "unclosed '{' in template \"{cpu_temp\""),
+            ("{cpu_temp} {gpu_temp", "unclosed '{' in template \"{cpu_temp} {gpu_temp\""),
+            ("cpu_temp}°", "unmatched '}' in template \"cpu_temp}°\""),
+            ("{cpu_temp}}", "unmatched '}' in template \"{cpu_temp}}\""),
+            ("{{cpu_temp}}", "unknown placeholder {{cpu_temp} (available: "),
+            // Polybar's own tags are not placeholders
+            ("%{F#f00}{cpu_temp}%{F-}", "unknown placeholder {F#f00} (available: "),
+        ] {
+            let err = parse_template(template).unwrap_err();
+            assert!(err.starts_with(expected), "{}: {}", template, err);
+        }
+    }
+
+    #[test]
+    fn i3bar_stream() {
+        let lines = render(BarFormat::I3bar, "{cpu_temp}° {cpu_load}%", 3);
+        assert_eq!(
+            lines[0],
+            "{\"version\":1}\n[\n[{\"name\":\"gamer_monitor\",\"full_text\":\"91° 42%\",\"color\":\"#e66100\"}]\n"
+        );
+        // Only the first array comes without a leading comma
+        assert_eq!(lines[1], ",[{\"name\":\"gamer_monitor\",\"full_text\":\"91° 42%\",\"color\":\"#e66100\"}]\n");
+        assert_eq!(lines[2], lines[1]);
+    }
+
+    #[test]
+    fn waybar_escapes_pango() {
+        let lines = render(BarFormat::Waybar, "<b>{gpu_temp}°</b> {gpu_name}", 1);
+        let module: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        // The template is markup of its own; only the readings are escaped
+        assert_eq!(module["text"], "<b>54°</b> Radeon RX 6800 &lt;XT&gt; &amp; co");
+        assert_eq!(module["class"], "normal");
+        assert!(module.get("tooltip").is_none());
+
+        let mut bar = Bar::new(BarFormat::Waybar, "{gpu_name}", &Config::default()).unwrap();
+        let module: serde_json::Value = serde_json::from_str(&bar.render(&snapshot())).unwrap();
+        let tooltip = module["tooltip"].as_str().unwrap();
+        assert!(tooltip.contains("GPU: Radeon RX 6800 &lt;XT&gt; &amp; co"), "{}", tooltip);
+
+        // Other bars take the text as it is
+        assert_eq!(render(BarFormat::Polybar, "{gpu_name}", 1)[0], "Radeon RX 6800 <XT> & co\n");
+        assert_eq!(render(BarFormat::Polybar, "{cpu_temp}", 1)[0], "%{F#e66100}91%{F-}\n");
+    }
+}
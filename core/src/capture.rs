@@ -0,0 +1,403 @@
+use crate::runner::Runner;
+use crate::timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+// Bumped if a change to the records would confuse older versions
+const FORMAT: u32 = 1;
+
+/// What a program printed, and how it exited
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    pub success: bool,
+    /// None when it was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Every raw input SensorData parses: files like /proc/stat and the output
+/// of programs like `sensors`. Reading them through a source lets a capture
+/// record them, and a replay feed them back to the same parsers.
+pub trait Source {
+    fn read_file(&mut self, path: &str) -> io::Result<String>;
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output>;
+}
+
+/// This machine
+#[derive(Default)]
+pub struct System {
+    runner: Runner,
+}
+
+impl Source for System {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+        self.runner.run(program, args)
+    }
+}
+
+// The first line of a capture
+#[derive(Serialize, Deserialize)]
+struct Header {
+    capture: u32,
+    version: String,
+    started: String,
+}
+
+// Every other line: one read, with the seconds since the capture started.
+// A failed read keeps its error, so a missing nvidia-smi replays as missing.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    time: f64,
+    kind: Kind,
+    input: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stderr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    // Why it failed, when that matters to the diagnostics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<Reason>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Reason {
+    NotFound,
+    PermissionDenied,
+    TimedOut,
+}
+
+impl Record {
+    fn set_error(&mut self, err: &io::Error) {
+        self.error = Some(err.to_string());
+        self.reason = match err.kind() {
+            io::ErrorKind::NotFound => Some(Reason::NotFound),
+            io::ErrorKind::PermissionDenied => Some(Reason::PermissionDenied),
+            io::ErrorKind::TimedOut => Some(Reason::TimedOut),
+            _ => None,
+        };
+    }
+
+    fn error(&self) -> Option<io::Error> {
+        let kind = match self.reason {
+            Some(Reason::NotFound) => io::ErrorKind::NotFound,
+            Some(Reason::PermissionDenied) => io::ErrorKind::PermissionDenied,
+            Some(Reason::TimedOut) => io::ErrorKind::TimedOut,
+            None => io::ErrorKind::Other,
+        };
+        Some(io::Error::new(kind, self.error.clone()?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    File,
+    Command,
+}
+
+pub(crate) fn command_line(program: &str, args: &[&str]) -> String {
+    std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ")
+}
+
+/// Reads this machine and appends every read to a capture (--capture)
+pub struct Recorder {
+    source: Box<dyn Source>,
+    file: LineWriter<File>,
+    started: Instant,
+    failed: bool,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::wrap(Box::new(System::default()), path)
+    }
+
+    // Records what another source reads, which tests use in place of this machine
+    fn wrap(source: Box<dyn Source>, path: &Path) -> io::Result<Self> {
+        let mut file = LineWriter::new(File::create(path)?);
+        let header = Header {
+            capture: FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started: timestamp::rfc3339(SystemTime::now()),
+        };
+        writeln!(file, "{}", serde_json::to_string(&header).map_err(io::Error::other)?)?;
+        Ok(Self { source, file, started: Instant::now(), failed: false })
+    }
+
+    fn record(&self, kind: Kind, input: String) -> Record {
+        Record {
+            time: self.started.elapsed().as_secs_f64(),
+            kind,
+            input,
+            success: None,
+            code: None,
+            output: None,
+            stderr: None,
+            error: None,
+            reason: None,
+        }
+    }
+
+    fn write(&mut self, record: Record) {
+        let written = serde_json::to_string(&record)
+            .map_err(io::Error::other)
+            .and_then(|line| writeln!(self.file, "{}", line));
+        // Keep monitoring; one message is enough
+        if let Err(err) = written
+            && !self.failed
+        {
+            log::error!("Cannot write to the capture: {}", err);
+            self.failed = true;
+        }
+    }
+}
+
+impl Source for Recorder {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        let result = self.source.read_file(path);
+        let mut record = self.record(Kind::File, path.to_string());
+        match &result {
+            Ok(text) => record.output = Some(text.clone()),
+            Err(err) => record.set_error(err),
+        }
+        self.write(record);
+        result
+    }
+
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+        let result = self.source.run(program, args);
+        let mut record = self.record(Kind::Command, command_line(program, args));
+        match &result {
+            Ok(output) => {
+                record.success = Some(output.success);
+                record.code = output.code;
+                record.output = Some(output.stdout.clone());
+                record.stderr = (!output.stderr.is_empty()).then(|| output.stderr.clone());
+            }
+            Err(err) => record.set_error(err),
+        }
+        self.write(record);
+        result
+    }
+}
+
+/// Plays a capture back at the speed it was recorded (--replay). Each read
+/// returns the latest recording of that input for the time into the replay,
+/// so the sampling intervals need not match the captured ones. At the end
+/// it starts over.
+pub struct Replay {
+    inputs: HashMap<(Kind, String), Vec<Record>>,
+    length: f64,
+    started: Instant,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+
+        let first = lines.next().transpose().map_err(|err| err.to_string())?;
+        match first.and_then(|line| serde_json::from_str::<Header>(&line).ok()) {
+            Some(header) if header.capture <= FORMAT => {}
+            Some(header) => return Err(format!("capture format {} needs a newer gamer-monitor", header.capture)),
+            None => return Err("not a gamer-monitor capture".to_string()),
+        }
+
+        let mut inputs: HashMap<(Kind, String), Vec<Record>> = HashMap::new();
+        let mut length: f64 = 0.0;
+        for (number, line) in lines.enumerate() {
+            let line = line.map_err(|err| err.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Record = serde_json::from_str(&line).map_err(|err| format!("line {}: {}", number + 2, err))?;
+            length = length.max(record.time);
+            inputs.entry((record.kind, record.input.clone())).or_default().push(record);
+        }
+        if inputs.is_empty() {
+            return Err("the capture has no readings".to_string());
+        }
+        for records in inputs.values_mut() {
+            records.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+
+        Ok(Self { inputs, length, started: Instant::now() })
+    }
+
+    fn find(&self, kind: Kind, input: String) -> io::Result<&Record> {
+        let records = self
+            .inputs
+            .get(&(kind, input))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the capture"))?;
+        // The last readings stay on screen for a second before starting over
+        let time = self.started.elapsed().as_secs_f64() % (self.length + 1.0);
+        let record = records.iter().rev().find(|record| record.time <= time).unwrap_or(&records[0]);
+        match record.error() {
+            Some(err) => Err(err),
+            None => Ok(record),
+        }
+    }
+}
+
+impl Source for Replay {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        let record = self.find(Kind::File, path.to_string())?;
+        Ok(record.output.clone().unwrap_or_default())
+    }
+
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+        let record = self.find(Kind::Command, command_line(program, args))?;
+        Ok(Output {
+            success: record.success.unwrap_or(true),
+            code: record.code,
+            stdout: record.output.clone().unwrap_or_default(),
+            stderr: record.stderr.clone().unwrap_or_default(),
+        })
+    }
+}
+
+/// A replay, this machine recorded into a capture, or just this machine
+pub fn open(replay: Option<&Path>, capture: Option<&Path>) -> Result<Box<dyn Source>, String> {
+    if let Some(path) = replay {
+        let replay = Replay::load(path).map_err(|err| format!("Cannot replay {}: {}", path.display(), err))?;
+        return Ok(Box::new(replay));
+    }
+    if let Some(path) = capture {
+        let recorder = Recorder::create(path).map_err(|err| format!("Cannot create the capture {}: {}", path.display(), err))?;
+        return Ok(Box::new(recorder));
+    }
+    Ok(Box::new(System::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorData;
+    use std::path::PathBuf;
+
+    // One machine's fixture outputs, with a few of the ways reads fail
+    struct Machine(PathBuf);
+
+    impl Machine {
+        fn read(&self, name: &str) -> io::Result<String> {
+            std::fs::read_to_string(self.0.join(name))
+        }
+    }
+
+    impl Source for Machine {
+        fn read_file(&mut self, path: &str) -> io::Result<String> {
+            match path {
+                "/proc/cpuinfo" => self.read("cpuinfo.txt"),
+                "/proc/stat" => Err(io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied (os error 13)")),
+                _ => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+            let stdout = match (program, args.first()) {
+                ("nvidia-smi", Some(&"--query-gpu=name")) => self.read("nvidia-smi-name.csv")?,
+                ("nvidia-smi", _) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "nvidia-smi did not finish within 5 seconds"));
+                }
+                // sensors fails when one chip cannot be read, but prints the others
+                ("sensors", _) => {
+                    return Ok(Output {
+                        success: false,
+                        code: Some(1),
+                        stdout: self.read("sensors.txt")?,
+                        stderr: "ERROR: Can't get value of subfeature temp1_input: Can't read\n".to_string(),
+                    });
+                }
+                ("lspci", _) => self.read("lspci.txt")?,
+                ("free", _) => self.read("free.txt")?,
+                _ => return Err(io::Error::new(io::ErrorKind::NotFound, "No such file or directory (os error 2)")),
+            };
+            Ok(Output { success: true, code: Some(0), stdout, stderr: String::new() })
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gamer-monitor-{}-{}", std::process::id(), name))
+    }
+
+    fn read_all(source: &mut dyn Source) -> SensorData {
+        let mut data = SensorData::new();
+        data.identify_hardware(source);
+        data.update(source);
+        data
+    }
+
+    #[test]
+    fn roundtrip() {
+        let path = temp_path("roundtrip.jsonl");
+        let machine = Machine(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/intel-12700k-rtx3070-it87"));
+        let mut recorder = Recorder::wrap(Box::new(machine), &path).unwrap();
+        let recorded = read_all(&mut recorder);
+        drop(recorder);
+
+        let mut replay = Replay::load(&path).unwrap();
+        let replayed = read_all(&mut replay);
+        assert_eq!(replayed, recorded);
+        assert_eq!((replayed.cpu_temp.as_str(), replayed.gpu_name.as_str()), ("41.0", "NVIDIA GeForce RTX 3070"));
+        assert_eq!(replayed.ram_total, 31899.0 / 1024.0);
+
+        // Failed reads replay with the reason the diagnostics report
+        let denied = replay.read_file("/proc/stat").unwrap_err();
+        assert_eq!((denied.kind(), denied.to_string()), (io::ErrorKind::PermissionDenied, "Permission denied (os error 13)".to_string()));
+        let timed_out = replay.run(
+            "nvidia-smi",
+            &[
+                "--query-gpu=temperature.gpu,fan.speed,memory.used,memory.total,power.draw,utilization.gpu",
+                "--format=csv,noheader,nounits",
+            ],
+        );
+        let timed_out = timed_out.unwrap_err();
+        assert_eq!((timed_out.kind(), timed_out.to_string()), (io::ErrorKind::TimedOut, "nvidia-smi did not finish within 5 seconds".to_string()));
+
+        let sensors = replay.run("sensors", &[]).unwrap();
+        assert_eq!((sensors.success, sensors.code), (false, Some(1)));
+        assert!(sensors.stderr.starts_with("ERROR: Can't get value"));
+
+        let missing = replay.run("glxinfo", &["-B"]).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn newer_formats_are_rejected() {
+        let path = temp_path("newer.jsonl");
+        let record = r#"{"time":0.0,"kind":"file","input":"/proc/stat","output":"cpu  1 2 3 4"}"#;
+        let header = |format: u32| format!(r#"{{"capture":{},"version":"9.0.0","started":"2030-01-01T00:00:00Z"}}"#, format);
+
+        std::fs::write(&path, format!("{}\n{}\n", header(FORMAT + 1), record)).unwrap();
+        assert_eq!(Replay::load(&path).err().unwrap(), format!("capture format {} needs a newer gamer-monitor", FORMAT + 1));
+
+        std::fs::write(&path, format!("{}\n{}\n", header(FORMAT), record)).unwrap();
+        assert!(Replay::load(&path).is_ok());
+
+        std::fs::write(&path, format!("{}\n", header(FORMAT))).unwrap();
+        assert_eq!(Replay::load(&path).err().unwrap(), "the capture has no readings");
+
+        std::fs::write(&path, "time,cpu_load_percent\n").unwrap();
+        assert_eq!(Replay::load(&path).err().unwrap(), "not a gamer-monitor capture");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
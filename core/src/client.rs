@@ -0,0 +1,203 @@
+use serde_json::{Value, json};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Client for the local socket of a running Gamer Monitor, or for an agent
+// started with --agent on another machine. The protocol is JSON-RPC 2.0
+// with one message per line; the server announces its protocol version in
+// the "hello" reply and only adds to it within a version.
+//
+//     let mut client = Client::connect()?;
+//     let snapshot = client.snapshot()?;
+//     println!("{}", snapshot["readings"]["cpu_temperature_celsius"]);
+
+pub const PROTOCOL_VERSION: u64 = 1;
+
+const SOCKET_NAME: &str = "gamer-monitor.sock";
+
+/// $XDG_RUNTIME_DIR/gamer-monitor.sock
+pub fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join(SOCKET_NAME))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // The server sent something that is not a JSON-RPC reply
+    Protocol(String),
+    // The server rejected the request
+    Remote { code: i64, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Protocol(reason) => write!(f, "unexpected reply: {}", reason),
+            Error::Remote { code, message } => write!(f, "{} (error {})", message, code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+pub struct Client {
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: Box<dyn Write + Send>,
+    next_id: u64,
+}
+
+impl Client {
+    pub fn connect() -> Result<Self, Error> {
+        let path = socket_path()
+            .ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::NotFound, "$XDG_RUNTIME_DIR is not set")))?;
+        Self::connect_to(&path)
+    }
+
+    pub fn connect_to(path: &Path) -> Result<Self, Error> {
+        let stream = UnixStream::connect(path)?;
+        Ok(Self::new(Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+
+    /// Connects to an agent. The timeout applies to connecting and to every
+    /// read, so a machine that went away is noticed instead of waited for.
+    pub fn connect_tcp(address: &str, timeout: Duration) -> Result<Self, Error> {
+        let mut last_error = None;
+        for addr in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(timeout))?;
+                    stream.set_write_timeout(Some(timeout))?;
+                    return Ok(Self::new(Box::new(stream.try_clone()?), Box::new(stream)));
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(Error::Io(last_error.unwrap_or_else(|| io::Error::other("no address to connect to"))))
+    }
+
+    fn new(reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 1,
+        }
+    }
+
+    /// {"protocol": 1, "version": "1.2.2"}
+    pub fn hello(&mut self) -> Result<Value, Error> {
+        self.call("hello", Value::Null)
+    }
+
+    /// The same reply, for agents started with a token; other requests are
+    /// refused until the token was sent
+    pub fn authenticate(&mut self, token: &str) -> Result<Value, Error> {
+        self.call("hello", json!({ "token": token }))
+    }
+
+    /// The latest snapshot, as printed by --format json plus its
+    /// "sequence" and "unix_time"
+    pub fn snapshot(&mut self) -> Result<Value, Error> {
+        self.call("snapshot", Value::Null)
+    }
+
+    /// Snapshots and markers kept by the server, optionally limited to a
+    /// range of Unix times: {"snapshots": [...], "markers": [...]}
+    pub fn history(&mut self, since: Option<f64>, until: Option<f64>) -> Result<Value, Error> {
+        self.call("history", json!({ "since": since, "until": until }))
+    }
+
+    /// Marks a moment of the session, e.g. "benchmark started"
+    pub fn add_marker(&mut self, label: &str) -> Result<Value, Error> {
+        self.call("add_marker", json!({ "label": label }))
+    }
+
+    /// Turns the connection into a stream of notifications: one "snapshot"
+    /// per sample and one "marker" per marker added by any client
+    pub fn subscribe(mut self) -> Result<Subscription, Error> {
+        self.call("subscribe", Value::Null)?;
+        Ok(Subscription { client: self })
+    }
+
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut request = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        writeln!(self.writer, "{}", request)?;
+        self.writer.flush()?;
+
+        // Notifications may arrive before the reply once subscribed
+        loop {
+            let message = self.read()?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(Error::Remote {
+                    code: error["code"].as_i64().unwrap_or_default(),
+                    message: error["message"].as_str().unwrap_or_default().to_string(),
+                });
+            }
+            return message
+                .get("result")
+                .cloned()
+                .ok_or_else(|| Error::Protocol("reply without a result".to_string()));
+        }
+    }
+
+    fn read(&mut self) -> Result<Value, Error> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        serde_json::from_str(&line).map_err(|err| Error::Protocol(err.to_string()))
+    }
+}
+
+pub struct Subscription {
+    client: Client,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// "snapshot" or "marker"
+    pub method: String,
+    pub params: Value,
+}
+
+impl Iterator for Subscription {
+    type Item = Result<Notification, Error>;
+
+    // Ends when the server goes away
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let message = match self.client.read() {
+                Ok(message) => message,
+                Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(method) = message.get("method").and_then(Value::as_str) {
+                return Some(Ok(Notification {
+                    method: method.to_string(),
+                    params: message.get("params").cloned().unwrap_or_default(),
+                }));
+            }
+        }
+    }
+}
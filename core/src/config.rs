@@ -0,0 +1,707 @@
+use crate::alerts::{AlertThresholds, Thresholds};
+use crate::bar;
+use crate::units::Units;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CONFIG_DIR: &str = "gamer-monitor";
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub sampling: SamplingConfig,
+    pub sections: Vec<Section>,
+    pub fans: FanConfig,
+    pub units: Units,
+    pub appearance: AppearanceConfig,
+    pub alerts: AlertThresholds,
+    pub logging: LoggingConfig,
+    pub games: GamesConfig,
+    pub history: HistoryConfig,
+    pub exporter: ExporterConfig,
+    pub bar: BarConfig,
+    pub agent: AgentConfig,
+    /// Machines running --agent, each shown in its own tab of the window
+    pub remotes: Vec<RemoteConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// How often each subsystem is read, in seconds. The sections redraw
+/// whenever any of them produces a new snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SamplingConfig {
+    pub cpu_load: u32,
+    pub sensors: u32,
+    pub gpu: u32,
+    pub memory: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    Cpu,
+    Gpu,
+    Storage,
+    Ram,
+    Fans,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FanConfig {
+    pub cpu: String,
+    pub chassis1: String,
+    pub chassis2: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppearanceConfig {
+    pub theme: Theme,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// CSV session log written by the Record button and --log. A new file is
+/// started once the current one reaches either limit; 0 disables a limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Defaults to $XDG_DATA_HOME/gamer-monitor/readings.csv
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub max_size_mb: u64,
+    pub max_age_hours: u64,
+}
+
+/// Optional exporters. The network ones are disabled by default; the D-Bus
+/// service is on, since only the user's own session bus can reach it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExporterConfig {
+    /// Address for the Prometheus /metrics endpoint, e.g. "127.0.0.1:9955"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<String>,
+    /// Address for the web dashboard, e.g. "0.0.0.0:9956"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web: Option<String>,
+    pub dbus: bool,
+    pub socket: SocketConfig,
+    pub push: PushConfig,
+    pub mqtt: MqttConfig,
+}
+
+/// JSON-RPC API on a Unix socket for other local programs. On by default,
+/// like D-Bus: the socket is only accessible to the user. Snapshots and
+/// markers are kept in memory for `history_minutes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketConfig {
+    pub enabled: bool,
+    /// Defaults to $XDG_RUNTIME_DIR/gamer-monitor.sock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub history_minutes: u64,
+}
+
+/// Pushes every snapshot to a time-series database. Lines that cannot be
+/// delivered are kept, up to `buffer` of them, and sent once it is back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PushConfig {
+    /// "udp://host:port" or "tcp://host:port"; pushing is off when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    pub format: PushFormat,
+    pub prefix: String,
+    /// Defaults to the machine's hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    pub tags: BTreeMap<String, String>,
+    pub buffer: usize,
+}
+
+/// Publishes every reading to an MQTT broker under
+/// `<topic_prefix>/<host>/...`, with Home Assistant discovery messages so the
+/// sensors show up there without any YAML.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    /// "host:port" or "mqtt://host:port"; publishing is off when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub topic_prefix: String,
+    /// Defaults to the machine's hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    pub discovery: bool,
+    pub discovery_prefix: String,
+    pub keep_alive: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushFormat {
+    #[default]
+    Influx,
+    Graphite,
+}
+
+/// Status bar module started with --bar. The template's placeholders are
+/// listed in bar.rs; the module's class is the worst severity among them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    pub template: String,
+    /// Waybar only; the other bars have no tooltips
+    pub tooltip: bool,
+}
+
+/// Game detection in the window, --headless and --agent. Each game played
+/// is appended to the session log when it quits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamesConfig {
+    pub detect: bool,
+    /// Defaults to $XDG_DATA_HOME/gamer-monitor/sessions.jsonl
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+/// Optional SQLite store of past readings for the history browser. Raw
+/// samples are kept for raw_hours, per-minute averages for minute_days.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Defaults to $XDG_DATA_HOME/gamer-monitor/history.sqlite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub raw_hours: u32,
+    pub minute_days: u32,
+}
+
+/// Settings for --agent. Without a token anyone who can reach the address
+/// can read the sensors, so --agent only listens beyond the loopback
+/// interface with one, unless `allow_without_token` is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// e.g. "0.0.0.0:7878"; --agent refuses to start without an address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub allow_without_token: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// The tab title; defaults to the address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// host:port of the agent
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid(PathBuf, String),
+    NoConfigDir,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            sampling: SamplingConfig::default(),
+            sections: Section::DEFAULT.to_vec(),
+            fans: FanConfig::default(),
+            units: Units::default(),
+            appearance: AppearanceConfig::default(),
+            alerts: AlertThresholds::default(),
+            logging: LoggingConfig::default(),
+            games: GamesConfig::default(),
+            history: HistoryConfig::default(),
+            exporter: ExporterConfig::default(),
+            bar: BarConfig::default(),
+            agent: AgentConfig::default(),
+            remotes: Vec::new(),
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 600,
+            height: 700,
+        }
+    }
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            cpu_load: 2,
+            sensors: 5,
+            gpu: 5,
+            memory: 5,
+        }
+    }
+}
+
+impl Default for FanConfig {
+    fn default() -> Self {
+        Self {
+            cpu: "CPU fan".to_string(),
+            chassis1: "Chassis fan 1".to_string(),
+            chassis2: "Chassis fan 2".to_string(),
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_size_mb: 50,
+            max_age_hours: 24,
+        }
+    }
+}
+
+impl Default for GamesConfig {
+    fn default() -> Self {
+        Self {
+            detect: true,
+            path: None,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            raw_hours: 48,
+            minute_days: 365,
+        }
+    }
+}
+
+impl Default for ExporterConfig {
+    fn default() -> Self {
+        Self {
+            prometheus: None,
+            web: None,
+            dbus: true,
+            socket: SocketConfig::default(),
+            push: PushConfig::default(),
+            mqtt: MqttConfig::default(),
+        }
+    }
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            history_minutes: 60,
+        }
+    }
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            format: PushFormat::default(),
+            prefix: "gamer_monitor".to_string(),
+            host: None,
+            tags: BTreeMap::new(),
+            buffer: 10_000,
+        }
+    }
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: None,
+            username: None,
+            password: None,
+            topic_prefix: "gamer-monitor".to_string(),
+            host: None,
+            discovery: true,
+            discovery_prefix: "homeassistant".to_string(),
+            keep_alive: 60,
+        }
+    }
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            template: "{cpu_temp}° {gpu_temp}°".to_string(),
+            tooltip: true,
+        }
+    }
+}
+
+impl RemoteConfig {
+    pub fn title(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.address)
+    }
+}
+
+impl SocketConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(crate::client::socket_path)
+    }
+}
+
+impl LoggingConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| Some(data_dir()?.join("readings.csv")))
+    }
+}
+
+impl GamesConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| Some(data_dir()?.join("sessions.jsonl")))
+    }
+}
+
+impl HistoryConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| Some(data_dir()?.join("history.sqlite")))
+    }
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [
+        Section::Cpu,
+        Section::Gpu,
+        Section::Storage,
+        Section::Ram,
+        Section::Fans,
+    ];
+
+    pub const DEFAULT: [Section; 4] = [Section::Cpu, Section::Gpu, Section::Storage, Section::Ram];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Section::Cpu => "cpu",
+            Section::Gpu => "gpu",
+            Section::Storage => "storage",
+            Section::Ram => "ram",
+            Section::Fans => "fans",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Cpu => "CPU",
+            Section::Gpu => "GPU (graphics card)",
+            Section::Storage => "Storage (NVMe)",
+            Section::Ram => "RAM Memory",
+            Section::Fans => "Fans",
+        }
+    }
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Serialize(err) => write!(f, "could not serialize configuration: {}", err),
+            ConfigError::Invalid(path, reason) => write!(f, "{}: {}", path.display(), reason),
+            ConfigError::NoConfigDir => {
+                write!(f, "neither $XDG_CONFIG_HOME nor $HOME is set")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// $XDG_CONFIG_HOME/gamer-monitor/config.toml, falling back to ~/.config
+    pub fn path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(base.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// A missing file is not an error: the defaults reproduce the built-in behavior
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+
+        let config: Config =
+            toml::from_str(&text).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+
+        config
+            .validate()
+            .map_err(|reason| ConfigError::Invalid(path.to_path_buf(), reason))?;
+
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let text = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| ConfigError::Io(dir.to_path_buf(), err))?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a truncated config
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, text).map_err(|err| ConfigError::Io(tmp.clone(), err))?;
+        std::fs::rename(&tmp, path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window.width < 300 || self.window.height < 300 {
+            return Err(format!(
+                "window size must be at least 300×300 (got {}×{})",
+                self.window.width, self.window.height
+            ));
+        }
+
+        let intervals = [
+            ("sampling.cpu_load", self.sampling.cpu_load),
+            ("sampling.sensors", self.sampling.sensors),
+            ("sampling.gpu", self.sampling.gpu),
+            ("sampling.memory", self.sampling.memory),
+        ];
+        for (name, value) in intervals {
+            if !(1..=3600).contains(&value) {
+                return Err(format!(
+                    "{} must be between 1 and 3600 seconds (got {})",
+                    name, value
+                ));
+            }
+        }
+
+        for (i, section) in self.sections.iter().enumerate() {
+            if self.sections[..i].contains(section) {
+                return Err(format!("section \"{}\" is listed more than once", section.key()));
+            }
+        }
+
+        for (name, _, thresholds) in self.alerts.named() {
+            validate_thresholds(name, thresholds)?;
+        }
+
+        bar::check_template(&self.bar.template).map_err(|err| format!("bar.template: {}", err))?;
+
+        if !(5..=3600).contains(&self.exporter.mqtt.keep_alive) {
+            return Err(format!(
+                "exporter.mqtt.keep_alive must be between 5 and 3600 seconds (got {})",
+                self.exporter.mqtt.keep_alive
+            ));
+        }
+        // MQTT 3.1.1 only allows a password together with a user name
+        if self.exporter.mqtt.password.is_some() && self.exporter.mqtt.username.is_none() {
+            return Err("exporter.mqtt.password needs exporter.mqtt.username".to_string());
+        }
+
+        if !(1..=8760).contains(&self.history.raw_hours) {
+            return Err(format!(
+                "history.raw_hours must be between 1 and 8760 (got {})",
+                self.history.raw_hours
+            ));
+        }
+        if !(1..=3650).contains(&self.history.minute_days) {
+            return Err(format!(
+                "history.minute_days must be between 1 and 3650 (got {})",
+                self.history.minute_days
+            ));
+        }
+
+        for remote in &self.remotes {
+            if remote.address.rsplit_once(':').is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err()) {
+                return Err(format!("remotes: invalid address '{}' (expected host:port)", remote.address));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// $XDG_DATA_HOME/gamer-monitor, falling back to ~/.local/share
+pub fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+
+    Some(base.join(CONFIG_DIR))
+}
+
+fn validate_thresholds(name: &str, thresholds: &Thresholds) -> Result<(), String> {
+    let values = [thresholds.warm, thresholds.hot, thresholds.critical];
+    if values.iter().any(|value| !value.is_finite()) {
+        return Err(format!("alerts.{} must only contain finite numbers", name));
+    }
+
+    if thresholds.warm > thresholds.hot || thresholds.hot > thresholds.critical {
+        return Err(format!(
+            "alerts.{} must satisfy warm <= hot <= critical (got {} / {} / {})",
+            name, thresholds.warm, thresholds.hot, thresholds.critical
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gamer-monitor-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn defaults_roundtrip() {
+        let dir = temp_dir("config-roundtrip");
+        let path = dir.join("config.toml");
+        Config::default().save_to(&path).unwrap();
+        assert_eq!(Config::load_from(&path).unwrap(), Config::default());
+        // Written through a temporary file, which is gone once it is renamed
+        assert!(!path.with_extension("toml.tmp").exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let config = Config {
+            sections: vec![Section::Fans, Section::Cpu],
+            remotes: vec![RemoteConfig { name: None, address: "streaming-pc:7878".to_string(), token: Some("secret".to_string()) }],
+            ..Config::default()
+        };
+        config.save_to(&path).unwrap();
+        assert_eq!(Config::load_from(&path).unwrap(), config);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partial_files_use_the_defaults() {
+        let config: Config = toml::from_str("[sampling]\ncpu_load = 5\n").unwrap();
+        assert_eq!(config.sampling.cpu_load, 5);
+        assert_eq!(config.sampling.sensors, SamplingConfig::default().sensors);
+        assert_eq!(config.alerts, AlertThresholds::default());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for text in ["[sampling]\ncpu_lod = 5\n", "[exporter.socket]\nenable = false\n", "colour = \"dark\"\n"] {
+            assert!(toml::from_str::<Config>(text).is_err(), "{}", text);
+        }
+
+        let dir = temp_dir("config-typo");
+        let path = dir.join("config.toml");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "[window]\nwidht = 800\n").unwrap();
+        assert!(matches!(Config::load_from(&path), Err(ConfigError::Parse(..))));
+        std::fs::write(&path, "[window]\nwidth = 100\n").unwrap();
+        assert!(matches!(Config::load_from(&path), Err(ConfigError::Invalid(..))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    type Change = fn(&mut Config);
+
+    #[test]
+    fn validation() {
+        let cases: [(&str, Change); 11] = [
+            ("window size must be at least 300×300 (got 299×200)", |config| config.window = WindowConfig { width: 299, height: 200 }),
+            ("sampling.gpu must be between 1 and 3600 seconds (got 0)", |config| config.sampling.gpu = 0),
+            ("sampling.memory must be between 1 and 3600 seconds (got 3601)", |config| config.sampling.memory = 3601),
+            ("section \"cpu\" is listed more than once", |config| config.sections = vec![Section::Cpu, Section::Gpu, Section::Cpu]),
+            ("alerts.gpu_hotspot must satisfy warm <= hot <= critical (got 105 / 100 / 110)", |config| config.alerts.gpu_hotspot.warm = 105.0),
+            ("alerts.nvme must only contain finite numbers", |config| config.alerts.nvme.critical = f32::NAN),
+            ("exporter.mqtt.keep_alive must be between 5 and 3600 seconds (got 4)", |config| config.exporter.mqtt.keep_alive = 4),
+            ("exporter.mqtt.password needs exporter.mqtt.username", |config| config.exporter.mqtt.password = Some("secret".to_string())),
+            ("history.raw_hours must be between 1 and 8760 (got 0)", |config| config.history.raw_hours = 0),
+            ("history.minute_days must be between 1 and 3650 (got 3651)", |config| config.history.minute_days = 3651),
+            ("remotes: invalid address 'streaming-pc' (expected host:port)", |config| {
+                config.remotes.push(RemoteConfig { name: None, address: "streaming-pc".to_string(), token: None })
+            }),
+        ];
+        assert_eq!(Config::default().validate(), Ok(()));
+        for (expected, change) in cases {
+            let mut config = Config::default();
+            change(&mut config);
+            assert_eq!(config.validate(), Err(expected.to_string()));
+        }
+
+        let mut config = Config::default();
+        config.bar.template = "{cpu_temp".to_string();
+        assert!(config.validate().unwrap_err().starts_with("bar.template: "));
+    }
+}
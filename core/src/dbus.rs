@@ -0,0 +1,352 @@
+use crate::config::Config;
+use crate::metrics::{self, Component, Reading};
+use crate::output::{Format, Output};
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use glib::variant::{DictEntry, ToVariant};
+use glib::{ControlFlow, Variant};
+use gio::{self, BusNameOwnerFlags, BusType, DBusConnection, DBusMethodInvocation, DBusNodeInfo};
+use std::cell::RefCell;
+use std::time::UNIX_EPOCH;
+
+// The service reuses the application id for its bus name, object path and
+// interface, next to the org.gtk.Application interface GTK already exports.
+// Unavailable readings are NaN in the per-metric properties and left out of
+// the readings dictionaries, whose keys are the CSV column names.
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="@INTERFACE@">
+    <method name="GetSnapshot">
+      <arg name="sequence" type="t" direction="out"/>
+      <arg name="timestamp" type="d" direction="out"/>
+      <arg name="readings" type="a{sd}" direction="out"/>
+    </method>
+    <method name="GetSnapshotJson">
+      <arg name="snapshot" type="s" direction="out"/>
+    </method>
+    <method name="GetReading">
+      <arg name="column" type="s" direction="in"/>
+      <arg name="value" type="d" direction="out"/>
+    </method>
+    <signal name="SnapshotUpdated">
+      <arg name="sequence" type="t"/>
+      <arg name="timestamp" type="d"/>
+      <arg name="readings" type="a{sd}"/>
+    </signal>
+    <property name="Version" type="s" access="read"/>
+    <property name="CpuName" type="s" access="read"/>
+    <property name="GpuName" type="s" access="read"/>
+    <property name="CpuTemperatureSensor" type="s" access="read"/>
+    <property name="Sequence" type="t" access="read"/>
+    <property name="Timestamp" type="d" access="read"/>
+    <property name="Readings" type="a{sd}" access="read"/>
+    <property name="CpuTemperature" type="d" access="read"/>
+    <property name="CpuLoad" type="d" access="read"/>
+    <property name="GpuEdge" type="d" access="read"/>
+    <property name="GpuHotspot" type="d" access="read"/>
+    <property name="GpuMemory" type="d" access="read"/>
+    <property name="GpuFan" type="d" access="read"/>
+    <property name="GpuPower" type="d" access="read"/>
+    <property name="GpuLoad" type="d" access="read"/>
+    <property name="GpuVramUsed" type="d" access="read"/>
+    <property name="GpuVramTotal" type="d" access="read"/>
+    <property name="NvmeTemperatures" type="ad" access="read"/>
+    <property name="FanCpu" type="d" access="read"/>
+    <property name="FanChassis1" type="d" access="read"/>
+    <property name="FanChassis2" type="d" access="read"/>
+    <property name="RamTotal" type="d" access="read"/>
+    <property name="RamUsed" type="d" access="read"/>
+    <property name="RamFree" type="d" access="read"/>
+    <property name="RamAvailable" type="d" access="read"/>
+    <property name="RamLoad" type="d" access="read"/>
+  </interface>
+</node>
+"#;
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+
+// e.g. /org/lunx/GamerMonitor
+fn object_path() -> String {
+    format!("/{}", crate::APP_ID.replace('.', "/"))
+}
+
+/// Exports the interface on a connection that already owns the bus name,
+/// which is the case for the window's GApplication
+pub fn register(connection: &DBusConnection, publisher: &Publisher, config: &Config) -> Result<(), glib::Error> {
+    let node = DBusNodeInfo::for_xml(&INTROSPECTION.replace("@INTERFACE@", crate::APP_ID))?;
+    let interface = node
+        .lookup_interface(crate::APP_ID)
+        .ok_or_else(|| glib::Error::new(gio::IOErrorEnum::Failed, "interface missing from introspection data"))?;
+    let path = object_path();
+
+    let methods = {
+        let publisher = publisher.clone();
+        let config = config.clone();
+        move |_: DBusConnection,
+              _: Option<&str>,
+              _: &str,
+              _: Option<&str>,
+              method: &str,
+              parameters: Variant,
+              invocation: DBusMethodInvocation| {
+            call(method, &parameters, invocation, publisher.latest().as_ref(), &config);
+        }
+    };
+    let properties = {
+        let publisher = publisher.clone();
+        let config = config.clone();
+        move |_: DBusConnection, _: Option<&str>, _: &str, _: &str, name: &str| {
+            let snapshot = publisher.latest();
+            let readings = snapshot.as_ref().map(|s| metrics::collect(&s.data, &config.fans)).unwrap_or_default();
+            property(name, snapshot.as_ref(), &readings)
+        }
+    };
+
+    connection
+        .register_object(&path, &interface)
+        .method_call(methods)
+        .property(properties)
+        .build()?;
+
+    // Signals go to every listener, so a failed emit only means the
+    // connection is closing; the subscriber stops then
+    let connection = connection.clone();
+    let fans = config.fans.clone();
+    let previous = RefCell::new(
+        publisher
+            .latest()
+            .map(|snapshot| all_properties(&snapshot, &metrics::collect(&snapshot.data, &fans)))
+            .unwrap_or_default(),
+    );
+    publisher.subscribe(move |snapshot| {
+        let readings = metrics::collect(&snapshot.data, &fans);
+        let current = all_properties(snapshot, &readings);
+
+        // Only what differs from the values clients may have cached
+        let changed: Vec<DictEntry<String, Variant>> = current
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, value))| previous.borrow().get(*i).is_none_or(|(_, before)| before != value))
+            .map(|(_, (name, value))| DictEntry::new(name.to_string(), value.clone()))
+            .collect();
+        *previous.borrow_mut() = current;
+
+        let updated = (snapshot.sequence, seconds(snapshot), available(&readings)).to_variant();
+        let properties = (crate::APP_ID, changed, Vec::<String>::new()).to_variant();
+        let result = connection
+            .emit_signal(None, &path, crate::APP_ID, "SnapshotUpdated", Some(&updated))
+            .and_then(|()| connection.emit_signal(None, &path, PROPERTIES_INTERFACE, "PropertiesChanged", Some(&properties)));
+
+        match result {
+            Ok(()) => ControlFlow::Continue,
+            Err(err) => {
+                log::error!("Stopped updating D-Bus clients: {}", err);
+                ControlFlow::Break
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Owns the bus name for the terminal modes, which have no GApplication.
+/// Losing the name (another instance has it, or there is no session bus) is
+/// reported but not fatal.
+pub fn own_name(publisher: &Publisher, config: &Config) {
+    let publisher = publisher.clone();
+    let config = config.clone();
+
+    // The name is only released when the process exits
+    let _ = gio::bus_own_name(
+        BusType::Session,
+        crate::APP_ID,
+        BusNameOwnerFlags::NONE,
+        move |connection, _| {
+            if let Err(err) = register(&connection, &publisher, &config) {
+                log::error!("Cannot export readings on D-Bus: {}", err);
+            }
+        },
+        |_, name| log::info!("Publishing readings on D-Bus as {}", name),
+        |connection, name| match connection {
+            Some(_) => log::warn!("D-Bus name {} is taken, is another instance running?", name),
+            None => log::warn!("No D-Bus session bus, readings are not published there"),
+        },
+    );
+}
+
+fn call(method: &str, parameters: &Variant, invocation: DBusMethodInvocation, snapshot: Option<&Snapshot>, config: &Config) {
+    let Some(snapshot) = snapshot else {
+        invocation.return_dbus_error("org.freedesktop.DBus.Error.Failed", "No readings yet");
+        return;
+    };
+    let readings = metrics::collect(&snapshot.data, &config.fans);
+
+    match method {
+        "GetSnapshot" => {
+            let reply = (snapshot.sequence, seconds(snapshot), available(&readings)).to_variant();
+            invocation.return_value(Some(&reply));
+        }
+        "GetSnapshotJson" => {
+            let json = Output::new(Format::Json, config).render(snapshot);
+            invocation.return_value(Some(&(json.trim_end(),).to_variant()));
+        }
+        "GetReading" => {
+            let Some((column,)) = parameters.get::<(String,)>() else {
+                invocation.return_dbus_error(INVALID_ARGS, "Expected a column name");
+                return;
+            };
+            match readings.iter().find(|r| r.column() == column) {
+                Some(reading) => {
+                    let value = reading.value.unwrap_or(f64::NAN);
+                    invocation.return_value(Some(&(value,).to_variant()));
+                }
+                None => invocation.return_dbus_error(INVALID_ARGS, &format!("No reading named {}", column)),
+            }
+        }
+        _ => invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", &format!("No method {}", method)),
+    }
+}
+
+// In introspection order, for PropertiesChanged
+const PROPERTY_NAMES: [&str; 26] = [
+    "Version",
+    "CpuName",
+    "GpuName",
+    "CpuTemperatureSensor",
+    "Sequence",
+    "Timestamp",
+    "Readings",
+    "CpuTemperature",
+    "CpuLoad",
+    "GpuEdge",
+    "GpuHotspot",
+    "GpuMemory",
+    "GpuFan",
+    "GpuPower",
+    "GpuLoad",
+    "GpuVramUsed",
+    "GpuVramTotal",
+    "NvmeTemperatures",
+    "FanCpu",
+    "FanChassis1",
+    "FanChassis2",
+    "RamTotal",
+    "RamUsed",
+    "RamFree",
+    "RamAvailable",
+    "RamLoad",
+];
+
+fn all_properties(snapshot: &Snapshot, readings: &[Reading]) -> Vec<(&'static str, Variant)> {
+    PROPERTY_NAMES
+        .iter()
+        .map(|&name| (name, property(name, Some(snapshot), readings)))
+        .collect()
+}
+
+// GDBus only asks for properties listed in the introspection data
+fn property(name: &str, snapshot: Option<&Snapshot>, readings: &[Reading]) -> Variant {
+    let data = snapshot.map(|s| &s.data);
+
+    match name {
+        "Version" => env!("CARGO_PKG_VERSION").to_variant(),
+        "CpuName" => data.map(|d| d.cpu_name.as_str()).unwrap_or_default().to_variant(),
+        "GpuName" => data.map(|d| d.gpu_name.as_str()).unwrap_or_default().to_variant(),
+        "CpuTemperatureSensor" => data.map(|d| d.cpu_temp_sensor.as_str()).unwrap_or_default().to_variant(),
+        "Sequence" => snapshot.map(|s| s.sequence).unwrap_or_default().to_variant(),
+        "Timestamp" => snapshot.map(seconds).unwrap_or_default().to_variant(),
+        "Readings" => available(readings).to_variant(),
+        "NvmeTemperatures" => readings
+            .iter()
+            .filter(|r| r.component == Component::Nvme)
+            .map(|r| r.value.unwrap_or(f64::NAN))
+            .collect::<Vec<f64>>()
+            .to_variant(),
+        _ => readings
+            .iter()
+            .find(|r| camel_case(&r.key) == name)
+            .and_then(|r| r.value)
+            .unwrap_or(f64::NAN)
+            .to_variant(),
+    }
+}
+
+// Readings that have a value, keyed by column name
+fn available(readings: &[Reading]) -> Vec<DictEntry<String, f64>> {
+    readings
+        .iter()
+        .filter_map(|r| Some(DictEntry::new(r.column(), r.value?)))
+        .collect()
+}
+
+fn seconds(snapshot: &Snapshot) -> f64 {
+    snapshot
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+// gpu_vram_used -> GpuVramUsed, fan_chassis1 -> FanChassis1
+fn camel_case(key: &str) -> String {
+    key.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FanConfig;
+    use crate::sensors::SensorData;
+
+    // Properties that are not one of metrics::collect's readings
+    const OWN: [&str; 8] = [
+        "Version",
+        "CpuName",
+        "GpuName",
+        "CpuTemperatureSensor",
+        "Sequence",
+        "Timestamp",
+        "Readings",
+        "NvmeTemperatures",
+    ];
+
+    #[test]
+    fn properties_match_readings() {
+        let readings = metrics::collect(&SensorData::new(), &FanConfig::default());
+        for name in PROPERTY_NAMES.iter().filter(|name| !OWN.contains(name)) {
+            assert!(readings.iter().any(|r| camel_case(&r.key) == *name), "{} is not a reading", name);
+        }
+
+        // Every reading but the NVMe drives, which share one array, has its own property
+        for reading in readings.iter().filter(|r| r.component != Component::Nvme) {
+            let name = camel_case(&reading.key);
+            assert!(PROPERTY_NAMES.contains(&name.as_str()), "{} has no property", reading.key);
+        }
+    }
+
+    #[test]
+    fn properties_are_introspected_in_order() {
+        let introspected: Vec<&str> = INTROSPECTION
+            .split("<property name=\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .collect();
+        assert_eq!(introspected, PROPERTY_NAMES);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(camel_case("gpu_vram_used"), "GpuVramUsed");
+        assert_eq!(camel_case("fan_chassis1"), "FanChassis1");
+        assert_eq!(camel_case("cpu_temperature"), "CpuTemperature");
+    }
+}
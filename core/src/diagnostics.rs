@@ -0,0 +1,431 @@
+use crate::capture::{self, Output, Source};
+use crate::sensors::SensorData;
+use std::io;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How the last read of an input went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    NotInstalled,
+    PermissionDenied,
+    TimedOut,
+    /// Could not be read, or the program exited with an error
+    Failed,
+    /// Read fine, but no chip there provides the reading
+    NoMatchingChip,
+    /// The chip is there, but the reading was not found in what it reported
+    ParseFailed,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::NotInstalled => "Not installed",
+            Status::PermissionDenied => "Permission denied",
+            Status::TimedOut => "Timed out",
+            Status::Failed => "Failed",
+            Status::NoMatchingChip => "No matching chip",
+            Status::ParseFailed => "Parse failed",
+        }
+    }
+
+    fn of(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Status::NotInstalled,
+            io::ErrorKind::PermissionDenied => Status::PermissionDenied,
+            io::ErrorKind::TimedOut => Status::TimedOut,
+            _ => Status::Failed,
+        }
+    }
+}
+
+/// The last read of one file or command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub input: String,
+    pub status: Status,
+    /// Why it failed, None when it worked
+    pub error: Option<String>,
+    /// What a program printed to standard error, which `sensors` also
+    /// does for chips it only partly reads
+    pub stderr: String,
+    pub time: SystemTime,
+    pub duration: Duration,
+}
+
+/// The last check of every input the sensors were read from, in the order
+/// they were first read
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    checks: Vec<Check>,
+}
+
+impl Diagnostics {
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// A source that reads through `source` and records every read here
+    pub fn track<'a>(&'a mut self, source: &'a mut dyn Source) -> Tracked<'a> {
+        Tracked { source, diagnostics: self }
+    }
+
+    fn record(&mut self, check: Check) {
+        match self.checks.iter_mut().find(|existing| existing.input == check.input) {
+            Some(existing) => *existing = check,
+            None => self.checks.push(check),
+        }
+    }
+}
+
+pub struct Tracked<'a> {
+    source: &'a mut dyn Source,
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl Source for Tracked<'_> {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        let started = Instant::now();
+        let result = self.source.read_file(path);
+        let (status, error) = match &result {
+            Ok(_) => (Status::Ok, None),
+            // A missing file is not a missing program
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (Status::Failed, Some(err.to_string())),
+            Err(err) => (Status::of(err), Some(err.to_string())),
+        };
+        self.diagnostics.record(Check {
+            input: path.to_string(),
+            status,
+            error,
+            stderr: String::new(),
+            time: SystemTime::now(),
+            duration: started.elapsed(),
+        });
+        result
+    }
+
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+        let started = Instant::now();
+        let result = self.source.run(program, args);
+        let (status, error, stderr) = match &result {
+            Ok(output) if output.success => (Status::Ok, None, output.stderr.clone()),
+            Ok(output) => (Status::Failed, Some(exit_error(output)), output.stderr.clone()),
+            Err(err) => (Status::of(err), Some(err.to_string()), String::new()),
+        };
+        self.diagnostics.record(Check {
+            input: capture::command_line(program, args),
+            status,
+            error,
+            stderr,
+            time: SystemTime::now(),
+            duration: started.elapsed(),
+        });
+        result
+    }
+}
+
+// The exit status, and the last thing the program complained about
+fn exit_error(output: &Output) -> String {
+    let status = match output.code {
+        Some(code) => format!("exited with status {}", code),
+        None => "was killed by a signal".to_string(),
+    };
+    // nvidia-smi explains itself on standard output
+    let message = [&output.stderr, &output.stdout]
+        .into_iter()
+        .find_map(|text| text.lines().rev().map(str::trim).find(|line| !line.is_empty()));
+    match message {
+        Some(message) => format!("{}: {}", status, message),
+        None => status,
+    }
+}
+
+// The setup steps from the README and build-install.sh
+const INSTALL_LM_SENSORS: &str = "Install lm-sensors (sudo apt install lm-sensors on Ubuntu/Debian, sudo dnf install lm-sensors on Fedora, sudo pacman -S lm_sensors on Arch), then run sudo sensors-detect --auto.";
+const SENSORS_DETECT: &str = "Run sudo sensors-detect --auto and then sudo systemctl restart kmod, so the chips it finds are loaded.";
+const REPORT: &str = "The value was not found where it was expected. Please report it with a capture (gamer-monitor --capture capture.jsonl), as described under \"Reporting a wrong or missing reading\" in the README.";
+
+/// What is known about one group of readings: where it comes from, and
+/// when it is blank, why and what to do about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub reading: &'static str,
+    pub source: String,
+    pub status: Status,
+    pub error: Option<String>,
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(reading: &'static str, source: String) -> Self {
+        Self { reading, source, status: Status::Ok, error: None, hint: None }
+    }
+
+    fn problem(reading: &'static str, source: String, status: Status, error: String, hint: &str) -> Self {
+        Self { reading, source, status, error: Some(error), hint: Some(hint.to_string()) }
+    }
+
+    // The input itself could not be read
+    fn failed(reading: &'static str, check: &Check) -> Self {
+        let program = check.input.split(' ').next().unwrap_or_default();
+        Self {
+            reading,
+            source: check.input.clone(),
+            status: check.status,
+            error: check.error.clone(),
+            hint: hint(program, check.status).map(str::to_string),
+        }
+    }
+}
+
+fn hint(program: &str, status: Status) -> Option<&'static str> {
+    Some(match (program, status) {
+        ("sensors", Status::NotInstalled) => INSTALL_LM_SENSORS,
+        ("sensors", Status::Failed) => SENSORS_DETECT,
+        ("lspci", Status::NotInstalled) => "Install pciutils (sudo apt install pciutils, sudo dnf install pciutils or sudo pacman -S pciutils).",
+        ("nvidia-smi", Status::NotInstalled) => "nvidia-smi comes with the proprietary NVIDIA driver: the nvidia-driver package on Ubuntu/Debian, xorg-x11-drv-nvidia-cuda from RPM Fusion on Fedora, nvidia-utils on Arch.",
+        ("nvidia-smi", Status::Failed) => "nvidia-smi cannot reach the driver. Check that the nvidia module is loaded (lsmod | grep nvidia), and reboot after a driver update.",
+        ("nvidia-smi", Status::TimedOut) => "Persistence mode (sudo nvidia-smi -pm 1) keeps the driver ready, so nvidia-smi answers quickly.",
+        (_, Status::TimedOut) => "It is tried again every minute. Run it in a terminal to see where it hangs.",
+        (_, Status::PermissionDenied) => "Gamer Monitor runs as your user, who is not allowed to read it. Check its permissions.",
+        _ => return None,
+    })
+}
+
+/// Explains each group of readings of this computer from the checks of the
+/// inputs they were read from and what was parsed out of them. Inputs that
+/// were never read, and readings this hardware does not have (e.g. NVIDIA
+/// readings with a Radeon card), are left out.
+pub fn explain(data: &SensorData, checks: &[Check]) -> Vec<Finding> {
+    let find = |prefix: &str| checks.iter().find(|check| check.input.starts_with(prefix));
+    let chip = |prefixes: &[&str]| {
+        data.chips.iter().find(|chip| prefixes.iter().any(|prefix| chip.starts_with(prefix))).cloned()
+    };
+    let mut findings = Vec::new();
+
+    if let Some(check) = find("/proc/cpuinfo") {
+        findings.push(match check.status {
+            Status::Ok if data.cpu_name.is_empty() => Finding::problem("CPU model", check.input.clone(), Status::ParseFailed, "no \"model name\" line".to_string(), REPORT),
+            Status::Ok => Finding::ok("CPU model", check.input.clone()),
+            _ => Finding::failed("CPU model", check),
+        });
+    }
+
+    if let Some(check) = find("/proc/stat") {
+        findings.push(match check.status {
+            Status::Ok => Finding::ok("CPU load", check.input.clone()),
+            _ => Finding::failed("CPU load", check),
+        });
+    }
+
+    // sensors fails when a single chip cannot be read, but still lists the others
+    let sensors = find("sensors").map(|check| match check.status {
+        Status::Failed if !data.chips.is_empty() => (check, Status::Ok),
+        status => (check, status),
+    });
+    if let Some((check, status)) = sensors {
+        let cpu_chip = chip(&["k10temp", "zenpower", "coretemp"]);
+        findings.push(match (status, cpu_chip) {
+            (Status::Ok, Some(chip)) if !data.cpu_temp.is_empty() => {
+                Finding::ok("CPU temperature", format!("sensors: {} of {}", data.cpu_temp_sensor, chip))
+            }
+            (Status::Ok, Some(chip)) => Finding::problem(
+                "CPU temperature",
+                format!("sensors: {}", chip),
+                Status::ParseFailed,
+                format!("{} is listed without Tctl, Tdie, Package id 0 or Core 0", chip),
+                REPORT,
+            ),
+            (Status::Ok, None) => Finding::problem(
+                "CPU temperature",
+                "sensors".to_string(),
+                Status::NoMatchingChip,
+                "sensors lists no k10temp, zenpower or coretemp chip".to_string(),
+                "Load the CPU's temperature driver with sudo modprobe k10temp (AMD) or sudo modprobe coretemp (Intel), or let sudo sensors-detect --auto find it.",
+            ),
+            _ => Finding::failed("CPU temperature", check),
+        });
+    }
+
+    let name_check = find("nvidia-smi --query-gpu=name").filter(|check| check.status == Status::Ok);
+    if let Some(check) = name_check.or_else(|| find("lspci")) {
+        findings.push(match check.status {
+            Status::Ok if data.gpu_name == "Unknown GPU" => Finding::problem(
+                "GPU model",
+                check.input.clone(),
+                Status::ParseFailed,
+                "lspci lists no graphics card Gamer Monitor recognizes".to_string(),
+                "Check which card and driver lspci -k | grep -A 3 VGA shows, and report it with a capture (gamer-monitor --capture capture.jsonl).",
+            ),
+            Status::Ok => Finding::ok("GPU model", check.input.clone()),
+            _ => Finding::failed("GPU model", check),
+        });
+    }
+
+    if data.gpu_name.to_lowercase().contains("nvidia")
+        && let Some(check) = find("nvidia-smi --query-gpu=temperature")
+    {
+        findings.push(match check.status {
+            Status::Ok if data.gpu_edge.is_empty() => Finding::problem(
+                "NVIDIA GPU readings",
+                "nvidia-smi".to_string(),
+                Status::ParseFailed,
+                "nvidia-smi printed no temperature".to_string(),
+                REPORT,
+            ),
+            Status::Ok => Finding::ok("NVIDIA GPU readings", "nvidia-smi".to_string()),
+            _ => Finding::failed("NVIDIA GPU readings", check),
+        });
+    }
+
+    if data.gpu_name.contains("Radeon")
+        && let Some((check, status)) = sensors
+    {
+        let amdgpu = chip(&["amdgpu"]);
+        findings.push(match (status, amdgpu) {
+            (Status::Ok, Some(chip)) if data.gpu_edge.is_empty() && data.gpu_hotspot.is_empty() => Finding::problem(
+                "Radeon temperatures",
+                format!("sensors: {}", chip),
+                Status::ParseFailed,
+                format!("{} is listed without edge or junction temperatures", chip),
+                REPORT,
+            ),
+            (Status::Ok, Some(chip)) => Finding::ok("Radeon temperatures", format!("sensors: {}", chip)),
+            (Status::Ok, None) => Finding::problem(
+                "Radeon temperatures",
+                "sensors".to_string(),
+                Status::NoMatchingChip,
+                "sensors lists no amdgpu chip".to_string(),
+                "These come from the amdgpu driver. Check that lspci -k | grep -A 3 VGA shows \"Kernel driver in use: amdgpu\" for the card.",
+            ),
+            _ => Finding::failed("Radeon temperatures", check),
+        });
+    }
+
+    if let Some((check, status)) = sensors {
+        let drives: Vec<&str> = data.chips.iter().filter(|chip| chip.starts_with("nvme")).map(String::as_str).collect();
+        findings.push(match status {
+            Status::Ok if !data.nvme_temps.is_empty() => Finding::ok("NVMe temperatures", format!("sensors: {}", drives.join(", "))),
+            Status::Ok if !drives.is_empty() => Finding::problem(
+                "NVMe temperatures",
+                format!("sensors: {}", drives.join(", ")),
+                Status::ParseFailed,
+                "the drives are listed without a Composite temperature".to_string(),
+                REPORT,
+            ),
+            Status::Ok => Finding::problem(
+                "NVMe temperatures",
+                "sensors".to_string(),
+                Status::NoMatchingChip,
+                "sensors lists no nvme chip".to_string(),
+                "Expected without an NVMe drive. Otherwise, drive temperatures need Linux 5.5 or newer.",
+            ),
+            _ => Finding::failed("NVMe temperatures", check),
+        });
+
+        let board = chip(&["nct6", "it8"]);
+        let fans = [&data.cpu_fan, &data.chassis_fan1, &data.chassis_fan2];
+        findings.push(match (status, board) {
+            (Status::Ok, Some(chip)) if fans.iter().all(|fan| fan.is_empty()) => Finding::problem(
+                "Fans",
+                format!("sensors: {}", chip),
+                Status::ParseFailed,
+                format!("{} is listed without fan1, fan2 or fan3 speeds", chip),
+                REPORT,
+            ),
+            (Status::Ok, Some(chip)) => Finding::ok("Fans", format!("sensors: {}", chip)),
+            (Status::Ok, None) if fans.iter().any(|fan| !fan.is_empty()) => Finding::ok("Fans", "sensors".to_string()),
+            (Status::Ok, None) => Finding::problem(
+                "Fans",
+                "sensors".to_string(),
+                Status::NoMatchingChip,
+                "sensors lists no motherboard chip (nct67xx or it87)".to_string(),
+                "On ASUS and other boards with a Nuvoton chip, load its driver with sudo modprobe nct6775, and keep it loaded with echo nct6775 | sudo tee /etc/modules-load.d/sensors.conf. Otherwise run sudo sensors-detect --auto. Laptops usually do not expose their fans.",
+            ),
+            _ => Finding::failed("Fans", check),
+        });
+    }
+
+    if let Some(check) = find("free") {
+        findings.push(match check.status {
+            Status::Ok if data.ram_total == 0.0 => Finding::problem("Memory", check.input.clone(), Status::ParseFailed, "no \"Mem:\" line".to_string(), REPORT),
+            Status::Ok => Finding::ok("Memory", check.input.clone()),
+            _ => Finding::failed("Memory", check),
+        });
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, status: Status) -> Check {
+        Check {
+            input: input.to_string(),
+            status,
+            error: (status != Status::Ok).then(|| format!("{} failed", input)),
+            stderr: String::new(),
+            time: SystemTime::now(),
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn finding<'a>(findings: &'a [Finding], reading: &str) -> Option<&'a Finding> {
+        findings.iter().find(|finding| finding.reading == reading)
+    }
+
+    #[test]
+    fn missing_chips_and_unparsed_chips() {
+        let mut data = SensorData::new();
+        data.gpu_name = "Radeon RX 7900 XT/7900 XTX/7900M".into();
+        data.chips = vec!["k10temp-pci-00c3".into(), "acpitz-acpi-0".into()];
+        let findings = explain(&data, &[check("sensors", Status::Ok)]);
+
+        let cpu = finding(&findings, "CPU temperature").unwrap();
+        assert_eq!((cpu.status, cpu.source.as_str()), (Status::ParseFailed, "sensors: k10temp-pci-00c3"));
+        for reading in ["Radeon temperatures", "NVMe temperatures", "Fans"] {
+            assert_eq!(finding(&findings, reading).unwrap().status, Status::NoMatchingChip, "{}", reading);
+        }
+        assert!(finding(&findings, "Fans").unwrap().hint.as_ref().unwrap().contains("modprobe nct6775"));
+        // Inputs that were never read are left out
+        assert!(finding(&findings, "Memory").is_none());
+    }
+
+    #[test]
+    fn missing_programs() {
+        let mut data = SensorData::new();
+        data.gpu_name = "Unknown GPU".into();
+        let checks = [
+            check("sensors", Status::NotInstalled),
+            check("nvidia-smi --query-gpu=name --format=csv,noheader", Status::NotInstalled),
+            check("lspci", Status::NotInstalled),
+        ];
+        let findings = explain(&data, &checks);
+
+        let cpu = finding(&findings, "CPU temperature").unwrap();
+        assert_eq!(cpu.status, Status::NotInstalled);
+        assert_eq!(cpu.hint.as_deref(), Some(INSTALL_LM_SENSORS));
+        let gpu = finding(&findings, "GPU model").unwrap();
+        assert_eq!((gpu.status, gpu.source.as_str()), (Status::NotInstalled, "lspci"));
+        assert!(gpu.hint.as_ref().unwrap().contains("pciutils"));
+    }
+
+    #[test]
+    fn sensors_failing_on_one_chip() {
+        let mut data = SensorData::new();
+        data.cpu_temp = "41.0".into();
+        data.cpu_temp_sensor = "Package id 0".into();
+        data.cpu_fan = "987 RPM".into();
+        data.nvme_temps = vec!["36.9".into()];
+        data.chips = vec!["coretemp-isa-0000".into(), "it8689-isa-0a40".into(), "nvme-pci-0200".into()];
+        let findings = explain(&data, &[check("sensors", Status::Failed)]);
+
+        assert!(findings.iter().all(|finding| finding.status == Status::Ok), "{:?}", findings);
+        assert_eq!(finding(&findings, "CPU temperature").unwrap().source, "sensors: Package id 0 of coretemp-isa-0000");
+        assert_eq!(finding(&findings, "Fans").unwrap().source, "sensors: it8689-isa-0a40");
+    }
+}
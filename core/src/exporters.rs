@@ -0,0 +1,121 @@
+use crate::config::Config;
+use crate::dbus;
+use crate::history::Store;
+use crate::mqtt;
+use crate::prometheus;
+use crate::push;
+use crate::socket;
+use crate::web;
+use crate::scheduler::Publisher;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+/// What the frontend asks for on top of the configuration: each address
+/// overrides the configured one, and the flags let it keep an exporter off
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub prometheus: Option<String>,
+    pub web: Option<String>,
+    pub push: Option<String>,
+    pub mqtt: Option<String>,
+    /// Whether to own the D-Bus name, if the configuration enables it
+    pub dbus_name: bool,
+    /// Whether to listen on the local socket, if the configuration enables it
+    pub socket: bool,
+    /// The address to serve remote monitors on
+    pub agent: Option<String>,
+    /// Overrides agent.token from the configuration
+    pub token: Option<String>,
+}
+
+/// Starts every exporter enabled in the configuration or on the command line.
+/// They all subscribe to the same publisher as the window, so enabling one
+/// never causes extra sensor reads.
+pub fn start(options: &Options, config: &Config, publisher: &Publisher, history: Option<&Store>) -> Result<(), String> {
+    if config.exporter.dbus && options.dbus_name {
+        dbus::own_name(publisher, config);
+    }
+
+    let local_socket = config.exporter.socket.enabled && options.socket;
+    if local_socket || options.agent.is_some() {
+        let server = socket::Server::new(publisher, config, history.cloned());
+
+        // Like D-Bus, the socket is on by default, so a second instance only warns
+        if local_socket && let Some(path) = config.exporter.socket.path() {
+            match server.listen_unix(&path) {
+                Ok(()) => log::info!("Listening for API requests on {}", path.display()),
+                Err(err) => log::warn!("Cannot listen on {}: {}", path.display(), err),
+            }
+        }
+
+        if let Some(address) = &options.agent {
+            let token = options.token.clone().or_else(|| config.agent.token.clone());
+            if token.is_none() && !is_loopback(address) {
+                if !config.agent.allow_without_token {
+                    return Err(format!(
+                        "refusing to serve readings on {} without a token: set --token or agent.token, listen on 127.0.0.1, or set agent.allow_without_token = true",
+                        address
+                    ));
+                }
+                log::warn!("No agent token is set, anyone who can reach {} can read the sensors", address);
+            }
+            let bound = server
+                .listen_tcp(address, token)
+                .map_err(|err| format!("cannot listen on {}: {}", address, err))?;
+            log::info!("Serving readings to remote monitors on {}", bound);
+        }
+    }
+
+    if let Some(address) = options.prometheus.as_ref().or(config.exporter.prometheus.as_ref()) {
+        let bound = prometheus::start(address, publisher, config)
+            .map_err(|err| format!("cannot serve metrics on {}: {}", address, err))?;
+        log::info!("Serving metrics on http://{}/metrics", bound);
+    }
+
+    if let Some(address) = options.web.as_ref().or(config.exporter.web.as_ref()) {
+        let bound = web::start(address, publisher, config)
+            .map_err(|err| format!("cannot serve the dashboard on {}: {}", address, err))?;
+        log::info!("Serving the dashboard on http://{}/", bound);
+    }
+
+    if let Some(endpoint) = options.push.as_ref().or(config.exporter.push.endpoint.as_ref()) {
+        push::start(endpoint, config, publisher)?;
+        log::info!("Pushing readings to {}", endpoint);
+    }
+
+    if let Some(broker) = options.mqtt.as_ref().or(config.exporter.mqtt.broker.as_ref()) {
+        mqtt::start(broker, config, publisher)?;
+        log::info!("Publishing readings to MQTT broker {}", broker);
+    }
+
+    Ok(())
+}
+
+// Whether every address the name resolves to is on this machine only
+fn is_loopback(address: &str) -> bool {
+    let Ok(addresses) = address.to_socket_addrs() else {
+        return false;
+    };
+    let addresses: Vec<SocketAddr> = addresses.collect();
+    !addresses.is_empty() && addresses.iter().all(|address| address.ip().is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_addresses() {
+        for (address, expected) in [
+            ("127.0.0.1:7878", true),
+            ("127.0.0.2:7878", true),
+            ("[::1]:7878", true),
+            ("localhost:7878", true),
+            ("0.0.0.0:7878", false),
+            ("[::]:7878", false),
+            ("192.168.1.20:7878", false),
+            ("127.0.0.1", false),
+        ] {
+            assert_eq!(is_loopback(address), expected, "{}", address);
+        }
+    }
+}
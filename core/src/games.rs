@@ -0,0 +1,376 @@
+use crate::processes::Process;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Windows programs every Wine prefix runs, which are never the game
+const WINE_HELPERS: [&str; 18] = [
+    "start.exe",
+    "services.exe",
+    "winedevice.exe",
+    "plugplay.exe",
+    "svchost.exe",
+    "explorer.exe",
+    "rpcss.exe",
+    "conhost.exe",
+    "wineboot.exe",
+    "winemenubuilder.exe",
+    "rundll32.exe",
+    "tabtip.exe",
+    "steam.exe",
+    "steamwebhelper.exe",
+    "steamerrorreporter.exe",
+    "crashpad_handler.exe",
+    "unitycrashhandler64.exe",
+    "easyanticheat_eos_setup.exe",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Launcher {
+    Steam,
+    Lutris,
+    Heroic,
+    Wine,
+    Gamescope,
+}
+
+impl Launcher {
+    pub fn name(self) -> &'static str {
+        match self {
+            Launcher::Steam => "Steam",
+            Launcher::Lutris => "Lutris",
+            Launcher::Heroic => "Heroic",
+            Launcher::Wine => "Wine",
+            Launcher::Gamescope => "gamescope",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub name: String,
+    pub launcher: Launcher,
+    /// The process whose exit ends the game: Steam's reaper, the Lutris
+    /// wrapper, the .exe or gamescope
+    pub pid: u32,
+}
+
+/// Finds the running game among the processes. Steam and Lutris name the
+/// game themselves; for everything else the .exe or the program gamescope
+/// wraps is the name.
+#[derive(Default)]
+pub struct Detector {
+    // Steam app names by AppId, read once from the app manifests
+    steam_names: HashMap<u32, Option<String>>,
+    current: Option<Game>,
+}
+
+impl Detector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn detect(&mut self, processes: &[Process]) -> Option<Game> {
+        // Keep following the same game while it runs, even if a second one
+        // (or its launcher) starts next to it
+        if let Some(game) = &self.current
+            && processes.iter().any(|process| process.pid == game.pid)
+        {
+            return self.current.clone();
+        }
+
+        let by_pid: HashMap<u32, &Process> = processes.iter().map(|process| (process.pid, process)).collect();
+        let game = processes
+            .iter()
+            .find_map(|process| self.steam(process))
+            .or_else(|| processes.iter().find_map(lutris))
+            .or_else(|| processes.iter().find_map(|process| windows_game(process, &by_pid)))
+            .or_else(|| processes.iter().find_map(gamescope));
+
+        self.current = game.clone();
+        game
+    }
+
+    // reaper SteamLaunch AppId=1091500 -- …
+    fn steam(&mut self, process: &Process) -> Option<Game> {
+        if !process.command.contains("SteamLaunch") {
+            return None;
+        }
+        let app_id: u32 = process
+            .command
+            .split_whitespace()
+            .find_map(|arg| arg.strip_prefix("AppId="))?
+            .parse()
+            .ok()?;
+        // Steam's own tools (the runtime, Proton) launch through reaper too
+        // but with AppId 0 or none at all
+        if app_id == 0 {
+            return None;
+        }
+
+        let name = self
+            .steam_names
+            .entry(app_id)
+            .or_insert_with(|| steam_app_name(app_id))
+            .clone()
+            .unwrap_or_else(|| format!("Steam app {}", app_id));
+        Some(Game { name, launcher: Launcher::Steam, pid: process.pid })
+    }
+}
+
+// Lutris runs each game under a wrapper titled "lutris-wrapper: <game>"
+fn lutris(process: &Process) -> Option<Game> {
+    let name = process.command.strip_prefix("lutris-wrapper: ")?.trim();
+    (!name.is_empty()).then(|| Game {
+        name: name.to_string(),
+        launcher: Launcher::Lutris,
+        pid: process.pid,
+    })
+}
+
+// A Windows program under Wine or Proton, attributed to the launcher that
+// started it. Wine replaces the command line with the Windows path, e.g.
+// "C:\Games\Hades\Hades.exe".
+fn windows_game(process: &Process, by_pid: &HashMap<u32, &Process>) -> Option<Game> {
+    let command = &process.command;
+    let bytes = command.as_bytes();
+    let windows_path = bytes.len() > 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\";
+    if !windows_path {
+        return None;
+    }
+    let end = command.to_ascii_lowercase().find(".exe")?;
+    let file = command[..end].rsplit('\\').next()?.trim();
+    if file.is_empty() || WINE_HELPERS.contains(&format!("{}.exe", file).to_lowercase().as_str()) {
+        return None;
+    }
+
+    let launcher = ancestors(process, by_pid)
+        .find_map(|ancestor| {
+            let command = ancestor.command.to_lowercase();
+            if command.contains("heroic") {
+                Some(Launcher::Heroic)
+            } else if command.starts_with("lutris") || command.contains("/lutris ") {
+                Some(Launcher::Lutris)
+            } else if ancestor.name.starts_with("gamescope") {
+                Some(Launcher::Gamescope)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Launcher::Wine);
+
+    Some(Game { name: file.to_string(), launcher, pid: process.pid })
+}
+
+// gamescope … -- <program> …, except a gamescope session wrapping Steam
+fn gamescope(process: &Process) -> Option<Game> {
+    if !process.name.starts_with("gamescope") {
+        return None;
+    }
+    let (_, wrapped) = process.command.split_once(" -- ")?;
+    let program = wrapped.split_whitespace().next()?;
+    let name = Path::new(program).file_name()?.to_string_lossy().to_string();
+    if name == "steam" {
+        return None;
+    }
+    Some(Game { name, launcher: Launcher::Gamescope, pid: process.pid })
+}
+
+fn ancestors<'a>(process: &Process, by_pid: &'a HashMap<u32, &'a Process>) -> impl Iterator<Item = &'a Process> {
+    let mut parent = process.parent;
+    std::iter::from_fn(move || {
+        let ancestor = by_pid.get(&parent).copied()?;
+        // PID 1 is its own root; stop there instead of looping
+        parent = if ancestor.parent == ancestor.pid { 0 } else { ancestor.parent };
+        Some(ancestor)
+    })
+}
+
+// The "name" in steamapps/appmanifest_<id>.acf, looked up in every Steam
+// library of every usual Steam install (native, ~/.steam, Flatpak)
+fn steam_app_name(app_id: u32) -> Option<String> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let roots = [
+        home.join(".local/share/Steam"),
+        home.join(".steam/steam"),
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+    ];
+
+    let mut libraries: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if let Ok(folders) = fs::read_to_string(root.join("steamapps/libraryfolders.vdf")) {
+            libraries.extend(vdf_values(&folders, "path").map(PathBuf::from));
+        }
+        libraries.push(root);
+    }
+
+    libraries.iter().find_map(|library| {
+        let manifest = fs::read_to_string(library.join(format!("steamapps/appmanifest_{}.acf", app_id))).ok()?;
+        vdf_values(&manifest, "name").next().map(str::to_string)
+    })
+}
+
+// Values of a key in Valve's KeyValues text, e.g. "name"		"Hades"
+fn vdf_values<'a>(text: &'a str, key: &'a str) -> impl Iterator<Item = &'a str> {
+    text.lines().filter_map(move |line| {
+        let mut quoted = line.split('"').skip(1).step_by(2);
+        (quoted.next()? == key).then(|| quoted.next()).flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent: u32, name: &str, command: &str) -> Process {
+        Process {
+            pid,
+            parent,
+            name: name.to_string(),
+            command: command.to_string(),
+            cpu_percent: 0.0,
+            memory_bytes: 0,
+            gpu_percent: None,
+            vram_bytes: None,
+        }
+    }
+
+    fn detect(processes: &[Process]) -> Option<(String, Launcher, u32)> {
+        let mut detector = Detector::new();
+        detector.steam_names.insert(1145360, Some("Hades".to_string()));
+        detector.steam_names.insert(1091500, None);
+        detector.detect(processes).map(|game| (game.name, game.launcher, game.pid))
+    }
+
+    #[test]
+    fn launchers() {
+        let steam = process(1000, 1, "steam", "/home/me/.local/share/Steam/ubuntu12_32/steam -srt-logger-opened");
+        let heroic = process(2000, 1, "heroic", "/opt/Heroic/heroic --no-sandbox");
+        let cases = [
+            (
+                "Steam names the game by its AppId",
+                vec![
+                    steam.clone(),
+                    process(1100, 1000, "reaper", "/home/me/.local/share/Steam/ubuntu12_32/reaper SteamLaunch AppId=1145360 -- proton waitforexitandrun Hades.exe"),
+                    process(1200, 1100, "Hades.exe", "C:\\Program Files\\Hades\\x64\\Hades.exe"),
+                ],
+                Some(("Hades", Launcher::Steam, 1100)),
+            ),
+            (
+                "a game without an app manifest",
+                vec![process(1100, 1000, "reaper", "reaper SteamLaunch AppId=1091500 -- ./bin/x64/Cyberpunk2077.exe")],
+                Some(("Steam app 1091500", Launcher::Steam, 1100)),
+            ),
+            (
+                "Steam's own tools run with AppId=0",
+                vec![steam.clone(), process(1100, 1000, "reaper", "reaper SteamLaunch AppId=0 -- steam-runtime-launcher-service")],
+                None,
+            ),
+            (
+                "the Lutris wrapper",
+                vec![
+                    process(3000, 1, "lutris", "/usr/bin/python3 /usr/bin/lutris"),
+                    process(3100, 3000, "lutris-wrapper", "lutris-wrapper: Diablo II: Resurrected"),
+                    process(3200, 3100, "D2R.exe", "C:\\Games\\Diablo II Resurrected\\D2R.exe"),
+                ],
+                Some(("Diablo II: Resurrected", Launcher::Lutris, 3100)),
+            ),
+            (
+                "an .exe started by Heroic",
+                vec![
+                    heroic.clone(),
+                    process(2100, 2000, "wine64-preloader", "/home/me/.config/heroic/tools/proton/GE-Proton9-4/files/bin/wine64 Control.exe"),
+                    process(2200, 2100, "Control.exe", "C:\\Games\\Control\\Control_DX12.EXE -dx12"),
+                ],
+                Some(("Control_DX12", Launcher::Heroic, 2200)),
+            ),
+            (
+                "an .exe inside gamescope",
+                vec![
+                    process(4000, 1, "gamescope", "gamescope -W 2560 -H 1440 -- wine Witcher3.exe"),
+                    process(4100, 4000, "Witcher3.exe", "Z:\\home\\me\\Games\\Witcher 3\\bin\\x64\\Witcher3.exe"),
+                ],
+                Some(("Witcher3", Launcher::Gamescope, 4100)),
+            ),
+            (
+                "plain Wine",
+                vec![process(5000, 1, "Terraria.exe", "C:\\Terraria\\Terraria.exe")],
+                Some(("Terraria", Launcher::Wine, 5000)),
+            ),
+            (
+                "Wine's helpers are never the game",
+                vec![
+                    process(5000, 1, "services.exe", "C:\\windows\\system32\\services.exe"),
+                    process(5001, 1, "explorer.exe", "C:\\windows\\system32\\explorer.exe /desktop"),
+                    process(5002, 1, "steam.exe", "c:\\windows\\system32\\steam.exe"),
+                    process(5003, 1, "winedevice.exe", "C:\\windows\\system32\\winedevice.exe"),
+                ],
+                None,
+            ),
+            (
+                "a native program inside gamescope",
+                vec![process(4000, 1, "gamescope", "gamescope -f -- /usr/games/supertuxkart --fullscreen")],
+                Some(("supertuxkart", Launcher::Gamescope, 4000)),
+            ),
+            (
+                "a gamescope session wrapping Steam",
+                vec![process(4000, 1, "gamescope", "gamescope -e -- steam -gamepadui"), steam.clone()],
+                None,
+            ),
+            ("a Linux path is not an .exe", vec![process(6000, 1, "bash", "/bin/bash ./start.exe.sh")], None),
+        ];
+
+        for (case, processes, expected) in cases {
+            let expected = expected.map(|(name, launcher, pid)| (name.to_string(), launcher, pid));
+            assert_eq!(detect(&processes), expected, "{}", case);
+        }
+    }
+
+    #[test]
+    fn follows_the_game_it_found() {
+        let mut detector = Detector::new();
+        let first = [process(5000, 1, "Terraria.exe", "C:\\Terraria\\Terraria.exe")];
+        assert_eq!(detector.detect(&first).map(|game| game.pid), Some(5000));
+
+        // A second game next to the first does not take over
+        let both = [first[0].clone(), process(5100, 1, "Celeste.exe", "C:\\Celeste\\Celeste.exe")];
+        assert_eq!(detector.detect(&both).map(|game| game.name), Some("Terraria".to_string()));
+
+        let second = [both[1].clone()];
+        assert_eq!(detector.detect(&second).map(|game| game.name), Some("Celeste".to_string()));
+        assert_eq!(detector.detect(&[]), None);
+    }
+
+    #[test]
+    fn ancestors_stop_at_the_root() {
+        let processes = [process(1, 1, "systemd", "/sbin/init"), process(10, 1, "sh", "sh"), process(20, 10, "game", "game")];
+        let by_pid: HashMap<u32, &Process> = processes.iter().map(|process| (process.pid, process)).collect();
+        let pids: Vec<u32> = ancestors(&processes[2], &by_pid).map(|ancestor| ancestor.pid).collect();
+        assert_eq!(pids, [10, 1]);
+    }
+
+    #[test]
+    fn valve_key_values() {
+        let folders = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/me/.local/share/Steam"
+		"label"		""
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+		"apps"
+		{
+			"1145360"		"15291423744"
+		}
+	}
+}"#;
+        assert_eq!(vdf_values(folders, "path").collect::<Vec<_>>(), ["/home/me/.local/share/Steam", "/mnt/games/SteamLibrary"]);
+
+        let manifest = "\"AppState\"\n{\n\t\"appid\"\t\t\"1145360\"\n\t\"name\"\t\t\"Hades\"\n\t\"installdir\"\t\t\"Hades\"\n}";
+        assert_eq!(vdf_values(manifest, "name").next(), Some("Hades"));
+        assert_eq!(vdf_values(manifest, "missing").next(), None);
+    }
+}
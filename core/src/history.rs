@@ -0,0 +1,596 @@
+use crate::alerts::Severity;
+use crate::bar;
+use crate::config::Config;
+use crate::metrics;
+use crate::scheduler::Publisher;
+use crate::sensors::SensorData;
+use crate::sessions::{self, Session};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Old rows are deleted this often, not on every sample
+const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+// Writes waiting for the writer thread. Samples arriving while it is this
+// far behind, e.g. on a locked database, are dropped rather than queued.
+const QUEUE_LENGTH: usize = 64;
+// Placeholders whose alerts are recorded; the rest are names and totals
+const ALERTS: [&str; 13] = [
+    "cpu_temp",
+    "cpu_load",
+    "gpu_temp",
+    "gpu_hotspot",
+    "gpu_memory",
+    "gpu_fan",
+    "gpu_load",
+    "vram_load",
+    "nvme_temp",
+    "cpu_fan",
+    "chassis_fan1",
+    "chassis_fan2",
+    "ram_load",
+];
+
+// Readings are stored in the base units of metrics::collect, keyed by
+// their CSV column name. Two instances recording at once (the window and
+// --headless) write the same rows, so nothing is stored twice.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS series (
+    id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    unit TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS raw (
+    series INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (series, time)
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS minute (
+    series INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    average REAL NOT NULL,
+    minimum REAL NOT NULL,
+    maximum REAL NOT NULL,
+    PRIMARY KEY (series, time)
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS sessions (
+    game TEXT NOT NULL,
+    launcher TEXT NOT NULL,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    stats TEXT NOT NULL,
+    PRIMARY KEY (game, start)
+);
+CREATE TABLE IF NOT EXISTS alerts (
+    time INTEGER NOT NULL,
+    reading TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (reading, time)
+);
+CREATE TABLE IF NOT EXISTS markers (
+    time INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    PRIMARY KEY (time, label)
+);
+";
+
+/// One line of a chart: a reading's values over the requested range
+pub struct Series {
+    pub key: String,
+    pub title: String,
+    /// The unit suffix of the column, e.g. "celsius" or "percent"
+    pub unit: String,
+    pub points: Vec<(i64, f64)>,
+}
+
+pub struct Alert {
+    pub time: i64,
+    pub reading: String,
+    pub severity: String,
+    pub value: String,
+}
+
+// What the writer thread saves
+enum Write {
+    Sample(SystemTime, Box<SensorData>),
+    Sessions(Vec<Session>),
+    Marker(SystemTime, String),
+}
+
+// Owned by the writer thread
+struct Inner {
+    connection: Connection,
+    series: HashMap<String, i64>,
+    // Start of the first minute not yet aggregated
+    aggregated: i64,
+    last_prune: Option<Instant>,
+    raw_seconds: i64,
+    minute_seconds: i64,
+    severities: HashMap<&'static str, Severity>,
+}
+
+/// The optional SQLite store of `[history]`: raw samples for the last few
+/// hours, one row per minute (average, minimum, maximum) for months, and
+/// the game sessions, alerts and markers in between. Shared with the
+/// socket threads, which save markers. Everything is written on a thread
+/// of its own, and read through a second, read-only connection that WAL
+/// lets see the last commit while a write is in progress, so a slow or
+/// locked database never stalls the main loop.
+#[derive(Clone)]
+pub struct Store {
+    reader: Arc<Mutex<Connection>>,
+    raw_seconds: i64,
+    writes: SyncSender<Write>,
+}
+
+impl Store {
+    pub fn open(path: &Path, config: &Config) -> Result<Self, String> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        // Readers (the history browser, a second instance) never block the recorder
+        connection
+            .pragma_update(None, "journal_mode", "wal")
+            .and_then(|()| connection.busy_timeout(Duration::from_secs(5)))
+            .map_err(|err| err.to_string())?;
+        let inner = Inner::new(connection, config).map_err(|err| err.to_string())?;
+        // Opened once the schema exists
+        let reader = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(|err| err.to_string())?;
+
+        let (writes, receiver) = mpsc::sync_channel(QUEUE_LENGTH);
+        let raw_seconds = inner.raw_seconds;
+        let config = config.clone();
+        thread::spawn(move || write(inner, receiver, &config));
+        Ok(Self {
+            reader: Arc::new(Mutex::new(reader)),
+            raw_seconds,
+            writes,
+        })
+    }
+
+    /// Queues a sample for the writer thread
+    pub fn record(&self, time: SystemTime, data: &SensorData) {
+        if let Err(TrySendError::Full(_)) = self.writes.try_send(Write::Sample(time, Box::new(data.clone()))) {
+            log::warn!("The history database is falling behind, a sample was not saved");
+        }
+    }
+
+    /// Sessions are kept for good: they are small and the point is comparing
+    /// games played months apart
+    pub fn add_session(&self, session: &Session) {
+        self.add_sessions(vec![session.clone()]);
+    }
+
+    fn add_sessions(&self, sessions: Vec<Session>) {
+        // Also in the session log, which is imported again on the next start
+        if let Err(TrySendError::Full(_)) = self.writes.try_send(Write::Sessions(sessions)) {
+            log::warn!("The history database is falling behind, a game session was not saved");
+        }
+    }
+
+    /// Waits for room in the queue, so only call it off the main thread
+    pub fn add_marker(&self, time: SystemTime, label: &str) {
+        let _ = self.writes.send(Write::Marker(time, label.to_string()));
+    }
+
+    /// Every reading between from and to (Unix seconds), at most about
+    /// max_points per series. Recent ranges come from the raw samples, older
+    /// or longer ones from the per-minute averages.
+    pub fn series(&self, from: i64, to: i64, max_points: i64) -> rusqlite::Result<Vec<Series>> {
+        let now = unix_seconds(SystemTime::now());
+        read_series(&self.reader.lock().unwrap(), from, to, max_points, now - self.raw_seconds)
+    }
+
+    pub fn sessions(&self, from: i64, to: i64) -> rusqlite::Result<Vec<Session>> {
+        read_sessions(&self.reader.lock().unwrap(), from, to)
+    }
+
+    pub fn markers(&self, from: i64, to: i64) -> rusqlite::Result<Vec<(i64, String)>> {
+        read_markers(&self.reader.lock().unwrap(), from, to)
+    }
+
+    pub fn alerts(&self, from: i64, to: i64) -> rusqlite::Result<Vec<Alert>> {
+        read_alerts(&self.reader.lock().unwrap(), from, to)
+    }
+
+    /// The oldest stored sample, where the history browser's range can start
+    pub fn first_time(&self) -> Option<i64> {
+        self.reader
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT min(time) FROM (SELECT min(time) AS time FROM minute UNION ALL SELECT min(time) FROM raw)",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+            .flatten()
+    }
+}
+
+impl Inner {
+    fn new(connection: Connection, config: &Config) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        let aggregated: Option<i64> = connection.query_row("SELECT max(time) + 60 FROM minute", [], |row| row.get(0))?;
+        Ok(Self {
+            connection,
+            series: HashMap::new(),
+            aggregated: aggregated.unwrap_or(0),
+            last_prune: None,
+            raw_seconds: config.history.raw_hours as i64 * 3600,
+            minute_seconds: config.history.minute_days as i64 * 86_400,
+            severities: HashMap::new(),
+        })
+    }
+}
+
+// Ranges starting at raw_since or later, with buckets under a minute, come
+// from the raw samples
+fn read_series(connection: &Connection, from: i64, to: i64, max_points: i64, raw_since: i64) -> rusqlite::Result<Vec<Series>> {
+    let bucket = ((to - from) / max_points.max(1)).max(1);
+    let raw = from >= raw_since && bucket < 60;
+    let (table, column, bucket) = if raw { ("raw", "value", bucket) } else { ("minute", "average", bucket.max(60)) };
+
+    let mut statement = connection.prepare(&format!(
+        "SELECT s.key, s.title, s.unit, t.time / ?3 * ?3 AS slot, avg(t.{column})
+         FROM {table} t JOIN series s ON s.id = t.series
+         WHERE t.time >= ?1 AND t.time <= ?2
+         GROUP BY s.id, slot ORDER BY s.id, slot"
+    ))?;
+    let mut rows = statement.query(params![from, to, bucket])?;
+
+    let mut series: Vec<Series> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        if series.last().is_none_or(|last| last.key != key) {
+            series.push(Series {
+                key,
+                title: row.get(1)?,
+                unit: row.get(2)?,
+                points: Vec::new(),
+            });
+        }
+        if let Some(last) = series.last_mut() {
+            last.points.push((row.get(3)?, row.get(4)?));
+        }
+    }
+    Ok(series)
+}
+
+fn read_sessions(connection: &Connection, from: i64, to: i64) -> rusqlite::Result<Vec<Session>> {
+    let mut statement = connection.prepare("SELECT stats FROM sessions WHERE end >= ?1 AND start <= ?2 ORDER BY start")?;
+    let sessions = statement
+        .query_map(params![from, to], |row| row.get::<_, String>(0))?
+        .filter_map(|stats| serde_json::from_str(&stats.ok()?).ok())
+        .collect();
+    Ok(sessions)
+}
+
+fn read_markers(connection: &Connection, from: i64, to: i64) -> rusqlite::Result<Vec<(i64, String)>> {
+    let mut statement = connection.prepare("SELECT time, label FROM markers WHERE time >= ?1 AND time <= ?2 ORDER BY time")?;
+    let markers = statement
+        .query_map(params![from, to], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(markers)
+}
+
+fn read_alerts(connection: &Connection, from: i64, to: i64) -> rusqlite::Result<Vec<Alert>> {
+    let mut statement = connection.prepare(
+        "SELECT time, reading, severity, value FROM alerts WHERE time >= ?1 AND time <= ?2 ORDER BY time",
+    )?;
+    let alerts = statement
+        .query_map(params![from, to], |row| {
+            Ok(Alert {
+                time: row.get(0)?,
+                reading: row.get(1)?,
+                severity: row.get(2)?,
+                value: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(alerts)
+}
+
+/// Opens the store when `[history]` is enabled and records every published
+/// snapshot into it. Sessions from before the store existed are imported
+/// from the session log.
+pub fn start(publisher: &Publisher, config: &Config) -> Option<Store> {
+    if !config.history.enabled {
+        return None;
+    }
+    let Some(path) = config.history.path() else {
+        log::warn!("Cannot keep the history: neither $XDG_DATA_HOME nor $HOME is set");
+        return None;
+    };
+    let store = match Store::open(&path, config) {
+        Ok(store) => store,
+        Err(err) => {
+            log::error!("Cannot open the history database {}: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    if let Some(log) = config.games.path()
+        && let Ok(sessions) = sessions::load(&log)
+    {
+        store.add_sessions(sessions);
+    }
+
+    if let Some(snapshot) = publisher.latest() {
+        store.record(snapshot.timestamp, &snapshot.data);
+    }
+    let recorder = store.clone();
+    publisher.subscribe(move |snapshot| {
+        recorder.record(snapshot.timestamp, &snapshot.data);
+        glib::ControlFlow::Continue
+    });
+
+    Some(store)
+}
+
+// Takes the samples, sessions and markers queued by the Store until every
+// copy of it is dropped
+fn write(mut inner: Inner, receiver: Receiver<Write>, config: &Config) {
+    for write in receiver {
+        let (what, result) = match write {
+            Write::Sample(time, data) => ("sample", save_sample(&mut inner, time, &data, config)),
+            Write::Sessions(sessions) => ("game session", save_sessions(&inner, &sessions)),
+            Write::Marker(time, label) => ("marker", save_marker(&inner, time, &label)),
+        };
+        if let Err(err) = result {
+            log::error!("Cannot save the {} in the history: {}", what, err);
+        }
+    }
+}
+
+fn save_sample(inner: &mut Inner, time: SystemTime, data: &SensorData, config: &Config) -> rusqlite::Result<()> {
+    let time = unix_seconds(time);
+    let readings = metrics::collect(data, &config.fans);
+    let transaction = inner.connection.unchecked_transaction()?;
+    for reading in &readings {
+        let Some(value) = reading.value else {
+            continue;
+        };
+        let series = match inner.series.get(&reading.column()) {
+            Some(&id) => id,
+            None => {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO series (key, title, unit) VALUES (?1, ?2, ?3)",
+                    params![reading.column(), series_title(reading), reading.quantity.suffix()],
+                )?;
+                let id = transaction.query_row(
+                    "SELECT id FROM series WHERE key = ?1",
+                    params![reading.column()],
+                    |row| row.get(0),
+                )?;
+                inner.series.insert(reading.column(), id);
+                id
+            }
+        };
+        transaction.execute(
+            "INSERT OR REPLACE INTO raw (series, time, value) VALUES (?1, ?2, ?3)",
+            params![series, time, value],
+        )?;
+    }
+
+    // A reading turning hot or critical is an alert; staying there is not
+    for name in ALERTS {
+        let (value, severity) = bar::field(name, data, config);
+        let previous = inner.severities.insert(name, severity).unwrap_or_default();
+        if severity > previous && severity >= Severity::Hot {
+            transaction.execute(
+                "INSERT OR REPLACE INTO alerts (time, reading, severity, value) VALUES (?1, ?2, ?3, ?4)",
+                params![time, name, severity.key(), value],
+            )?;
+        }
+    }
+
+    // Minutes that are over are averaged into the long-term table
+    let minute = time / 60 * 60;
+    if inner.aggregated == 0 {
+        inner.aggregated = transaction
+            .query_row("SELECT min(time) / 60 * 60 FROM raw", [], |row| row.get::<_, Option<i64>>(0))?
+            .unwrap_or(minute);
+    }
+    if inner.aggregated < minute {
+        transaction.execute(
+            "INSERT OR REPLACE INTO minute (series, time, average, minimum, maximum)
+             SELECT series, time / 60 * 60, avg(value), min(value), max(value)
+             FROM raw WHERE time >= ?1 AND time < ?2 GROUP BY series, time / 60",
+            params![inner.aggregated, minute],
+        )?;
+        inner.aggregated = minute;
+    }
+
+    if inner.last_prune.is_none_or(|last| last.elapsed() >= PRUNE_EVERY) {
+        let raw_cutoff = time - inner.raw_seconds;
+        let cutoff = time - inner.minute_seconds;
+        transaction.execute("DELETE FROM raw WHERE time < ?1", params![raw_cutoff])?;
+        transaction.execute("DELETE FROM minute WHERE time < ?1", params![cutoff])?;
+        transaction.execute("DELETE FROM alerts WHERE time < ?1", params![cutoff])?;
+        transaction.execute("DELETE FROM markers WHERE time < ?1", params![cutoff])?;
+        inner.last_prune = Some(Instant::now());
+    }
+
+    transaction.commit()
+}
+
+fn save_sessions(inner: &Inner, sessions: &[Session]) -> rusqlite::Result<()> {
+    let transaction = inner.connection.unchecked_transaction()?;
+    for session in sessions {
+        let stats = serde_json::to_string(session).unwrap_or_default();
+        transaction.execute(
+            "INSERT OR REPLACE INTO sessions (game, launcher, start, end, stats) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session.game, session.launcher, session.unix_start as i64, session.unix_end as i64, stats],
+        )?;
+    }
+    transaction.commit()
+}
+
+fn save_marker(inner: &Inner, time: SystemTime, label: &str) -> rusqlite::Result<()> {
+    inner.connection.execute(
+        "INSERT OR REPLACE INTO markers (time, label) VALUES (?1, ?2)",
+        params![unix_seconds(time), label],
+    )?;
+    Ok(())
+}
+
+// "CPU Tctl" rather than just "Tctl", since charts mix components
+fn series_title(reading: &metrics::Reading) -> String {
+    let component = match reading.component {
+        metrics::Component::Nvme => "",
+        metrics::Component::Fan => "",
+        component => component.title(),
+    };
+    format!("{} {}", component, reading.title).trim().to_string()
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs() as i64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The start of a minute
+    const T: i64 = 1_700_000_040;
+
+    fn inner() -> (Inner, Config) {
+        let mut config = Config::default();
+        config.history.raw_hours = 1;
+        config.history.minute_days = 1;
+        let inner = Inner::new(Connection::open_in_memory().unwrap(), &config).unwrap();
+        (inner, config)
+    }
+
+    fn save(inner: &mut Inner, config: &Config, time: i64, cpu_temp: f32, cpu_load: f32) {
+        let mut data = SensorData::new();
+        data.cpu_temp = format!("+{:.1}°C", cpu_temp);
+        data.cpu_temp_sensor = "Tctl".to_string();
+        data.cpu_usage = cpu_load;
+        save_sample(inner, UNIX_EPOCH + Duration::from_secs(time as u64), &data, config).unwrap();
+    }
+
+    // (time, average, minimum, maximum) of the CPU load
+    fn minutes(connection: &Connection) -> Vec<(i64, f64, f64, f64)> {
+        let mut statement = connection
+            .prepare(
+                "SELECT m.time, m.average, m.minimum, m.maximum FROM minute m JOIN series s ON s.id = m.series
+                 WHERE s.key = 'cpu_load_percent' ORDER BY m.time",
+            )
+            .unwrap();
+        statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn count(connection: &Connection, table: &str) -> i64 {
+        connection.query_row(&format!("SELECT count(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn minutes_are_aggregated_once_over() {
+        let (mut inner, config) = inner();
+        save(&mut inner, &config, T, 50.0, 10.0);
+        save(&mut inner, &config, T + 30, 50.0, 20.0);
+        save(&mut inner, &config, T + 59, 50.0, 60.0);
+        assert_eq!(minutes(&inner.connection), []);
+
+        save(&mut inner, &config, T + 60, 50.0, 50.0);
+        assert_eq!(minutes(&inner.connection), [(T, 30.0, 10.0, 60.0)]);
+
+        // After a restart, aggregation resumes after the last stored minute
+        let Inner { connection, .. } = inner;
+        let mut inner = Inner::new(connection, &config).unwrap();
+        assert_eq!(inner.aggregated, T + 60);
+        save(&mut inner, &config, T + 125, 50.0, 0.0);
+        assert_eq!(minutes(&inner.connection), [(T, 30.0, 10.0, 60.0), (T + 60, 50.0, 50.0, 50.0)]);
+    }
+
+    #[test]
+    fn retention() {
+        let (mut inner, config) = inner();
+        save(&mut inner, &config, T, 50.0, 10.0);
+        save(&mut inner, &config, T + 30, 50.0, 20.0);
+        save_marker(&inner, UNIX_EPOCH + Duration::from_secs(T as u64), "start").unwrap();
+        let series = inner.series.len() as i64;
+        assert_eq!(count(&inner.connection, "raw"), 2 * series);
+
+        // Raw samples go after raw_hours, the minutes they were averaged into stay
+        inner.last_prune = None;
+        save(&mut inner, &config, T + 3600 + 120, 50.0, 30.0);
+        assert_eq!(count(&inner.connection, "raw"), series);
+        assert_eq!(minutes(&inner.connection).len(), 1);
+        assert_eq!(count(&inner.connection, "markers"), 1);
+
+        // Pruning waits for PRUNE_EVERY
+        save(&mut inner, &config, T + 86_400 + 300, 50.0, 30.0);
+        assert_eq!(count(&inner.connection, "raw"), 2 * series);
+
+        inner.last_prune = None;
+        save(&mut inner, &config, T + 86_400 + 360, 50.0, 30.0);
+        assert_eq!(count(&inner.connection, "raw"), 2 * series);
+        assert_eq!(minutes(&inner.connection).iter().map(|minute| minute.0).collect::<Vec<_>>(), [T + 3720, T + 86_400 + 300]);
+        assert_eq!(count(&inner.connection, "markers"), 0);
+    }
+
+    #[test]
+    fn series_from_raw_or_minutes() {
+        let (mut inner, config) = inner();
+        for (offset, load) in [(0, 10.0), (30, 20.0), (60, 40.0), (90, 60.0), (120, 0.0)] {
+            save(&mut inner, &config, T + offset, 50.0, load);
+        }
+        let load = |series: Vec<Series>| {
+            let load = series.into_iter().find(|series| series.key == "cpu_load_percent").unwrap();
+            assert_eq!((load.title.as_str(), load.unit.as_str()), ("CPU Load", "percent"));
+            load.points
+        };
+
+        // A recent range with buckets under a minute
+        let raw = read_series(&inner.connection, T, T + 119, 120, T).unwrap();
+        assert_eq!(load(raw), [(T, 10.0), (T + 30, 20.0), (T + 60, 40.0), (T + 90, 60.0)]);
+        // Slots are multiples of the bucket, here 40 seconds
+        let averaged = read_series(&inner.connection, T, T + 120, 3, T).unwrap();
+        assert_eq!(load(averaged), [(T, 15.0), (T + 40, 40.0), (T + 80, 60.0), (T + 120, 0.0)]);
+
+        // Older than the raw samples kept, or buckets of a minute or more
+        let old = read_series(&inner.connection, T, T + 119, 120, T + 1).unwrap();
+        assert_eq!(load(old), [(T, 15.0), (T + 60, 50.0)]);
+        let long = read_series(&inner.connection, T, T + 3600, 60, T).unwrap();
+        assert_eq!(load(long), [(T, 15.0), (T + 60, 50.0)]);
+    }
+
+    #[test]
+    fn alerts_on_rising_edges() {
+        let (mut inner, config) = inner();
+        let temps = [50.0, 86.0, 87.0, 96.0, 80.0, 90.0, 50.0];
+        for (i, temp) in temps.into_iter().enumerate() {
+            save(&mut inner, &config, T + i as i64, temp, 10.0);
+        }
+
+        let alerts: Vec<(i64, String, String)> = read_alerts(&inner.connection, 0, i64::MAX)
+            .unwrap()
+            .into_iter()
+            .filter(|alert| alert.reading == "cpu_temp")
+            .map(|alert| (alert.time - T, alert.severity, alert.value))
+            .collect();
+        let expected = [(1, "hot", "86"), (3, "critical", "96"), (5, "hot", "90")];
+        assert_eq!(alerts, expected.map(|(time, severity, value)| (time, severity.to_string(), value.to_string())));
+    }
+}
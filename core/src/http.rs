@@ -0,0 +1,345 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// A deliberately small HTTP/1.1 server for the local endpoints: one thread
+// per connection up to a limit, GET only, no keep-alive. It is meant to be
+// scraped by Prometheus or opened in a browser on the LAN, not exposed to
+// the internet.
+// A handler may take the connection over instead, e.g. for a WebSocket.
+
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HEADER_LINES: usize = 100;
+// The request line and headers together; the endpoints take no body
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+// Connections served at once, dashboards' WebSockets included; past this
+// new ones are answered 503 instead of each getting a thread
+const MAX_CONNECTIONS: usize = 64;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Names are lowercased
+    pub headers: Vec<(String, String)>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+    pub headers: Vec<(&'static str, String)>,
+    /// Runs on the connection's thread once a 101 response is written
+    pub upgrade: Option<Upgrade>,
+}
+
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+pub type Upgrade = Box<dyn FnOnce(TcpStream) + Send>;
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+            headers: Vec::new(),
+            upgrade: None,
+        }
+    }
+
+    /// Switches the connection to another protocol, handing the stream to
+    /// `upgrade` after the headers are sent
+    pub fn switching_protocols(headers: Vec<(&'static str, String)>, upgrade: Upgrade) -> Self {
+        Self {
+            status: 101,
+            content_type: "",
+            body: Vec::new(),
+            headers,
+            upgrade: Some(upgrade),
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn text(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "Not found\n")
+    }
+}
+
+/// Binds right away so the caller can report a bad address, then accepts
+/// connections on a background thread
+pub fn serve(address: &str, handler: Handler) -> io::Result<SocketAddr> {
+    serve_at_most(address, handler, MAX_CONNECTIONS)
+}
+
+fn serve_at_most(address: &str, handler: Handler, max_connections: usize) -> io::Result<SocketAddr> {
+    let listener = bind(address)?;
+    let local = listener.local_addr()?;
+    let active = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if active.fetch_add(1, Ordering::SeqCst) >= max_connections {
+                active.fetch_sub(1, Ordering::SeqCst);
+                let _ = reject(&stream);
+                continue;
+            }
+            let connection = Connection(active.clone());
+            let handler = handler.clone();
+            thread::spawn(move || {
+                let _ = handle(stream, &handler);
+                drop(connection);
+            });
+        }
+    });
+
+    Ok(local)
+}
+
+// Counts as served until its thread is done, however that ends
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Answered on the accepting thread, so neither side may wait long on the
+// peer. The request is drained before closing: closing with it unread
+// resets the connection, and the peer may never see the 503.
+fn reject(stream: &TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+    let response = Response::text(503, "Too many connections\n").with_header("Retry-After", "5");
+    write_response(stream, &response, true)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    io::copy(&mut stream.take(MAX_HEAD_BYTES), &mut io::sink())?;
+    Ok(())
+}
+
+fn bind(address: &str) -> io::Result<TcpListener> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpListener::bind(addr) {
+            Ok(listener) => return Ok(listener),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to bind")))
+}
+
+fn handle(stream: TcpStream, handler: &Handler) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let (mut response, with_body) = match read_request(&mut reader)? {
+        Ok(request) if request.method == "GET" || request.method == "HEAD" => {
+            (handler(&request), request.method == "GET")
+        }
+        Ok(_) => (Response::text(405, "Method not allowed\n"), true),
+        Err(response) => (response, true),
+    };
+
+    write_response(&stream, &response, with_body)?;
+    if let Some(upgrade) = response.upgrade.take() {
+        upgrade(stream);
+    }
+    Ok(())
+}
+
+// The error is the response for a request that can't be served: 400 for one
+// that is malformed or cut short, 431 for one over the header limits
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut reader = reader.take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    if let Err(response) = read_line(&mut reader, &mut line)? {
+        return Ok(Err(response));
+    }
+
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err(bad_request()));
+    };
+    // Query strings are not used by any endpoint
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let method = method.to_string();
+
+    let mut headers = Vec::new();
+    for _ in 0..MAX_HEADER_LINES {
+        line.clear();
+        if let Err(response) = read_line(&mut reader, &mut line)? {
+            return Ok(Err(response));
+        }
+        if line.trim_end().is_empty() {
+            return Ok(Ok(Request { method, path, headers }));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    Ok(Err(too_large()))
+}
+
+// A line without its newline either ran into the size limit or the peer
+// closed the connection halfway through the request
+fn read_line(reader: &mut io::Take<impl BufRead>, line: &mut String) -> io::Result<Result<(), Response>> {
+    match reader.read_line(line) {
+        Ok(_) if line.ends_with('\n') => Ok(Ok(())),
+        Ok(_) if reader.limit() == 0 => Ok(Err(too_large())),
+        Ok(_) => Ok(Err(bad_request())),
+        Err(err) if err.kind() == io::ErrorKind::InvalidData => Ok(Err(bad_request())),
+        Err(err) => Err(err),
+    }
+}
+
+fn bad_request() -> Response {
+    Response::text(400, "Bad request\n")
+}
+
+fn too_large() -> Response {
+    Response::text(431, "Request header fields too large\n")
+}
+
+// HEAD gets the same headers as GET, including Content-Length, but no body
+fn write_response(mut stream: &TcpStream, response: &Response, with_body: bool) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status, reason(response.status));
+    if response.upgrade.is_some() {
+        head.push_str("Connection: Upgrade\r\n");
+    } else {
+        head.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            response.content_type,
+            response.body.len()
+        ));
+    }
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    if with_body {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        426 => "Upgrade Required",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(request: &[u8]) -> u16 {
+        match read_request(&mut &request[..]).unwrap() {
+            Ok(_) => 200,
+            Err(response) => response.status,
+        }
+    }
+
+    #[test]
+    fn request() {
+        let request = read_request(&mut &b"GET /metrics?x=1 HTTP/1.1\r\nHost: pc:9090\r\nAccept: */*\r\n\r\n"[..])
+            .unwrap()
+            .ok()
+            .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/metrics");
+        assert_eq!(request.header("HOST"), Some("pc:9090"));
+        assert_eq!(request.headers.len(), 2);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(status(b""), 400);
+        assert_eq!(status(b"GET\r\n\r\n"), 400);
+        // The connection closed before the blank line
+        assert_eq!(status(b"GET / HTTP/1.1\r\nHost: pc"), 400);
+        assert_eq!(status(b"GET / HTTP/1.1\r\nHost: \xFF\r\n\r\n"), 400);
+    }
+
+    fn connect(address: SocketAddr) -> TcpStream {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream
+    }
+
+    fn get(address: SocketAddr) -> String {
+        let mut stream = connect(address);
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn connections_are_capped() {
+        let handler: Handler = Arc::new(|_| Response::text(200, "ok\n"));
+        let address = serve_at_most("127.0.0.1:0", handler, 2).unwrap();
+
+        // Two peers that connect and send nothing hold both places
+        let first = connect(address);
+        let second = connect(address);
+        let refused = get(address);
+        assert!(refused.starts_with("HTTP/1.1 503 Service Unavailable\r\n"), "{}", refused);
+        assert!(refused.contains("\r\nRetry-After: 5\r\n"));
+
+        // A place frees up once a connection is done
+        drop(first);
+        let mut response = String::new();
+        for _ in 0..50 {
+            response = get(address);
+            if response.starts_with("HTTP/1.1 200 OK") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(response.ends_with("\r\n\r\nok\n"), "{}", response);
+        drop(second);
+    }
+
+    #[test]
+    fn limits() {
+        let long_header = format!("GET / HTTP/1.1\r\nCookie: {}\r\n\r\n", "x".repeat(MAX_HEAD_BYTES as usize));
+        assert_eq!(status(long_header.as_bytes()), 431);
+
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "x".repeat(MAX_HEAD_BYTES as usize));
+        assert_eq!(status(long_line.as_bytes()), 431);
+
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADER_LINES));
+        assert_eq!(status(many_headers.as_bytes()), 431);
+        let enough_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADER_LINES - 1));
+        assert_eq!(status(enough_headers.as_bytes()), 200);
+    }
+}
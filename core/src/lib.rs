@@ -0,0 +1,135 @@
+//! The part of Gamer Monitor that works without a display: sensor
+//! collection, the scheduler that publishes snapshots, alerting, the
+//! history database and every exporter. The `gamer-monitor` window,
+//! terminal dashboard and status bar modules are frontends on top of it.
+//!
+//! The crate does not depend on GTK, but it does link glib and gio (for the
+//! main loop and D-Bus) and SQLite, so building it needs their development
+//! files and `pkg-config`: `libglib2.0-dev` and `libsqlite3-dev` on
+//! Debian and Ubuntu, `glib2-devel` and `sqlite-devel` on Fedora, `glib2`
+//! and `sqlite` on Arch. No display or session bus is needed to run it.
+//!
+//! Readings come from a [`sampler::Sampler`], which reads each subsystem at
+//! its own rate from a [`capture::Source`], usually this machine:
+//!
+//! ```no_run
+//! use gamer_monitor_core::capture::System;
+//! use gamer_monitor_core::config::Config;
+//! use gamer_monitor_core::sampler::Sampler;
+//! use std::time::{Duration, Instant};
+//!
+//! let config = Config::load().unwrap_or_default();
+//! let mut sampler = Sampler::new(config.sampling.clone(), Box::new(System::default()));
+//! println!("CPU {} °C", sampler.sample_all().data.cpu_temp);
+//!
+//! loop {
+//!     std::thread::sleep(Duration::from_secs(1));
+//!     if let Some(snapshot) = sampler.tick(Instant::now()) {
+//!         println!("CPU {} °C", snapshot.data.cpu_temp);
+//!     }
+//! }
+//! ```
+//!
+//! The history, alerts and exporters subscribe to a [`scheduler::Publisher`]
+//! instead, which [`scheduler::start`] feeds from a glib main loop:
+//!
+//! ```no_run
+//! use gamer_monitor_core::capture::System;
+//! use gamer_monitor_core::config::Config;
+//! use gamer_monitor_core::glib::{ControlFlow, MainLoop};
+//! use gamer_monitor_core::sampler::Sampler;
+//! use gamer_monitor_core::scheduler::{self, Publisher};
+//! use gamer_monitor_core::{exporters, history};
+//! use std::cell::RefCell;
+//! use std::rc::Rc;
+//!
+//! let config = Config::load().unwrap_or_default();
+//! let sampler = Rc::new(RefCell::new(Sampler::new(config.sampling.clone(), Box::new(System::default()))));
+//! let publisher = Publisher::new();
+//!
+//! let history = history::start(&publisher, &config);
+//! let options = exporters::Options { prometheus: Some("127.0.0.1:9100".to_string()), ..Default::default() };
+//! exporters::start(&options, &config, &publisher, history.as_ref()).unwrap();
+//!
+//! publisher.subscribe(|snapshot| {
+//!     println!("GPU {} °C", snapshot.data.gpu_edge);
+//!     ControlFlow::Continue
+//! });
+//! publisher.publish(sampler.borrow_mut().sample_all());
+//! scheduler::start(&sampler, &publisher);
+//! MainLoop::new(None, false).run();
+//! ```
+//!
+//! Everything runs on the thread of the glib main context, like the window.
+//!
+//! Nothing is printed: what happens in the background, such as an exporter
+//! listening, a push endpoint going away or a history write failing, is
+//! reported through the [`log`] crate, so install a
+//! logger to see it.
+
+/// Thresholds and the severity of each reading
+pub mod alerts;
+/// The text of waybar, i3bar and polybar modules
+pub mod bar;
+/// Where raw sensor inputs come from: this machine, a capture being recorded, or a replay
+pub mod capture;
+/// Client for the local socket of a running monitor, or for a remote agent
+pub mod client;
+/// The configuration file
+pub mod config;
+/// The D-Bus interface
+pub mod dbus;
+/// How the last read of each sensor input went
+pub mod diagnostics;
+/// Starts every exporter the configuration or the frontend enables
+pub mod exporters;
+/// The per-game session summary
+pub mod games;
+/// The downsampled SQLite history
+pub mod history;
+mod http;
+/// The CSV logger
+pub mod logger;
+/// Readings by metric name, shared by the exporters
+pub mod metrics;
+/// The MQTT exporter
+pub mod mqtt;
+/// One-shot and streaming output as text, JSON or CSV
+pub mod output;
+/// Per-process CPU, memory, GPU and VRAM usage
+pub mod processes;
+/// The Prometheus exporter
+pub mod prometheus;
+/// The UDP and TCP push exporter
+pub mod push;
+/// Readings from agents on other machines
+pub mod remote;
+/// The hardware report attached to support tickets
+pub mod report;
+/// Runs the programs the sensors are read from, with a timeout
+pub mod runner;
+/// Reads each subsystem at its own rate
+pub mod sampler;
+/// Publishes snapshots to subscribers from the glib main loop
+pub mod scheduler;
+/// The rows of each dashboard section
+pub mod sections;
+/// Sensor parsing
+pub mod sensors;
+/// Detects running games and records their sessions
+pub mod sessions;
+/// The local socket and agent API
+pub mod socket;
+/// Timestamps as text
+pub mod timestamp;
+/// Unit conversion and formatting
+pub mod units;
+/// The web dashboard
+pub mod web;
+mod websocket;
+
+// So frontends use the same glib as the scheduler
+pub use glib;
+
+/// The application ID, also the D-Bus name and interface
+pub const APP_ID: &str = "org.lunx.GamerMonitor";
@@ -0,0 +1,301 @@
+use crate::config::{Config, FanConfig};
+use crate::metrics;
+use crate::output;
+use crate::sampler::Snapshot;
+use crate::timestamp;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const MEBIBYTE: u64 = 1024 * 1024;
+
+/// Appends one CSV row per snapshot. Each file starts with a commented
+/// header naming the detected hardware, followed by the column names.
+/// Once the file grows past the size or age limit, or the set of columns
+/// changes (e.g. an NVMe drive appears), it is renamed with a timestamp
+/// suffix and a fresh file is started under the original name.
+pub struct CsvLogger {
+    path: PathBuf,
+    fans: FanConfig,
+    max_size: u64,
+    max_age: Duration,
+    file: Option<File>,
+    columns: Vec<String>,
+    started: SystemTime,
+    size: u64,
+}
+
+impl CsvLogger {
+    pub fn new(path: PathBuf, config: &Config) -> Self {
+        Self {
+            path,
+            fans: config.fans.clone(),
+            max_size: config.logging.max_size_mb * MEBIBYTE,
+            max_age: Duration::from_secs(config.logging.max_age_hours * 3600),
+            file: None,
+            columns: Vec::new(),
+            started: SystemTime::now(),
+            size: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let readings = metrics::collect(&snapshot.data, &self.fans);
+        let columns = output::csv_columns(&readings);
+
+        if self.file.is_some() && (columns != self.columns || self.limit_reached(snapshot.timestamp)) {
+            self.file = None;
+            self.rotate()?;
+        }
+
+        if self.file.is_none() {
+            self.open(snapshot, columns)?;
+        }
+
+        let line = format!("{}\n", output::csv_row(snapshot, &readings));
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+        }
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn limit_reached(&self, now: SystemTime) -> bool {
+        let too_big = self.max_size > 0 && self.size >= self.max_size;
+        let too_old = !self.max_age.is_zero()
+            && now.duration_since(self.started).unwrap_or_default() >= self.max_age;
+        too_big || too_old
+    }
+
+    fn open(&mut self, snapshot: &Snapshot, columns: Vec<String>) -> io::Result<()> {
+        if let Some(dir) = self.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Keep appending to the previous session's file when it is compatible
+        let appending = match read_columns(&self.path)? {
+            Some(existing) if existing == columns => true,
+            Some(_) => {
+                self.rotate()?;
+                false
+            }
+            None => false,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(appending)
+            .write(true)
+            .truncate(!appending)
+            .open(&self.path)?;
+
+        if appending {
+            let metadata = file.metadata()?;
+            self.size = metadata.len();
+            self.started = metadata.created().unwrap_or(snapshot.timestamp);
+        } else {
+            let data = &snapshot.data;
+            let header = format!(
+                "# Gamer Monitor {}\n# cpu_name: {}\n# gpu_name: {}\n# cpu_temperature_sensor: {}\n# started: {}\n{}\n",
+                env!("CARGO_PKG_VERSION"),
+                data.cpu_name,
+                data.gpu_name,
+                data.cpu_temp_sensor,
+                timestamp::rfc3339(snapshot.timestamp),
+                columns.join(","),
+            );
+            file.write_all(header.as_bytes())?;
+            self.size = header.len() as u64;
+            self.started = snapshot.timestamp;
+        }
+
+        self.file = Some(file);
+        self.columns = columns;
+        Ok(())
+    }
+
+    // readings.csv -> readings-20260314T092653Z.csv
+    fn rotate(&self) -> io::Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = self.path.extension().map(|ext| ext.to_string_lossy()).unwrap_or("csv".into());
+        let stamp = timestamp::compact(SystemTime::now());
+
+        let mut target = self.path.with_file_name(format!("{}-{}.{}", stem, stamp, extension));
+        let mut attempt = 1;
+        while target.exists() {
+            target = self.path.with_file_name(format!("{}-{}-{}.{}", stem, stamp, attempt, extension));
+            attempt += 1;
+        }
+
+        fs::rename(&self.path, target)
+    }
+}
+
+// Column names of an existing log, or None if there is no usable file
+fn read_columns(path: &Path) -> io::Result<Option<Vec<String>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !line.starts_with('#') {
+            return Ok(Some(line.split(',').map(str::to_string).collect()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Writes to an optional logger and drops it after the first failure, so a
+/// full disk is reported once instead of on every sample. Returns false if
+/// logging stopped.
+pub fn record(logger: &mut Option<CsvLogger>, snapshot: &Snapshot) -> bool {
+    let Some(active) = logger.as_mut() else {
+        return true;
+    };
+
+    if let Err(err) = active.write(snapshot) {
+        log::error!("Stopped logging to {}: {}", active.path().display(), err);
+        *logger = None;
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    const T: u64 = 1_773_480_413;
+
+    fn snapshot(seconds: u64, nvme: usize) -> Snapshot {
+        let mut data = crate::sensors::SensorData::new();
+        data.cpu_name = "AMD Ryzen 7 5800X3D".to_string();
+        data.cpu_temp = "+61.0°C".to_string();
+        data.nvme_temps = vec!["+38.9°C".to_string(); nvme];
+        Snapshot {
+            sequence: seconds,
+            timestamp: UNIX_EPOCH + Duration::from_secs(T + seconds),
+            data,
+            processes: Arc::new(Vec::new()),
+            diagnostics: Arc::new(Vec::new()),
+        }
+    }
+
+    fn start(name: &str) -> (CsvLogger, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("gamer-monitor-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        (CsvLogger::new(dir.join("readings.csv"), &Config::default()), dir)
+    }
+
+    // The file names in the directory, and the lines of readings.csv that are not comments
+    fn contents(dir: &Path) -> (Vec<String>, Vec<String>) {
+        let mut files: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        let text = fs::read_to_string(dir.join("readings.csv")).unwrap();
+        let lines = text.lines().filter(|line| !line.starts_with('#')).map(str::to_string).collect();
+        (files, lines)
+    }
+
+    fn is_rotated(name: &str) -> bool {
+        name.starts_with("readings-") && name.ends_with(".csv")
+    }
+
+    #[test]
+    fn appends_to_a_compatible_file() {
+        let (mut logger, dir) = start("log-append");
+        logger.write(&snapshot(0, 1)).unwrap();
+        logger.write(&snapshot(1, 1)).unwrap();
+        drop(logger);
+
+        let mut logger = CsvLogger::new(dir.join("readings.csv"), &Config::default());
+        logger.write(&snapshot(2, 1)).unwrap();
+        let (files, lines) = contents(&dir);
+        assert_eq!(files, ["readings.csv"]);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("timestamp,") && lines[0].contains(",nvme0_temperature_celsius,"));
+        assert_eq!(logger.size, fs::metadata(dir.join("readings.csv")).unwrap().len());
+
+        let text = fs::read_to_string(dir.join("readings.csv")).unwrap();
+        assert_eq!(text.matches("# cpu_name: AMD Ryzen 7 5800X3D\n").count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_when_the_columns_change() {
+        let (mut logger, dir) = start("log-columns");
+        logger.write(&snapshot(0, 1)).unwrap();
+        logger.write(&snapshot(1, 2)).unwrap();
+        let (files, lines) = contents(&dir);
+        assert_eq!(files.len(), 2);
+        assert!(is_rotated(&files[0]), "{:?}", files);
+        assert!(lines[0].contains(",nvme0_temperature_celsius,nvme1_temperature_celsius,"));
+        assert_eq!(lines.len(), 2);
+        drop(logger);
+
+        // A new run with other columns does not append to the old file
+        let mut logger = CsvLogger::new(dir.join("readings.csv"), &Config::default());
+        logger.write(&snapshot(2, 0)).unwrap();
+        let (files, lines) = contents(&dir);
+        assert_eq!(files.len(), 3);
+        assert!(!lines[0].contains("nvme"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_on_size_and_age() {
+        let (mut logger, dir) = start("log-limits");
+        logger.max_size = 1024;
+        let mut rows = 0;
+        while fs::read_dir(&dir).map_or(1, |entries| entries.count()) == 1 {
+            logger.write(&snapshot(rows, 1)).unwrap();
+            rows += 1;
+        }
+        let (_, lines) = contents(&dir);
+        // The row over the limit starts the new file
+        assert_eq!(lines.len(), 2);
+        assert!(rows > 2);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (mut logger, dir) = start("log-age");
+        logger.write(&snapshot(0, 1)).unwrap();
+        logger.write(&snapshot(24 * 3600 - 1, 1)).unwrap();
+        assert_eq!(contents(&dir).0.len(), 1);
+        logger.write(&snapshot(24 * 3600, 1)).unwrap();
+        let (files, lines) = contents(&dir);
+        assert_eq!(files.len(), 2);
+        assert_eq!(lines.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_limits_when_zero() {
+        let (mut logger, dir) = start("log-unlimited");
+        logger.max_size = 0;
+        logger.max_age = Duration::ZERO;
+        logger.write(&snapshot(0, 1)).unwrap();
+        logger.write(&snapshot(365 * 24 * 3600, 1)).unwrap();
+        assert_eq!(contents(&dir).0, ["readings.csv"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
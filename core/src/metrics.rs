@@ -0,0 +1,199 @@
+use crate::config::FanConfig;
+use crate::sensors::{SensorData, parse_reading};
+
+// A flat, numeric view of SensorData shared by every export format.
+// Values are always in base units (°C, %, RPM, W, MiB, GiB); conversion
+// to the user's preferred units is left to the text formats.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    Cpu,
+    Gpu,
+    Nvme,
+    Fan,
+    Ram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Celsius,
+    Percent,
+    Rpm,
+    Watts,
+    Mebibytes,
+    Gibibytes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub component: Component,
+    /// Flat identifier such as "gpu_hotspot" or "nvme0_temperature"
+    pub key: String,
+    /// What is measured, shared by readings that differ only by their labels,
+    /// e.g. "gpu_temperature" for the edge, hotspot and memory sensors
+    pub family: &'static str,
+    /// Dimensions for exporters with labels or tags, e.g. ("device", "nvme0")
+    pub labels: Vec<(&'static str, String)>,
+    /// Human-readable name such as "Hotspot" or "NVMe 1"
+    pub title: String,
+    pub quantity: Quantity,
+    pub value: Option<f64>,
+}
+
+impl Component {
+    pub const ALL: [Component; 5] = [
+        Component::Cpu,
+        Component::Gpu,
+        Component::Nvme,
+        Component::Fan,
+        Component::Ram,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Component::Cpu => "CPU",
+            Component::Gpu => "GPU",
+            Component::Nvme => "Storage (NVMe)",
+            Component::Fan => "Fans",
+            Component::Ram => "RAM",
+        }
+    }
+}
+
+impl Quantity {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Quantity::Celsius => "celsius",
+            Quantity::Percent => "percent",
+            Quantity::Rpm => "rpm",
+            Quantity::Watts => "watts",
+            Quantity::Mebibytes => "mebibytes",
+            Quantity::Gibibytes => "gibibytes",
+        }
+    }
+}
+
+impl Reading {
+    fn new(
+        component: Component,
+        family: &'static str,
+        key: impl Into<String>,
+        title: impl Into<String>,
+        quantity: Quantity,
+        value: Option<f64>,
+    ) -> Self {
+        Self {
+            component,
+            key: key.into(),
+            family,
+            labels: Vec::new(),
+            title: title.into(),
+            quantity,
+            value,
+        }
+    }
+
+    fn label(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.labels.push((name, value.into()));
+        self
+    }
+
+    /// Key plus unit, e.g. "gpu_hotspot_celsius"; used for CSV columns and JSON fields
+    pub fn column(&self) -> String {
+        format!("{}_{}", self.key, self.quantity.suffix())
+    }
+}
+
+// Sensors are read as f32; round so exports show 45.3 rather than 45.29999923706055.
+// A NaN or infinite load (e.g. from a zero time delta) counts as unavailable.
+fn number(value: f32) -> Option<f64> {
+    value.is_finite().then(|| (value as f64 * 100.0).round() / 100.0)
+}
+
+fn text_value(text: &str) -> Option<f64> {
+    parse_reading(text).and_then(number)
+}
+
+/// Coarse vendor name derived from the GPU model, used as a label
+pub fn gpu_vendor(gpu_name: &str) -> &'static str {
+    let name = gpu_name.to_lowercase();
+    if name.contains("nvidia") {
+        "nvidia"
+    } else if name.contains("radeon") || name.contains("amd") {
+        "amd"
+    } else if name.contains("intel") || name.contains("arc") {
+        "intel"
+    } else {
+        "unknown"
+    }
+}
+
+pub fn collect(data: &SensorData, fans: &FanConfig) -> Vec<Reading> {
+    use Component::*;
+    use Quantity::*;
+
+    let mut readings = vec![
+        Reading::new(Cpu, "cpu_temperature", "cpu_temperature", "Temperature", Celsius, text_value(&data.cpu_temp))
+            .label("sensor", data.cpu_temp_sensor.as_str()),
+        Reading::new(Cpu, "cpu_load", "cpu_load", "Load", Percent, number(data.cpu_usage)),
+    ];
+
+    // nvidia-smi reports the fan duty cycle, amdgpu the actual speed.
+    // GPU load is only available through nvidia-smi.
+    let vendor = gpu_vendor(&data.gpu_name);
+    let gpu_load = if vendor == "nvidia" { number(data.gpu_utilization) } else { None };
+    let gpu_fan_quantity = if vendor == "nvidia" { Percent } else { Rpm };
+
+    let gpu = [
+        Reading::new(Gpu, "gpu_temperature", "gpu_edge", "Edge", Celsius, text_value(&data.gpu_edge))
+            .label("sensor", "edge"),
+        Reading::new(Gpu, "gpu_temperature", "gpu_hotspot", "Hotspot", Celsius, text_value(&data.gpu_hotspot))
+            .label("sensor", "hotspot"),
+        Reading::new(Gpu, "gpu_temperature", "gpu_memory", "Memory", Celsius, text_value(&data.gpu_memory))
+            .label("sensor", "memory"),
+        Reading::new(Gpu, "gpu_fan", "gpu_fan", "Fan", gpu_fan_quantity, text_value(&data.gpu_fan)),
+        Reading::new(Gpu, "gpu_power", "gpu_power", "Power", Watts, text_value(&data.gpu_power)),
+        Reading::new(Gpu, "gpu_load", "gpu_load", "Load", Percent, gpu_load),
+        Reading::new(Gpu, "gpu_vram_used", "gpu_vram_used", "VRAM used", Mebibytes, text_value(&data.gpu_vram_used)),
+        Reading::new(Gpu, "gpu_vram_total", "gpu_vram_total", "VRAM total", Mebibytes, text_value(&data.gpu_vram_total)),
+    ];
+    // Only one GPU is monitored for now, so its index is always 0
+    readings.extend(gpu.into_iter().map(|reading| reading.label("gpu", "0").label("vendor", vendor)));
+
+    for (i, temp) in data.nvme_temps.iter().enumerate() {
+        readings.push(
+            Reading::new(
+                Nvme,
+                "nvme_temperature",
+                format!("nvme{}_temperature", i),
+                format!("NVMe {}", i + 1),
+                Celsius,
+                text_value(temp),
+            )
+            .label("device", format!("nvme{}", i)),
+        );
+    }
+
+    let fan_readings = [
+        ("cpu", &fans.cpu, &data.cpu_fan),
+        ("chassis1", &fans.chassis1, &data.chassis_fan1),
+        ("chassis2", &fans.chassis2, &data.chassis_fan2),
+    ];
+    for (fan, name, value) in fan_readings {
+        readings.push(
+            Reading::new(Fan, "fan_speed", format!("fan_{}", fan), name.as_str(), Rpm, text_value(value))
+                .label("fan", fan)
+                .label("name", name.as_str()),
+        );
+    }
+
+    readings.extend([
+        Reading::new(Ram, "ram_total", "ram_total", "Total", Gibibytes, number(data.ram_total)),
+        Reading::new(Ram, "ram_used", "ram_used", "Used", Gibibytes, number(data.ram_used)),
+        Reading::new(Ram, "ram_free", "ram_free", "Free", Gibibytes, number(data.ram_free)),
+        Reading::new(Ram, "ram_available", "ram_available", "Available", Gibibytes, number(data.ram_available)),
+        Reading::new(Ram, "ram_load", "ram_load", "Load", Percent, number(data.ram_percent)),
+    ]);
+
+    readings
+}
@@ -0,0 +1,504 @@
+use crate::config::{Config, MqttConfig};
+use crate::metrics::{self, Component, Quantity, Reading};
+use crate::push;
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use crate::sensors::SensorData;
+use serde_json::json;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_PORT: u16 = 1883;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// MQTT 3.1.1 packet types
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PINGREQ: u8 = 0xC0;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+    topic: String,
+    payload: String,
+    retain: bool,
+}
+
+// What one snapshot publishes. Discovery messages are only sent again when
+// they differ from what the broker already has, e.g. a new NVMe drive.
+struct Batch {
+    discovery: Vec<Message>,
+    states: Vec<Message>,
+}
+
+struct Session {
+    address: String,
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    keep_alive: u16,
+    // "online" while connected; the broker publishes "offline" for us
+    // when the connection drops
+    status_topic: String,
+}
+
+/// Builds the messages on the main thread and hands them to a worker thread,
+/// like push.rs. Readings are fire-and-forget (QoS 0): after an outage only
+/// the latest values matter, so nothing is queued.
+pub fn start(broker: &str, config: &Config, publisher: &Publisher) -> Result<(), String> {
+    let address = parse_broker(broker)?;
+    let mqtt = config.exporter.mqtt.clone();
+    let fans = config.fans.clone();
+    let host = mqtt.host.clone().unwrap_or_else(push::hostname);
+    let base = format!("{}/{}", mqtt.topic_prefix, topic_segment(&host));
+
+    let session = Session {
+        address,
+        client_id: format!("gamer-monitor-{}", topic_segment(&host)),
+        username: mqtt.username.clone(),
+        password: mqtt.password.clone(),
+        keep_alive: mqtt.keep_alive,
+        status_topic: format!("{}/status", base),
+    };
+    let (sender, receiver) = mpsc::channel::<Batch>();
+    let description = broker.to_string();
+    thread::spawn(move || deliver(receiver, &session, &description));
+
+    let batch = move |snapshot: &Snapshot| {
+        let readings = metrics::collect(&snapshot.data, &fans);
+        let discovery = match mqtt.discovery {
+            true => discovery_messages(&readings, &snapshot.data, &mqtt, &host, &base),
+            false => Vec::new(),
+        };
+        let states = readings
+            .iter()
+            .filter_map(|reading| {
+                Some(Message {
+                    topic: format!("{}/{}", base, topic_path(reading)),
+                    payload: reading.value?.to_string(),
+                    retain: false,
+                })
+            })
+            .collect();
+        Batch { discovery, states }
+    };
+    // The first snapshot is taken before the exporters start
+    if let Some(snapshot) = publisher.latest() {
+        let _ = sender.send(batch(&snapshot));
+    }
+    publisher.subscribe(move |snapshot| match sender.send(batch(snapshot)) {
+        Ok(()) => glib::ControlFlow::Continue,
+        Err(_) => glib::ControlFlow::Break,
+    });
+
+    Ok(())
+}
+
+fn parse_broker(broker: &str) -> Result<String, String> {
+    let address = match broker.split_once("://") {
+        Some(("mqtt" | "tcp", address)) => address,
+        Some(("mqtts" | "ssl", _)) => {
+            return Err(format!("MQTT over TLS is not supported ('{}'); use a broker on the LAN", broker));
+        }
+        Some(_) => return Err(format!("invalid MQTT broker '{}' (expected host:port)", broker)),
+        None => broker,
+    };
+    if address.is_empty() {
+        return Err(format!("invalid MQTT broker '{}' (expected host:port)", broker));
+    }
+
+    match address.rsplit_once(':') {
+        // An IPv6 address without a port, e.g. [::1]
+        Some((_, port)) if port.ends_with(']') => Ok(format!("{}:{}", address, DEFAULT_PORT)),
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(address.to_string()),
+        Some(_) => Err(format!("invalid MQTT broker '{}' (expected host:port)", broker)),
+        None => Ok(format!("{}:{}", address, DEFAULT_PORT)),
+    }
+}
+
+// Where a reading lives under <prefix>/<host>, e.g. gpu/0/hotspot,
+// nvme/1/temperature or fan/chassis1
+fn topic_path(reading: &Reading) -> String {
+    let label = |name: &str| {
+        reading
+            .labels
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    };
+
+    match reading.component {
+        Component::Cpu => format!("cpu/{}", reading.key.trim_start_matches("cpu_")),
+        Component::Gpu => format!("gpu/{}/{}", label("gpu"), reading.key.trim_start_matches("gpu_")),
+        Component::Nvme => format!("nvme/{}/temperature", label("device").trim_start_matches("nvme")),
+        Component::Fan => format!("fan/{}", label("fan")),
+        Component::Ram => format!("ram/{}", reading.key.trim_start_matches("ram_")),
+    }
+}
+
+// Home Assistant MQTT discovery: one retained config message per sensor,
+// all grouped under one device per host
+fn discovery_messages(readings: &[Reading], data: &SensorData, mqtt: &MqttConfig, host: &str, base: &str) -> Vec<Message> {
+    let node = format!("gamer_monitor_{}", object_id(host));
+    let device = json!({
+        "identifiers": [node],
+        "name": format!("Gamer Monitor {}", host),
+        "manufacturer": "Gamer Monitor",
+        "model": format!("{} / {}", data.cpu_name, data.gpu_name),
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+
+    readings
+        .iter()
+        .map(|reading| {
+            let name = match reading.component {
+                Component::Cpu => format!("CPU {}", reading.title),
+                Component::Gpu => format!("GPU {}", reading.title),
+                Component::Nvme => format!("{} Temperature", reading.title),
+                Component::Fan => reading.title.clone(),
+                Component::Ram => format!("RAM {}", reading.title),
+            };
+            let (unit, device_class) = match reading.quantity {
+                Quantity::Celsius => ("°C", Some("temperature")),
+                Quantity::Percent => ("%", None),
+                Quantity::Rpm => ("RPM", None),
+                Quantity::Watts => ("W", Some("power")),
+                Quantity::Mebibytes => ("MiB", Some("data_size")),
+                Quantity::Gibibytes => ("GiB", Some("data_size")),
+            };
+
+            let mut config = json!({
+                "name": name,
+                "unique_id": format!("{}_{}", node, reading.key),
+                "state_topic": format!("{}/{}", base, topic_path(reading)),
+                "availability_topic": format!("{}/status", base),
+                "unit_of_measurement": unit,
+                "state_class": "measurement",
+                "suggested_display_precision": 1,
+                "device": device,
+            });
+            if let Some(device_class) = device_class {
+                config["device_class"] = json!(device_class);
+            }
+
+            Message {
+                topic: format!("{}/sensor/{}/{}/config", mqtt.discovery_prefix, node, reading.key),
+                payload: config.to_string(),
+                retain: true,
+            }
+        })
+        .collect()
+}
+
+// Topic levels must not contain the separators or wildcards
+fn topic_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if matches!(c, '/' | '+' | '#') || c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+// Home Assistant only accepts [a-zA-Z0-9_-] in discovery ids
+fn object_id(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+// Runs on its own thread. Pings the broker when no snapshot arrived for half
+// the keep-alive, and reconnects with exponential backoff, republishing the
+// latest readings right away since a restarted broker may have lost them.
+fn deliver(receiver: Receiver<Batch>, session: &Session, description: &str) {
+    let ping_after = Duration::from_secs(session.keep_alive as u64) / 2;
+    let mut connection: Option<TcpStream> = None;
+    let mut announced: Vec<Message> = Vec::new();
+    let mut latest: Option<Batch> = None;
+    let mut backoff = Duration::from_secs(1);
+    let mut retry_at = Instant::now();
+    let mut failing = false;
+
+    loop {
+        let fresh = match receiver.recv_timeout(ping_after) {
+            Ok(batch) => {
+                latest = Some(batch);
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        if connection.is_none() && Instant::now() < retry_at {
+            continue;
+        }
+
+        match send(&mut connection, &mut announced, session, latest.as_ref(), fresh) {
+            Ok(()) => {
+                backoff = Duration::from_secs(1);
+                if failing {
+                    log::info!("MQTT broker {} is reachable again", description);
+                    failing = false;
+                }
+            }
+            Err(err) => {
+                connection = None;
+                if !failing {
+                    log::warn!("Publishing to MQTT broker {} failed, retrying in the background: {}", description, err);
+                    failing = true;
+                }
+                retry_at = Instant::now() + backoff;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+// Publishes the batch if it is new or the connection is, and pings otherwise
+fn send(
+    connection: &mut Option<TcpStream>,
+    announced: &mut Vec<Message>,
+    session: &Session,
+    batch: Option<&Batch>,
+    fresh: bool,
+) -> io::Result<()> {
+    let reconnected = connection.is_none();
+    let batch = batch.filter(|_| fresh || reconnected);
+    let stream = match connection {
+        Some(stream) => stream,
+        None => {
+            announced.clear();
+            connection.insert(connect(session)?)
+        }
+    };
+    drain(stream)?;
+
+    let mut out = Vec::new();
+    match batch {
+        Some(batch) => {
+            if batch.discovery != *announced {
+                for message in &batch.discovery {
+                    out.extend(publish_packet(message));
+                }
+            }
+            for message in &batch.states {
+                out.extend(publish_packet(message));
+            }
+        }
+        None => out.extend([PINGREQ, 0]),
+    }
+    stream.write_all(&out)?;
+    stream.flush()?;
+
+    if let Some(batch) = batch {
+        announced.clone_from(&batch.discovery);
+    }
+    Ok(())
+}
+
+fn connect(session: &Session) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in session.address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    let mut stream = stream.ok_or_else(|| last_error.unwrap_or_else(|| io::Error::other("no address to connect to")))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+
+    stream.write_all(&connect_packet(session))?;
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    check_connack(&reply)?;
+
+    let online = Message {
+        topic: session.status_topic.clone(),
+        payload: "online".to_string(),
+        retain: true,
+    };
+    stream.write_all(&publish_packet(&online))?;
+    Ok(stream)
+}
+
+// The broker's answer to CONNECT: the packet type, a remaining length of 2,
+// the session present flag and the return code
+fn check_connack(reply: &[u8; 4]) -> io::Result<()> {
+    if reply[0] != CONNACK || reply[1] != 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected CONNACK from the broker"));
+    }
+    let refused = match reply[3] {
+        0 => return Ok(()),
+        1 => "unsupported protocol version",
+        2 => "client id rejected",
+        3 => "server unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "refused",
+    };
+    Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("broker refused the connection: {}", refused)))
+}
+
+// Reads and discards whatever the broker sent (ping replies), which also
+// notices a connection the broker closed
+fn drain(stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(true)?;
+    let mut buffer = [0u8; 512];
+    let result = loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the broker closed the connection")),
+            Ok(_) => continue,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+    stream.set_nonblocking(false)?;
+    result
+}
+
+fn connect_packet(session: &Session) -> Vec<u8> {
+    // Clean session, and a retained "offline" will on the status topic
+    let mut flags = 0x02 | 0x04 | 0x20;
+    if session.username.is_some() {
+        flags |= 0x80;
+    }
+    if session.password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&session.keep_alive.to_be_bytes());
+    push_string(&mut body, &session.client_id);
+    push_string(&mut body, &session.status_topic);
+    push_string(&mut body, "offline");
+    if let Some(username) = &session.username {
+        push_string(&mut body, username);
+    }
+    if let Some(password) = &session.password {
+        push_string(&mut body, password);
+    }
+
+    packet(CONNECT, &body)
+}
+
+fn publish_packet(message: &Message) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, &message.topic);
+    body.extend_from_slice(message.payload.as_bytes());
+    packet(PUBLISH | message.retain as u8, &body)
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    // Remaining length, 7 bits per byte
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn push_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(username: Option<&str>, password: Option<&str>) -> Session {
+        Session {
+            address: "localhost:1883".to_string(),
+            client_id: "gm".to_string(),
+            username: username.map(str::to_string),
+            password: password.map(str::to_string),
+            keep_alive: 60,
+            status_topic: "s".to_string(),
+        }
+    }
+
+    #[test]
+    fn remaining_length() {
+        // The boundaries from the table in MQTT 3.1.1 section 2.2.3
+        let cases: [(usize, &[u8]); 8] = [
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (16_383, &[0xFF, 0x7F]),
+            (16_384, &[0x80, 0x80, 0x01]),
+            (2_097_151, &[0xFF, 0xFF, 0x7F]),
+            (2_097_152, &[0x80, 0x80, 0x80, 0x01]),
+            (268_435_455, &[0xFF, 0xFF, 0xFF, 0x7F]),
+        ];
+        for (length, encoded) in cases {
+            let packet = packet(PUBLISH, &vec![0; length]);
+            assert_eq!(&packet[1..1 + encoded.len()], encoded, "remaining length {}", length);
+            assert_eq!(packet.len(), 1 + encoded.len() + length);
+        }
+    }
+
+    #[test]
+    fn connect_flags_and_payload() {
+        let mut expected = vec![0x10, 32, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04];
+        // User name, password, will retain, will and clean session
+        expected.extend_from_slice(&[0xE6, 0x00, 0x3C]);
+        expected.extend_from_slice(&[0x00, 0x02, b'g', b'm', 0x00, 0x01, b's']);
+        expected.extend_from_slice(&[0x00, 0x07]);
+        expected.extend_from_slice(b"offline");
+        expected.extend_from_slice(&[0x00, 0x01, b'u', 0x00, 0x01, b'p']);
+        assert_eq!(connect_packet(&session(Some("u"), Some("p"))), expected);
+
+        let anonymous = connect_packet(&session(None, None));
+        assert_eq!(anonymous[1], 26);
+        assert_eq!(anonymous[9], 0x26);
+        assert!(anonymous.ends_with(b"offline"));
+
+        let username_only = connect_packet(&session(Some("u"), None));
+        assert_eq!(username_only[9], 0xA6);
+        assert!(username_only.ends_with(&[0x00, 0x01, b'u']));
+    }
+
+    #[test]
+    fn publish() {
+        let message = |retain| Message { topic: "a/b".to_string(), payload: "42".to_string(), retain };
+        assert_eq!(publish_packet(&message(true)), [0x31, 0x07, 0x00, 0x03, b'a', b'/', b'b', b'4', b'2']);
+        assert_eq!(publish_packet(&message(false))[0], 0x30);
+    }
+
+    #[test]
+    fn connack() {
+        assert!(check_connack(&[0x20, 0x02, 0x00, 0x00]).is_ok());
+        // Session present makes no difference with a clean session
+        assert!(check_connack(&[0x20, 0x02, 0x01, 0x00]).is_ok());
+
+        let refused = check_connack(&[0x20, 0x02, 0x00, 0x04]).unwrap_err();
+        assert_eq!(refused.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(refused.to_string(), "broker refused the connection: bad user name or password");
+
+        for reply in [[0x30, 0x02, 0x00, 0x00], [0x20, 0x03, 0x00, 0x00]] {
+            assert_eq!(check_connack(&reply).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::Config;
+use crate::metrics::{self, Component, Reading};
+use crate::sampler::Snapshot;
+use crate::timestamp;
+use serde_json::{Map, Value, json};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Text,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "text" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Renders snapshots for the command line. JSON is one object per line and
+/// CSV repeats its header only when the set of columns changes, so both can
+/// be streamed by --watch.
+pub struct Output {
+    format: Format,
+    config: Config,
+    csv_columns: Option<Vec<String>>,
+    // Text snapshots after the first are separated by a blank line
+    rendered: bool,
+}
+
+impl Output {
+    pub fn new(format: Format, config: &Config) -> Self {
+        Self {
+            format,
+            config: config.clone(),
+            csv_columns: None,
+            rendered: false,
+        }
+    }
+
+    pub fn render(&mut self, snapshot: &Snapshot) -> String {
+        let readings = metrics::collect(&snapshot.data, &self.config.fans);
+
+        let text = match self.format {
+            Format::Json => json(snapshot, &readings),
+            Format::Text => self.text(snapshot, &readings),
+            Format::Csv => self.csv(snapshot, &readings),
+        };
+        self.rendered = true;
+        text
+    }
+
+    fn text(&self, snapshot: &Snapshot, readings: &[Reading]) -> String {
+        let data = &snapshot.data;
+        let units = self.config.units;
+        let mut out = String::new();
+
+        if self.rendered {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "Time: {}", timestamp::rfc3339(snapshot.timestamp));
+        let _ = writeln!(out, "CPU:  {}", data.cpu_name);
+        let _ = writeln!(out, "GPU:  {}", data.gpu_name);
+
+        for component in Component::ALL {
+            let mut section = readings.iter().filter(|r| r.component == component).peekable();
+            if section.peek().is_none() {
+                continue;
+            }
+
+            let _ = writeln!(out, "\n{}", component.title());
+            for reading in section {
+                let value = match reading.value {
+                    Some(value) => units.reading(reading.quantity, value),
+                    None => "N/A".to_string(),
+                };
+                let _ = writeln!(out, "  {:<16} {}", reading.title, value);
+            }
+        }
+
+        out
+    }
+
+    fn csv(&mut self, snapshot: &Snapshot, readings: &[Reading]) -> String {
+        let columns = csv_columns(readings);
+        let mut out = String::new();
+
+        if self.csv_columns.as_ref() != Some(&columns) {
+            let _ = writeln!(out, "{}", columns.join(","));
+            self.csv_columns = Some(columns);
+        }
+
+        let _ = writeln!(out, "{}", csv_row(snapshot, readings));
+        out
+    }
+}
+
+/// The CSV header: a timestamp followed by one column per reading
+pub fn csv_columns(readings: &[Reading]) -> Vec<String> {
+    std::iter::once("timestamp".to_string())
+        .chain(readings.iter().map(Reading::column))
+        .collect()
+}
+
+/// One CSV line without its newline; unavailable readings are left empty
+pub fn csv_row(snapshot: &Snapshot, readings: &[Reading]) -> String {
+    std::iter::once(timestamp::rfc3339(snapshot.timestamp))
+        .chain(readings.iter().map(|r| r.value.map(|v| v.to_string()).unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn json(snapshot: &Snapshot, readings: &[Reading]) -> String {
+    format!("{}\n", document(snapshot, readings))
+}
+
+/// The object printed by --format json, also served over the local socket
+pub fn document(snapshot: &Snapshot, readings: &[Reading]) -> Value {
+    let data = &snapshot.data;
+
+    let values: Map<String, Value> = readings
+        .iter()
+        .map(|r| (r.column(), r.value.map_or(Value::Null, Value::from)))
+        .collect();
+
+    json!({
+        "timestamp": timestamp::rfc3339(snapshot.timestamp),
+        "hardware": {
+            "cpu": data.cpu_name,
+            "cpu_temperature_sensor": data.cpu_temp_sensor,
+            "gpu": data.gpu_name,
+        },
+        "readings": values,
+    })
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// One row of the Processes page. CPU is in percent of one core, like top,
+/// so a busy game on an eight core CPU can go up to 800%.
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: u32,
+    pub parent: u32,
+    pub name: String,
+    pub command: String,
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    /// None when the process holds no GPU device, or its fdinfo is not
+    /// readable (another user's process, or a driver without DRM stats)
+    pub gpu_percent: Option<f32>,
+    pub vram_bytes: Option<u64>,
+}
+
+// Counters kept between samples, so every figure is a delta
+#[derive(Default)]
+struct Previous {
+    cpu_ticks: u64,
+    gpu_nanoseconds: HashMap<String, u64>,
+}
+
+/// Reads /proc for every process. Each update is compared with the one
+/// before, so the first returns zero CPU and GPU for everything.
+#[derive(Default)]
+pub struct ProcessTable {
+    previous: HashMap<u32, Previous>,
+    cpu_total: Option<u64>,
+    last_update: Option<Instant>,
+}
+
+impl ProcessTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self) -> Vec<Process> {
+        let now = Instant::now();
+        let elapsed_ns = self
+            .last_update
+            .map(|last| now.duration_since(last).as_nanos() as f64)
+            .filter(|&elapsed| elapsed > 0.0);
+
+        // The machine's jiffies over the interval, per core
+        let (cpu_total, cores) = read_cpu_total().unwrap_or((0, 1));
+        let core_ticks = self
+            .cpu_total
+            .map(|last| cpu_total.saturating_sub(last) as f64 / cores as f64)
+            .filter(|&ticks| ticks > 0.0);
+
+        let mut previous = HashMap::new();
+        let mut processes = Vec::new();
+
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return processes;
+        };
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+            let dir = entry.path();
+            // Gone between listing /proc and reading it
+            let Some((name, parent, cpu_ticks)) = fs::read_to_string(dir.join("stat")).ok().and_then(|stat| parse_stat(&stat))
+            else {
+                continue;
+            };
+
+            let last = self.previous.remove(&pid);
+            let cpu_percent = match (&last, core_ticks) {
+                (Some(last), Some(ticks)) => cpu_ticks.saturating_sub(last.cpu_ticks) as f64 / ticks * 100.0,
+                _ => 0.0,
+            };
+
+            let drm = read_drm_usage(&dir);
+            let gpu_percent = drm.as_ref().map(|usage| match (&last, elapsed_ns) {
+                (Some(last), Some(elapsed)) => busiest_engine(&usage.engines, &last.gpu_nanoseconds, elapsed),
+                _ => 0.0,
+            });
+
+            let memory_bytes = fs::read_to_string(dir.join("status"))
+                .ok()
+                .and_then(|status| parse_rss(&status))
+                .unwrap_or(0);
+            let command = fs::read(dir.join("cmdline"))
+                .map(|bytes| parse_cmdline(&bytes))
+                .unwrap_or_default();
+
+            processes.push(Process {
+                pid,
+                parent,
+                name,
+                command,
+                cpu_percent: cpu_percent as f32,
+                memory_bytes,
+                gpu_percent,
+                vram_bytes: drm.as_ref().and_then(|usage| usage.vram_bytes),
+            });
+            previous.insert(
+                pid,
+                Previous {
+                    cpu_ticks,
+                    gpu_nanoseconds: drm.map(|usage| usage.engines).unwrap_or_default(),
+                },
+            );
+        }
+
+        self.previous = previous;
+        self.cpu_total = Some(cpu_total);
+        self.last_update = Some(now);
+        processes
+    }
+}
+
+// The name, parent PID and utime + stime from /proc/[pid]/stat. The name
+// is in parentheses and may itself contain spaces or parentheses.
+fn parse_stat(stat: &str) -> Option<(String, u32, u64)> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+
+    // Fields after the name start at the state, field 3 in proc(5)
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    let parent: u32 = fields.get(1)?.parse().ok()?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, parent, utime + stime))
+}
+
+// VmRSS from /proc/[pid]/status; kernel threads have none
+fn parse_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn parse_cmdline(bytes: &[u8]) -> String {
+    bytes
+        .split(|&byte| byte == 0)
+        .filter(|part| !part.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The first line of /proc/stat summed, and how many cores it covers
+fn read_cpu_total() -> Option<(u64, usize)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let total = stat
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .filter_map(|value| value.parse::<u64>().ok())
+        .sum();
+    let cores = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && line.as_bytes().get(3).is_some_and(u8::is_ascii_digit))
+        .count();
+    Some((total, cores.max(1)))
+}
+
+#[derive(Default)]
+struct DrmUsage {
+    // Busy time per engine (gfx, compute, render, video…), summed over the
+    // process's DRM clients
+    engines: HashMap<String, u64>,
+    vram_bytes: Option<u64>,
+}
+
+// The DRM keys of every GPU file the process has open. A client opened
+// once and duplicated shows up under several descriptors with the same
+// drm-client-id, so each client is counted once.
+fn read_drm_usage(dir: &Path) -> Option<DrmUsage> {
+    let descriptors = fs::read_dir(dir.join("fd")).ok()?;
+    let mut usage: Option<DrmUsage> = None;
+    let mut clients = HashSet::new();
+
+    for descriptor in descriptors.flatten() {
+        // Only GPU device nodes, so a browser's thousands of sockets are
+        // skipped without reading their fdinfo
+        let is_gpu = fs::read_link(descriptor.path()).is_ok_and(|target| target.starts_with("/dev/dri/"));
+        if !is_gpu {
+            continue;
+        }
+        let Ok(info) = fs::read_to_string(dir.join("fdinfo").join(descriptor.file_name())) else {
+            continue;
+        };
+
+        let keys = parse_fdinfo(&info);
+        let Some(client) = keys.get("drm-client-id") else {
+            continue;
+        };
+        let driver = keys.get("drm-pdev").or(keys.get("drm-driver")).cloned().unwrap_or_default();
+        if !clients.insert((driver, client.clone())) {
+            continue;
+        }
+
+        let usage = usage.get_or_insert_with(DrmUsage::default);
+        for (key, value) in &keys {
+            if let Some(engine) = key.strip_prefix("drm-engine-")
+                && !engine.starts_with("capacity-")
+                && let Some(nanoseconds) = value.strip_suffix(" ns").and_then(|value| value.trim().parse::<u64>().ok())
+            {
+                *usage.engines.entry(engine.to_string()).or_default() += nanoseconds;
+            }
+        }
+        if let Some(bytes) = keys.get("drm-memory-vram").and_then(|value| parse_size(value)) {
+            *usage.vram_bytes.get_or_insert(0) += bytes;
+        }
+    }
+
+    usage
+}
+
+fn parse_fdinfo(info: &str) -> HashMap<String, String> {
+    info.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+// "123456 KiB" and the like, as the DRM usage stats document them
+fn parse_size(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let number: u64 = parts.next()?.parse().ok()?;
+    let scale = match parts.next() {
+        None => 1,
+        Some("KiB") => 1 << 10,
+        Some("MiB") => 1 << 20,
+        Some("GiB") => 1 << 30,
+        Some(_) => return None,
+    };
+    Some(number * scale)
+}
+
+// Engines run in parallel, so the busiest one is how loaded the GPU is
+// for this process, the way the GPU load bar reads for the whole card
+fn busiest_engine(current: &HashMap<String, u64>, last: &HashMap<String, u64>, elapsed_ns: f64) -> f32 {
+    current
+        .iter()
+        .map(|(engine, &busy)| {
+            let before = last.get(engine).copied().unwrap_or(busy);
+            (busy.saturating_sub(before) as f64 / elapsed_ns * 100.0).min(100.0)
+        })
+        .fold(0.0, f64::max) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn stat_names_with_parentheses() {
+        let stat = "4242 (Web Content) (x) S 4100 4242 4100 0 -1 4194560 51820 0 3 0 1530 270 0 0 20 0 29 0 91822 3061977088 62187";
+        assert_eq!(parse_stat(stat), Some(("Web Content) (x".to_string(), 4100, 1800)));
+        assert_eq!(parse_stat("1 (systemd) S 0 1 1 0 -1 4194560 48000 0 120 0 85 110 0"), Some(("systemd".to_string(), 0, 195)));
+        // Cut short, as when the process exits mid-read
+        assert_eq!(parse_stat("4242 (game) S 4100 4242"), None);
+        assert_eq!(parse_stat(""), None);
+    }
+
+    #[test]
+    fn sizes() {
+        for (value, expected) in [
+            ("4096", Some(4096)),
+            ("524288 KiB", Some(512 << 20)),
+            ("512 MiB", Some(512 << 20)),
+            ("2 GiB", Some(2 << 30)),
+            ("12 kB", None),
+            ("KiB", None),
+        ] {
+            assert_eq!(parse_size(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn fdinfo_keys() {
+        let keys = parse_fdinfo("pos:\t0\nflags:\t02100002\ndrm-driver:\tamdgpu\ndrm-pdev:\t0000:03:00.0\ndrm-engine-gfx:\t1298765 ns\n");
+        assert_eq!(keys.get("drm-driver").map(String::as_str), Some("amdgpu"));
+        assert_eq!(keys.get("drm-pdev").map(String::as_str), Some("0000:03:00.0"));
+        assert_eq!(keys.get("drm-engine-gfx").map(String::as_str), Some("1298765 ns"));
+    }
+
+    // A fake /proc/[pid] with GPU descriptors 3 and 4 sharing one DRM
+    // client, descriptor 5 a second client and 6 an ordinary file
+    #[test]
+    fn drm_clients_counted_once() {
+        let dir = std::env::temp_dir().join(format!("gamer-monitor-{}-fdinfo", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("fd")).unwrap();
+        fs::create_dir_all(dir.join("fdinfo")).unwrap();
+
+        let first = "drm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t7\ndrm-engine-render:\t5000000 ns\ndrm-engine-capacity-render:\t2\ndrm-engine-video:\t1000 ns\ndrm-memory-vram:\t524288 KiB\n";
+        let second = "drm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t8\ndrm-engine-render:\t3000000 ns\ndrm-memory-vram:\t256 MiB\n";
+        for (fd, target, info) in [
+            ("3", "/dev/dri/renderD128", first),
+            ("4", "/dev/dri/renderD128", first),
+            ("5", "/dev/dri/card0", second),
+            ("6", "/home/me/save.dat", "pos:\t0\ndrm-client-id:\t9\ndrm-engine-render:\t9 ns\n"),
+        ] {
+            symlink(target, dir.join("fd").join(fd)).unwrap();
+            fs::write(dir.join("fdinfo").join(fd), info).unwrap();
+        }
+
+        let usage = read_drm_usage(&dir).unwrap();
+        let mut engines: Vec<_> = usage.engines.into_iter().collect();
+        engines.sort();
+        assert_eq!(engines, [("render".to_string(), 8_000_000), ("video".to_string(), 1000)]);
+        assert_eq!(usage.vram_bytes, Some(768 << 20));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn busiest_engine_over_the_interval() {
+        let last = HashMap::from([("gfx".to_string(), 1_000_000_000), ("compute".to_string(), 0)]);
+        let current = HashMap::from([
+            ("gfx".to_string(), 1_750_000_000),
+            ("compute".to_string(), 250_000_000),
+            // Appeared since the last sample: no delta yet
+            ("dec".to_string(), 900_000_000),
+        ]);
+        assert_eq!(busiest_engine(&current, &last, 1e9), 75.0);
+        // Engines that overlap within the interval never read above 100%
+        assert_eq!(busiest_engine(&current, &last, 5e8), 100.0);
+        assert_eq!(busiest_engine(&HashMap::new(), &last, 1e9), 0.0);
+    }
+}
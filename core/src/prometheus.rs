@@ -0,0 +1,265 @@
+use crate::config::Config;
+use crate::http::{self, Response};
+use crate::metrics::{self, Quantity, Reading};
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use std::fmt::Write;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+const PREFIX: &str = "gamer_monitor";
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Serves the latest snapshot on /metrics. The page is rendered on the main
+/// thread whenever a snapshot is published, so a scrape never touches the
+/// sensors and always sees a complete set of readings.
+pub fn start(address: &str, publisher: &Publisher, config: &Config) -> io::Result<SocketAddr> {
+    let page = Arc::new(Mutex::new(None::<String>));
+
+    let update = {
+        let page = page.clone();
+        let config = config.clone();
+        move |snapshot: &Snapshot| {
+            *page.lock().unwrap() = Some(render(snapshot, &config));
+        }
+    };
+    if let Some(snapshot) = publisher.latest() {
+        update(&snapshot);
+    }
+    publisher.subscribe(move |snapshot| {
+        update(snapshot);
+        glib::ControlFlow::Continue
+    });
+
+    http::serve(
+        address,
+        Arc::new(move |request| match request.path.as_str() {
+            "/metrics" => match page.lock().unwrap().clone() {
+                Some(body) => Response::new(200, CONTENT_TYPE, body),
+                None => Response::text(503, "No readings yet\n"),
+            },
+            "/" => Response::text(200, "Gamer Monitor exporter, metrics are at /metrics\n"),
+            _ => Response::not_found(),
+        }),
+    )
+}
+
+/// Renders one snapshot in the OpenMetrics text format. Unavailable readings
+/// are left out rather than exported as NaN.
+pub fn render(snapshot: &Snapshot, config: &Config) -> String {
+    let data = &snapshot.data;
+    let readings = metrics::collect(data, &config.fans);
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# TYPE {} info", PREFIX);
+    let _ = writeln!(out, "# HELP {} Detected hardware and exporter version.", PREFIX);
+    let _ = writeln!(
+        out,
+        "{}_info{{version=\"{}\",cpu=\"{}\",gpu=\"{}\",gpu_vendor=\"{}\"}} 1",
+        PREFIX,
+        escape(env!("CARGO_PKG_VERSION")),
+        escape(&data.cpu_name),
+        escape(&data.gpu_name),
+        metrics::gpu_vendor(&data.gpu_name),
+    );
+
+    let seconds = snapshot
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0);
+    let name = format!("{}_last_sample_timestamp_seconds", PREFIX);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "# UNIT {} seconds", name);
+    let _ = writeln!(out, "# HELP {} Time of the last sensor reading.", name);
+    let _ = writeln!(out, "{} {:.3}", name, seconds);
+
+    // Readings of a family are adjacent in metrics::collect, but a family
+    // can hold several quantities (the GPU fan is RPM or percent)
+    let mut families: Vec<(String, Vec<&Reading>)> = Vec::new();
+    for reading in readings.iter().filter(|r| r.value.is_some()) {
+        let name = metric_name(reading);
+        match families.iter_mut().find(|(family, _)| *family == name) {
+            Some((_, members)) => members.push(reading),
+            None => families.push((name, vec![reading])),
+        }
+    }
+
+    for (name, members) in &families {
+        let unit = unit(members[0].quantity);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "# UNIT {} {}", name, unit);
+        let _ = writeln!(out, "# HELP {} {}", name, help(members[0].family));
+
+        for reading in members {
+            let labels: Vec<String> = reading
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                .collect();
+            let value = scale(reading.quantity, reading.value.unwrap_or_default());
+
+            if labels.is_empty() {
+                let _ = writeln!(out, "{} {}", name, value);
+            } else {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+            }
+        }
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn metric_name(reading: &Reading) -> String {
+    format!("{}_{}_{}", PREFIX, reading.family, unit(reading.quantity))
+}
+
+// Prometheus base units: ratios instead of percentages, bytes instead of MiB
+fn unit(quantity: Quantity) -> &'static str {
+    match quantity {
+        Quantity::Celsius => "celsius",
+        Quantity::Percent => "ratio",
+        Quantity::Rpm => "rpm",
+        Quantity::Watts => "watts",
+        Quantity::Mebibytes | Quantity::Gibibytes => "bytes",
+    }
+}
+
+fn scale(quantity: Quantity, value: f64) -> f64 {
+    match quantity {
+        Quantity::Percent => value / 100.0,
+        Quantity::Mebibytes => (value * 1024.0 * 1024.0).round(),
+        Quantity::Gibibytes => (value * 1024.0 * 1024.0 * 1024.0).round(),
+        _ => value,
+    }
+}
+
+fn help(family: &str) -> &'static str {
+    match family {
+        "cpu_temperature" => "CPU temperature, from the sensor named in the label.",
+        "cpu_load" => "CPU load across all cores.",
+        "gpu_temperature" => "GPU temperature by sensor (edge, hotspot or memory).",
+        "gpu_fan" => "GPU fan speed.",
+        "gpu_power" => "GPU power draw.",
+        "gpu_load" => "GPU utilization.",
+        "gpu_vram_used" => "Video memory in use.",
+        "gpu_vram_total" => "Total video memory.",
+        "nvme_temperature" => "NVMe drive temperature.",
+        "fan_speed" => "Motherboard fan speed.",
+        "ram_total" => "Total system memory.",
+        "ram_used" => "System memory in use.",
+        "ram_free" => "Unused system memory.",
+        "ram_available" => "System memory available for new applications.",
+        "ram_load" => "Share of system memory in use.",
+        _ => "Gamer Monitor reading.",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorData;
+    use std::time::Duration;
+
+    #[test]
+    fn exposition() {
+        let mut config = Config::default();
+        config.fans.cpu = "CPU \"Pump\"".to_string();
+        let mut data = SensorData::new();
+        data.cpu_name = "AMD \"Ryzen\" 7\\5800X3D\nrev 2".to_string();
+        data.cpu_temp = "+61.5°C".to_string();
+        data.cpu_temp_sensor = "Tctl".to_string();
+        data.cpu_usage = f32::NAN;
+        data.gpu_name = "NVIDIA GeForce RTX 3070".to_string();
+        data.gpu_edge = "54".to_string();
+        data.gpu_hotspot = "N/A".to_string();
+        data.gpu_fan = "35 %".to_string();
+        data.gpu_power = "N/A".to_string();
+        data.gpu_vram_used = "4096 MiB".to_string();
+        data.gpu_vram_total = "8192 MiB".to_string();
+        data.gpu_utilization = 97.0;
+        data.nvme_temps = vec!["+38.9°C".to_string()];
+        data.cpu_fan = "1200 RPM".to_string();
+        data.ram_total = 31.25;
+        data.ram_used = 12.5;
+        data.ram_free = f32::INFINITY;
+        data.ram_available = 18.75;
+        data.ram_percent = 40.0;
+        let snapshot = Snapshot {
+            sequence: 1,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_500),
+            data,
+            processes: Arc::default(),
+            diagnostics: Arc::default(),
+        };
+
+        let expected = r#"# TYPE gamer_monitor info
+# HELP gamer_monitor Detected hardware and exporter version.
+gamer_monitor_info{version="VERSION",cpu="AMD \"Ryzen\" 7\\5800X3D\nrev 2",gpu="NVIDIA GeForce RTX 3070",gpu_vendor="nvidia"} 1
+# TYPE gamer_monitor_last_sample_timestamp_seconds gauge
+# UNIT gamer_monitor_last_sample_timestamp_seconds seconds
+# HELP gamer_monitor_last_sample_timestamp_seconds Time of the last sensor reading.
+gamer_monitor_last_sample_timestamp_seconds 1700000000.500
+# TYPE gamer_monitor_cpu_temperature_celsius gauge
+# UNIT gamer_monitor_cpu_temperature_celsius celsius
+# HELP gamer_monitor_cpu_temperature_celsius CPU temperature, from the sensor named in the label.
+gamer_monitor_cpu_temperature_celsius{sensor="Tctl"} 61.5
+# TYPE gamer_monitor_gpu_temperature_celsius gauge
+# UNIT gamer_monitor_gpu_temperature_celsius celsius
+# HELP gamer_monitor_gpu_temperature_celsius GPU temperature by sensor (edge, hotspot or memory).
+gamer_monitor_gpu_temperature_celsius{sensor="edge",gpu="0",vendor="nvidia"} 54
+# TYPE gamer_monitor_gpu_fan_ratio gauge
+# UNIT gamer_monitor_gpu_fan_ratio ratio
+# HELP gamer_monitor_gpu_fan_ratio GPU fan speed.
+gamer_monitor_gpu_fan_ratio{gpu="0",vendor="nvidia"} 0.35
+# TYPE gamer_monitor_gpu_load_ratio gauge
+# UNIT gamer_monitor_gpu_load_ratio ratio
+# HELP gamer_monitor_gpu_load_ratio GPU utilization.
+gamer_monitor_gpu_load_ratio{gpu="0",vendor="nvidia"} 0.97
+# TYPE gamer_monitor_gpu_vram_used_bytes gauge
+# UNIT gamer_monitor_gpu_vram_used_bytes bytes
+# HELP gamer_monitor_gpu_vram_used_bytes Video memory in use.
+gamer_monitor_gpu_vram_used_bytes{gpu="0",vendor="nvidia"} 4294967296
+# TYPE gamer_monitor_gpu_vram_total_bytes gauge
+# UNIT gamer_monitor_gpu_vram_total_bytes bytes
+# HELP gamer_monitor_gpu_vram_total_bytes Total video memory.
+gamer_monitor_gpu_vram_total_bytes{gpu="0",vendor="nvidia"} 8589934592
+# TYPE gamer_monitor_nvme_temperature_celsius gauge
+# UNIT gamer_monitor_nvme_temperature_celsius celsius
+# HELP gamer_monitor_nvme_temperature_celsius NVMe drive temperature.
+gamer_monitor_nvme_temperature_celsius{device="nvme0"} 38.9
+# TYPE gamer_monitor_fan_speed_rpm gauge
+# UNIT gamer_monitor_fan_speed_rpm rpm
+# HELP gamer_monitor_fan_speed_rpm Motherboard fan speed.
+gamer_monitor_fan_speed_rpm{fan="cpu",name="CPU \"Pump\""} 1200
+# TYPE gamer_monitor_ram_total_bytes gauge
+# UNIT gamer_monitor_ram_total_bytes bytes
+# HELP gamer_monitor_ram_total_bytes Total system memory.
+gamer_monitor_ram_total_bytes 33554432000
+# TYPE gamer_monitor_ram_used_bytes gauge
+# UNIT gamer_monitor_ram_used_bytes bytes
+# HELP gamer_monitor_ram_used_bytes System memory in use.
+gamer_monitor_ram_used_bytes 13421772800
+# TYPE gamer_monitor_ram_available_bytes gauge
+# UNIT gamer_monitor_ram_available_bytes bytes
+# HELP gamer_monitor_ram_available_bytes System memory available for new applications.
+gamer_monitor_ram_available_bytes 20132659200
+# TYPE gamer_monitor_ram_load_ratio gauge
+# UNIT gamer_monitor_ram_load_ratio ratio
+# HELP gamer_monitor_ram_load_ratio Share of system memory in use.
+gamer_monitor_ram_load_ratio 0.4
+# EOF
+"#;
+        assert_eq!(render(&snapshot, &config), expected.replace("VERSION", env!("CARGO_PKG_VERSION")));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::config::{Config, PushConfig, PushFormat};
+use crate::metrics::{self, Reading};
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// Stays below the usual 1500 byte MTU, as Telegraf recommends for UDP
+const MAX_DATAGRAM: usize = 1400;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Transport {
+    Udp(String),
+    Tcp(String),
+}
+
+/// Formats each snapshot on the main thread and hands the lines to a worker
+/// thread, so a slow or unreachable endpoint never stalls the window
+pub fn start(endpoint: &str, config: &Config, publisher: &Publisher) -> Result<(), String> {
+    let transport = parse_endpoint(endpoint)?;
+    let push = config.exporter.push.clone();
+    let fans = config.fans.clone();
+    let host = push.host.clone().unwrap_or_else(hostname);
+
+    let (sender, receiver) = mpsc::channel::<Vec<String>>();
+    let buffer = push.buffer.max(1);
+    let description = endpoint.to_string();
+    thread::spawn(move || deliver(receiver, transport, buffer, &description));
+
+    publisher.subscribe(move |snapshot| {
+        let readings = metrics::collect(&snapshot.data, &fans);
+        let lines = match push.format {
+            PushFormat::Influx => influx_lines(snapshot, &readings, &push, &host),
+            PushFormat::Graphite => graphite_lines(snapshot, &readings, &push, &host),
+        };
+
+        match sender.send(lines) {
+            Ok(()) => glib::ControlFlow::Continue,
+            Err(_) => glib::ControlFlow::Break,
+        }
+    });
+
+    Ok(())
+}
+
+fn parse_endpoint(endpoint: &str) -> Result<Transport, String> {
+    let invalid = || format!("invalid push endpoint '{}' (expected udp://host:port or tcp://host:port)", endpoint);
+
+    let (scheme, address) = endpoint.split_once("://").ok_or_else(invalid)?;
+    if address.rsplit_once(':').is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err()) {
+        return Err(invalid());
+    }
+
+    match scheme {
+        "udp" => Ok(Transport::Udp(address.to_string())),
+        "tcp" => Ok(Transport::Tcp(address.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+// InfluxDB line protocol: one line per reading, the reading family as the
+// measurement, its labels as tags and its unit as the field name, e.g.
+// gamer_monitor_gpu_temperature,host=rig,sensor=hotspot,gpu=0,vendor=amd celsius=71 1710408413000000000
+fn influx_lines(snapshot: &Snapshot, readings: &[Reading], push: &PushConfig, host: &str) -> Vec<String> {
+    let nanos = snapshot
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+
+    let mut common = format!(",host={}", influx_escape(host));
+    for (key, value) in &push.tags {
+        common.push_str(&format!(",{}={}", influx_escape(key), influx_escape(value)));
+    }
+
+    readings
+        .iter()
+        .filter_map(|reading| {
+            let value = reading.value?;
+            let mut line = format!("{}_{}{}", push.prefix, reading.family, common);
+            for (key, label) in &reading.labels {
+                if !label.is_empty() {
+                    line.push_str(&format!(",{}={}", key, influx_escape(label)));
+                }
+            }
+            Some(format!("{} {}={} {}", line, reading.quantity.suffix(), value, nanos))
+        })
+        .collect()
+}
+
+fn influx_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+// Graphite plaintext: prefix.host.column value seconds, with any extra tags
+// in the Graphite 1.1 ";key=value" form
+fn graphite_lines(snapshot: &Snapshot, readings: &[Reading], push: &PushConfig, host: &str) -> Vec<String> {
+    let seconds = snapshot
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    let tags: String = push
+        .tags
+        .iter()
+        .map(|(key, value)| format!(";{}={}", graphite_escape(key), graphite_escape(value)))
+        .collect();
+
+    readings
+        .iter()
+        .filter_map(|reading| {
+            let value = reading.value?;
+            Some(format!(
+                "{}.{}.{}{} {} {}",
+                push.prefix,
+                graphite_escape(host),
+                reading.column(),
+                tags,
+                value,
+                seconds
+            ))
+        })
+        .collect()
+}
+
+// Dots separate path segments and whitespace separates fields
+fn graphite_escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c == '.' || c == ';' || c == '=' || c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+// Runs on its own thread. Failed lines stay queued (oldest dropped first
+// once the buffer is full) and are retried with exponential backoff.
+fn deliver(receiver: Receiver<Vec<String>>, transport: Transport, buffer: usize, description: &str) {
+    let mut pending: VecDeque<String> = VecDeque::new();
+    let mut connection: Option<Connection> = None;
+    let mut backoff = Duration::from_secs(1);
+    let mut retry_at = Instant::now();
+    let mut failing = false;
+    let mut dropped = 0usize;
+
+    while let Ok(lines) = receiver.recv() {
+        pending.extend(lines);
+        while pending.len() > buffer {
+            pending.pop_front();
+            dropped += 1;
+        }
+
+        if Instant::now() < retry_at {
+            continue;
+        }
+
+        let result = match connection.as_mut() {
+            Some(connection) => send(connection, &pending),
+            None => connect(&transport).and_then(|mut fresh| {
+                let result = send(&mut fresh, &pending);
+                connection = Some(fresh);
+                result
+            }),
+        };
+
+        match result {
+            Ok(()) => {
+                pending.clear();
+                backoff = Duration::from_secs(1);
+                if failing {
+                    log::info!("Push to {} resumed ({} lines dropped meanwhile)", description, dropped);
+                    failing = false;
+                    dropped = 0;
+                }
+            }
+            Err(err) => {
+                connection = None;
+                if !failing {
+                    log::warn!("Push to {} failed, retrying in the background: {}", description, err);
+                    failing = true;
+                }
+                retry_at = Instant::now() + backoff;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn connect(transport: &Transport) -> std::io::Result<Connection> {
+    match transport {
+        Transport::Udp(address) => {
+            let target = address
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| std::io::Error::other("no address to send to"))?;
+            let local = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(target)?;
+            Ok(Connection::Udp(socket))
+        }
+        Transport::Tcp(address) => {
+            let mut last_error = None;
+            for addr in address.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                    Ok(stream) => {
+                        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                        return Ok(Connection::Tcp(stream));
+                    }
+                    Err(err) => last_error = Some(err),
+                }
+            }
+            Err(last_error.unwrap_or_else(|| std::io::Error::other("no address to connect to")))
+        }
+    }
+}
+
+fn send(connection: &mut Connection, lines: &VecDeque<String>) -> std::io::Result<()> {
+    match connection {
+        Connection::Tcp(stream) => {
+            let mut payload = String::new();
+            for line in lines {
+                payload.push_str(line);
+                payload.push('\n');
+            }
+            stream.write_all(payload.as_bytes())?;
+            stream.flush()
+        }
+        Connection::Udp(socket) => {
+            // Batch lines into datagrams without splitting any line
+            let mut datagram = String::new();
+            for line in lines {
+                if !datagram.is_empty() && datagram.len() + line.len() + 1 > MAX_DATAGRAM {
+                    socket.send(datagram.as_bytes())?;
+                    datagram.clear();
+                }
+                datagram.push_str(line);
+                datagram.push('\n');
+            }
+            if !datagram.is_empty() {
+                socket.send(datagram.as_bytes())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorData;
+    use std::sync::Arc;
+
+    fn sample() -> (Snapshot, Vec<Reading>, PushConfig) {
+        let mut config = Config::default();
+        config.fans.cpu = "CPU fan=1".to_string();
+        config.exporter.push.tags.insert("rack 1".to_string(), "a,b=c".to_string());
+        config.exporter.push.tags.insert("zone".to_string(), "eu.west;1".to_string());
+
+        let mut data = SensorData::new();
+        data.cpu_temp = "+61.5°C".to_string();
+        data.gpu_name = "AMD Radeon RX 6800 XT".to_string();
+        data.gpu_edge = "54".to_string();
+        data.gpu_hotspot = "N/A".to_string();
+        data.gpu_fan = "1500 RPM".to_string();
+        data.cpu_fan = "1200 RPM".to_string();
+        data.ram_used = 4.5;
+
+        // A few readings are enough to cover tags, labels and units
+        let keys = ["cpu_temperature", "gpu_edge", "gpu_hotspot", "gpu_fan", "fan_cpu", "ram_used"];
+        let readings = metrics::collect(&data, &config.fans)
+            .into_iter()
+            .filter(|reading| keys.contains(&reading.key.as_str()))
+            .collect();
+        let snapshot = Snapshot {
+            sequence: 1,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_500),
+            data,
+            processes: Arc::default(),
+            diagnostics: Arc::default(),
+        };
+        (snapshot, readings, config.exporter.push)
+    }
+
+    #[test]
+    fn influx() {
+        let (snapshot, readings, push) = sample();
+        let tags = r"host=my\ rig.local,rack\ 1=a\,b\=c,zone=eu.west;1";
+        let expected = [
+            format!("gamer_monitor_cpu_temperature,{} celsius=61.5 1700000000500000000", tags),
+            format!("gamer_monitor_gpu_temperature,{},sensor=edge,gpu=0,vendor=amd celsius=54 1700000000500000000", tags),
+            format!("gamer_monitor_gpu_fan,{},gpu=0,vendor=amd rpm=1500 1700000000500000000", tags),
+            format!(r"gamer_monitor_fan_speed,{},fan=cpu,name=CPU\ fan\=1 rpm=1200 1700000000500000000", tags),
+            format!("gamer_monitor_ram_used,{} gibibytes=4.5 1700000000500000000", tags),
+        ];
+        assert_eq!(influx_lines(&snapshot, &readings, &push, "my rig.local"), expected);
+    }
+
+    #[test]
+    fn graphite() {
+        let (snapshot, readings, push) = sample();
+        let expected = [
+            "gamer_monitor.my_rig_local.cpu_temperature_celsius;rack_1=a,b_c;zone=eu_west_1 61.5 1700000000",
+            "gamer_monitor.my_rig_local.gpu_edge_celsius;rack_1=a,b_c;zone=eu_west_1 54 1700000000",
+            "gamer_monitor.my_rig_local.gpu_fan_rpm;rack_1=a,b_c;zone=eu_west_1 1500 1700000000",
+            "gamer_monitor.my_rig_local.fan_cpu_rpm;rack_1=a,b_c;zone=eu_west_1 1200 1700000000",
+            "gamer_monitor.my_rig_local.ram_used_gibibytes;rack_1=a,b_c;zone=eu_west_1 4.5 1700000000",
+        ];
+        assert_eq!(graphite_lines(&snapshot, &readings, &push, "my rig.local"), expected);
+    }
+
+    #[test]
+    fn endpoints() {
+        assert!(matches!(parse_endpoint("udp://graphite:2003"), Ok(Transport::Udp(address)) if address == "graphite:2003"));
+        assert!(matches!(parse_endpoint("tcp://[::1]:8094"), Ok(Transport::Tcp(address)) if address == "[::1]:8094"));
+        for endpoint in ["graphite:2003", "http://graphite:2003", "udp://:2003", "udp://graphite", "tcp://graphite:port"] {
+            assert!(parse_endpoint(endpoint).is_err(), "{}", endpoint);
+        }
+    }
+}
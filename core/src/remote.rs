@@ -0,0 +1,80 @@
+use crate::sensors::SensorData;
+use crate::client::{Client, Error};
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Agents publish every couple of seconds, so this long without a snapshot
+// means the machine or the network went away
+const TIMEOUT: Duration = Duration::from_secs(30);
+const RETRY: Duration = Duration::from_secs(5);
+
+pub enum Event {
+    Connected,
+    Snapshot { timestamp: SystemTime, data: Box<SensorData> },
+    // Why the connection failed or ended; a new attempt follows shortly
+    Disconnected(String),
+}
+
+/// Follows one agent on a background thread, reconnecting until the
+/// receiver is dropped. Events are read on the main thread.
+pub fn watch(address: String, token: Option<String>) -> Receiver<Event> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        loop {
+            let reason = match follow(&address, token.as_deref(), &sender) {
+                Ok(()) => "the agent closed the connection".to_string(),
+                Err(err) => err.to_string(),
+            };
+            if sender.send(Event::Disconnected(reason)).is_err() {
+                return;
+            }
+            thread::sleep(RETRY);
+        }
+    });
+
+    receiver
+}
+
+fn follow(address: &str, token: Option<&str>, sender: &Sender<Event>) -> Result<(), Error> {
+    let mut client = Client::connect_tcp(address, TIMEOUT)?;
+    match token {
+        Some(token) => client.authenticate(token)?,
+        None => client.hello()?,
+    };
+    let _ = sender.send(Event::Connected);
+
+    // Show the current readings without waiting for the next sample
+    match client.snapshot() {
+        Ok(document) => send(&document, sender),
+        Err(Error::Remote { .. }) => {}
+        Err(err) => return Err(err),
+    }
+
+    for notification in client.subscribe()? {
+        let notification = notification?;
+        if notification.method == "snapshot" {
+            send(&notification.params, sender);
+        }
+    }
+    Ok(())
+}
+
+// Documents from agents without sensor data (older versions) are skipped
+fn send(document: &Value, sender: &Sender<Event>) {
+    let Some(data) = document
+        .get("sensors")
+        .and_then(|sensors| serde_json::from_value::<SensorData>(sensors.clone()).ok())
+    else {
+        return;
+    };
+    let timestamp = document
+        .get("unix_time")
+        .and_then(Value::as_f64)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .map_or_else(SystemTime::now, |elapsed| UNIX_EPOCH + elapsed);
+
+    let _ = sender.send(Event::Snapshot { timestamp, data: Box::new(data) });
+}
@@ -0,0 +1,834 @@
+use crate::capture::Source;
+use crate::config::Config;
+use crate::diagnostics::{self, Status};
+use crate::metrics;
+use crate::sampler::Snapshot;
+use crate::timestamp;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The formats a report can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "markdown" | "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// The format of a file going by its extension: JSON or HTML, otherwise Markdown
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => Format::Json,
+            Some("html" | "htm") => Format::Html,
+            _ => Format::Markdown,
+        }
+    }
+}
+
+/// Everything about this machine a support ticket or forum post needs: the
+/// hardware, its firmware and drivers, and what the sensors read right now.
+/// Fields that could not be read are `None` or empty.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub generated: String,
+    pub version: String,
+    pub system: System,
+    pub board: Board,
+    pub cpu: Cpu,
+    pub gpus: Vec<Gpu>,
+    pub memory: Memory,
+    pub drives: Vec<Drive>,
+    /// The chips under /sys/class/hwmon
+    pub hwmon: Vec<Chip>,
+    /// The chips `sensors` listed
+    pub sensors_chips: Vec<String>,
+    pub readings: Vec<Reading>,
+    /// Why readings are blank, as on the Diagnostics page
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct System {
+    pub distribution: Option<String>,
+    pub kernel: Option<String>,
+    pub architecture: String,
+    /// From `glxinfo -B`, the userspace driver of AMD and Intel graphics
+    pub mesa: Option<String>,
+}
+
+/// From /sys/class/dmi/id
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Board {
+    pub system_vendor: Option<String>,
+    pub system_product: Option<String>,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub revision: Option<String>,
+    pub bios_vendor: Option<String>,
+    pub bios_version: Option<String>,
+    pub bios_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Cpu {
+    pub model: String,
+    pub vendor: Option<String>,
+    pub sockets: usize,
+    pub cores: usize,
+    pub threads: usize,
+    pub microcode: Option<String>,
+    pub cache: Option<String>,
+    pub max_mhz: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Gpu {
+    /// The PCI address, e.g. "03:00.0"
+    pub slot: String,
+    pub name: String,
+    pub driver: Option<String>,
+    pub driver_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Memory {
+    pub total_mib: Option<u64>,
+    pub swap_mib: Option<u64>,
+    /// How many slots the board has, when udev knows
+    pub slots: Option<usize>,
+    /// The installed modules, from the udev properties of the DMI device
+    pub modules: Vec<Module>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Module {
+    pub locator: String,
+    pub size_mib: Option<u64>,
+    pub kind: Option<String>,
+    pub speed_mts: Option<u32>,
+    pub configured_speed_mts: Option<u32>,
+    pub manufacturer: Option<String>,
+    pub part_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Drive {
+    pub name: String,
+    pub model: Option<String>,
+    pub kind: &'static str,
+    pub size_bytes: u64,
+    pub firmware: Option<String>,
+    pub removable: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Chip {
+    pub hwmon: String,
+    pub name: String,
+    /// The device it belongs to, e.g. "0000:03:00.0" or "nct6775.656"
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reading {
+    pub component: &'static str,
+    pub name: String,
+    pub key: String,
+    /// In base units, see `unit`
+    pub value: Option<f64>,
+    pub unit: &'static str,
+    /// The value in the units of the configuration
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub reading: &'static str,
+    pub source: String,
+    pub status: &'static str,
+    pub details: String,
+}
+
+// One part of the report, the same in Markdown and HTML
+struct Section {
+    title: &'static str,
+    fields: Vec<(&'static str, String)>,
+    table: Option<(&'static [&'static str], Vec<Vec<String>>)>,
+    note: Option<&'static str>,
+}
+
+impl Report {
+    /// Gathers the report from this machine, with the readings of a snapshot
+    /// taken from the same source
+    pub fn gather(source: &mut dyn Source, snapshot: &Snapshot, config: &Config) -> Report {
+        let kernel = read(source, "/proc/sys/kernel/osrelease");
+
+        let mut cpu = source.read_file("/proc/cpuinfo").map(|cpuinfo| parse_cpuinfo(&cpuinfo)).unwrap_or_default();
+        cpu.model = snapshot.data.cpu_name.clone();
+        cpu.max_mhz = read(source, "/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq")
+            .and_then(|khz| khz.parse::<u32>().ok())
+            .map(|khz| khz / 1000);
+
+        let readings = metrics::collect(&snapshot.data, &config.fans)
+            .into_iter()
+            .map(|reading| Reading {
+                component: reading.component.title(),
+                text: reading.value.map(|value| config.units.reading(reading.quantity, value)),
+                name: reading.title,
+                key: reading.key,
+                value: reading.value,
+                unit: reading.quantity.suffix(),
+            })
+            .collect();
+
+        let problems = diagnostics::explain(&snapshot.data, &snapshot.diagnostics)
+            .into_iter()
+            .filter(|finding| finding.status != Status::Ok)
+            .map(|finding| Problem {
+                reading: finding.reading,
+                source: finding.source,
+                status: finding.status.label(),
+                details: [finding.error, finding.hint].into_iter().flatten().collect::<Vec<_>>().join(". "),
+            })
+            .collect();
+
+        Report {
+            generated: timestamp::rfc3339(SystemTime::now()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            system: System {
+                distribution: source.read_file("/etc/os-release").ok().and_then(|text| parse_os_release(&text)),
+                architecture: std::env::consts::ARCH.to_string(),
+                mesa: source.run("glxinfo", &["-B"]).ok().filter(|output| output.success).and_then(|output| parse_mesa(&output.stdout)),
+                kernel: kernel.clone(),
+            },
+            board: Board {
+                system_vendor: read(source, "/sys/class/dmi/id/sys_vendor"),
+                system_product: read(source, "/sys/class/dmi/id/product_name"),
+                vendor: read(source, "/sys/class/dmi/id/board_vendor"),
+                model: read(source, "/sys/class/dmi/id/board_name"),
+                revision: read(source, "/sys/class/dmi/id/board_version"),
+                bios_vendor: read(source, "/sys/class/dmi/id/bios_vendor"),
+                bios_version: read(source, "/sys/class/dmi/id/bios_version"),
+                bios_date: read(source, "/sys/class/dmi/id/bios_date"),
+            },
+            cpu,
+            gpus: gpus(source, snapshot, kernel.as_deref()),
+            memory: memory(source),
+            drives: drives(source),
+            hwmon: hwmon(source),
+            sensors_chips: snapshot.data.chips.clone(),
+            readings,
+            problems,
+        }
+    }
+
+    pub fn render(&self, format: Format, config: &Config) -> String {
+        match format {
+            Format::Markdown => self.markdown(config),
+            Format::Json => serde_json::to_string_pretty(self).unwrap_or_default() + "\n",
+            Format::Html => self.html(config),
+        }
+    }
+
+    fn sections(&self, config: &Config) -> Vec<Section> {
+        let system = &self.system;
+        let board = &self.board;
+        let cpu = &self.cpu;
+        let memory = &self.memory;
+
+        let modules = memory
+            .modules
+            .iter()
+            .map(|module| {
+                vec![
+                    module.locator.clone(),
+                    module.size_mib.map(|mib| config.units.bytes(mib * 1024 * 1024)).unwrap_or_default(),
+                    module.kind.clone().unwrap_or_default(),
+                    match (module.speed_mts, module.configured_speed_mts) {
+                        (Some(rated), Some(configured)) if configured != rated => format!("{} MT/s (rated {})", configured, rated),
+                        (_, Some(speed)) | (Some(speed), None) => format!("{} MT/s", speed),
+                        (None, None) => String::new(),
+                    },
+                    module.manufacturer.clone().unwrap_or_default(),
+                    module.part_number.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        vec![
+            Section {
+                title: "System",
+                fields: vec![
+                    ("Distribution", known(&system.distribution)),
+                    ("Kernel", known(&system.kernel)),
+                    ("Architecture", system.architecture.clone()),
+                    ("Mesa", known(&system.mesa)),
+                ],
+                table: None,
+                note: None,
+            },
+            Section {
+                title: "Board",
+                fields: vec![
+                    ("System", join(&[&board.system_vendor, &board.system_product])),
+                    ("Board", join(&[&board.vendor, &board.model, &board.revision])),
+                    ("BIOS", join(&[&board.bios_vendor, &board.bios_version])),
+                    ("BIOS date", known(&board.bios_date)),
+                ],
+                table: None,
+                note: None,
+            },
+            Section {
+                title: "CPU",
+                fields: vec![
+                    ("Model", if cpu.model.is_empty() { "unknown".to_string() } else { cpu.model.clone() }),
+                    ("Vendor", known(&cpu.vendor)),
+                    ("Topology", format!("{} socket(s), {} core(s), {} thread(s)", cpu.sockets, cpu.cores, cpu.threads)),
+                    ("Microcode", known(&cpu.microcode)),
+                    ("Cache", known(&cpu.cache)),
+                    ("Maximum clock", cpu.max_mhz.map(|mhz| format!("{} MHz", mhz)).unwrap_or_else(|| "unknown".to_string())),
+                ],
+                table: None,
+                note: None,
+            },
+            Section {
+                title: "GPUs",
+                fields: Vec::new(),
+                table: Some((
+                    &["Slot", "Name", "Driver", "Driver version"],
+                    self.gpus
+                        .iter()
+                        .map(|gpu| vec![gpu.slot.clone(), gpu.name.clone(), known(&gpu.driver), known(&gpu.driver_version)])
+                        .collect(),
+                )),
+                note: self.gpus.is_empty().then_some("No GPU found: lspci is not installed or lists none."),
+            },
+            Section {
+                title: "Memory",
+                fields: vec![
+                    ("Total", memory.total_mib.map(|mib| config.units.bytes(mib * 1024 * 1024)).unwrap_or_else(|| "unknown".to_string())),
+                    ("Swap", memory.swap_mib.map(|mib| config.units.bytes(mib * 1024 * 1024)).unwrap_or_else(|| "unknown".to_string())),
+                    ("Slots", memory.slots.map(|slots| format!("{} used of {}", memory.modules.len(), slots)).unwrap_or_else(|| "unknown".to_string())),
+                ],
+                table: Some((&["Slot", "Size", "Type", "Speed", "Manufacturer", "Part number"], modules)),
+                note: memory.modules.is_empty().then_some("No module details: udevadm did not report any memory devices."),
+            },
+            Section {
+                title: "Drives",
+                fields: Vec::new(),
+                table: Some((
+                    &["Device", "Model", "Type", "Size", "Firmware"],
+                    self.drives
+                        .iter()
+                        .map(|drive| {
+                            vec![
+                                drive.name.clone(),
+                                known(&drive.model),
+                                if drive.removable { format!("{} (removable)", drive.kind) } else { drive.kind.to_string() },
+                                config.units.bytes(drive.size_bytes),
+                                known(&drive.firmware),
+                            ]
+                        })
+                        .collect(),
+                )),
+                note: None,
+            },
+            Section {
+                title: "Sensor chips",
+                fields: vec![(
+                    "Listed by sensors",
+                    if self.sensors_chips.is_empty() { "none".to_string() } else { self.sensors_chips.join(", ") },
+                )],
+                table: Some((
+                    &["hwmon", "Chip", "Device"],
+                    self.hwmon.iter().map(|chip| vec![chip.hwmon.clone(), chip.name.clone(), known(&chip.device)]).collect(),
+                )),
+                note: None,
+            },
+            Section {
+                title: "Readings",
+                fields: Vec::new(),
+                table: Some((
+                    &["Component", "Reading", "Value"],
+                    self.readings
+                        .iter()
+                        .map(|reading| vec![reading.component.to_string(), reading.name.clone(), reading.text.clone().unwrap_or_else(|| "unavailable".to_string())])
+                        .collect(),
+                )),
+                note: None,
+            },
+            Section {
+                title: "Problems",
+                fields: Vec::new(),
+                table: (!self.problems.is_empty()).then(|| {
+                    (
+                        &["Reading", "Source", "Status", "Details"][..],
+                        self.problems
+                            .iter()
+                            .map(|problem| vec![problem.reading.to_string(), problem.source.clone(), problem.status.to_string(), problem.details.clone()])
+                            .collect(),
+                    )
+                }),
+                note: self.problems.is_empty().then_some("None: every reading is available."),
+            },
+        ]
+    }
+
+    fn markdown(&self, config: &Config) -> String {
+        let mut out = format!("# Gamer Monitor hardware report\n\nGenerated {} by Gamer Monitor {}.\n", self.generated, self.version);
+
+        for section in self.sections(config) {
+            let _ = write!(out, "\n## {}\n\n", section.title);
+            for (name, value) in &section.fields {
+                let _ = writeln!(out, "- **{}:** {}", name, markdown_cell(value));
+            }
+            if !section.fields.is_empty() {
+                out.push('\n');
+            }
+            if let Some((columns, rows)) = &section.table
+                && !rows.is_empty()
+            {
+                let _ = writeln!(out, "| {} |", columns.join(" | "));
+                let _ = writeln!(out, "|{}", " --- |".repeat(columns.len()));
+                for row in rows {
+                    let _ = writeln!(out, "| {} |", row.iter().map(|cell| markdown_cell(cell)).collect::<Vec<_>>().join(" | "));
+                }
+                out.push('\n');
+            }
+            if let Some(note) = section.note {
+                let _ = write!(out, "{}\n\n", note);
+            }
+            // One blank line between sections
+            while out.ends_with("\n\n") {
+                out.pop();
+            }
+        }
+        out
+    }
+
+    fn html(&self, config: &Config) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Gamer Monitor hardware report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em auto; max-width: 60em; padding: 0 1em; }\n\
+             table { border-collapse: collapse; margin: 0.5em 0 1em; }\n\
+             th, td { border: 1px solid #ccc; padding: 0.25em 0.6em; text-align: left; vertical-align: top; }\n\
+             th { background: #f0f0f0; }\n\
+             dl { display: grid; grid-template-columns: max-content auto; gap: 0.25em 1em; }\n\
+             dt { font-weight: bold; }\n\
+             dd { margin: 0; }\n\
+             </style>\n</head>\n<body>\n<h1>Gamer Monitor hardware report</h1>\n",
+        );
+        let _ = writeln!(out, "<p>Generated {} by Gamer Monitor {}.</p>", escape(&self.generated), escape(&self.version));
+
+        for section in self.sections(config) {
+            let _ = writeln!(out, "<h2>{}</h2>", escape(section.title));
+            if !section.fields.is_empty() {
+                out.push_str("<dl>\n");
+                for (name, value) in &section.fields {
+                    let _ = writeln!(out, "<dt>{}</dt><dd>{}</dd>", escape(name), escape(value));
+                }
+                out.push_str("</dl>\n");
+            }
+            if let Some((columns, rows)) = &section.table
+                && !rows.is_empty()
+            {
+                out.push_str("<table>\n<tr>");
+                for column in columns.iter() {
+                    let _ = write!(out, "<th>{}</th>", escape(column));
+                }
+                out.push_str("</tr>\n");
+                for row in rows {
+                    out.push_str("<tr>");
+                    for cell in row {
+                        let _ = write!(out, "<td>{}</td>", escape(cell));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+            if let Some(note) = section.note {
+                let _ = writeln!(out, "<p>{}</p>", escape(note));
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+// A sysfs or procfs file holding one value, None when missing or blank
+fn read(source: &mut dyn Source, path: &str) -> Option<String> {
+    source.read_file(path).ok().map(|text| text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn known(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "unknown".to_string())
+}
+
+fn join(values: &[&Option<String>]) -> String {
+    let known: Vec<&str> = values.iter().filter_map(|value| value.as_deref()).collect();
+    if known.is_empty() { "unknown".to_string() } else { known.join(" ") }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// The names of the entries of a sysfs directory, sorted so hwmon10 follows hwmon9
+fn entries(path: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    names.sort_by_key(|name| {
+        let digits = name.trim_start_matches(|c: char| !c.is_ascii_digit());
+        (name[..name.len() - digits.len()].to_string(), digits.parse::<u64>().unwrap_or(0), name.clone())
+    });
+    names
+}
+
+fn link_name(path: &str) -> Option<String> {
+    fs::read_link(path).ok().and_then(|target| target.file_name().map(|name| name.to_string_lossy().into_owned()))
+}
+
+fn parse_os_release(text: &str) -> Option<String> {
+    text.lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim().trim_matches('"').to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// "OpenGL version string: 4.6 (Compatibility Profile) Mesa 24.0.5-1ubuntu1"
+fn parse_mesa(glxinfo: &str) -> Option<String> {
+    glxinfo.lines().filter(|line| line.contains("version string")).find_map(|line| {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let index = words.iter().position(|word| *word == "Mesa")?;
+        words.get(index + 1).map(|version| version.to_string())
+    })
+}
+
+// /proc/cpuinfo has one block per thread. Threads of the same core share a
+// physical and core id; ARM and some virtual machines have neither.
+fn parse_cpuinfo(cpuinfo: &str) -> Cpu {
+    let mut cpu = Cpu::default();
+    let mut sockets = BTreeSet::new();
+    let mut cores = BTreeSet::new();
+    let mut cores_per_socket = None;
+
+    for block in cpuinfo.split("\n\n").filter(|block| block.contains("processor")) {
+        cpu.threads += 1;
+        let field = |name: &str| {
+            block.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                (key.trim() == name).then(|| value.trim().to_string())
+            })
+        };
+        if let Some(socket) = field("physical id") {
+            if let Some(core) = field("core id") {
+                cores.insert((socket.clone(), core));
+            }
+            sockets.insert(socket);
+        }
+        cores_per_socket = cores_per_socket.or_else(|| field("cpu cores").and_then(|count| count.parse::<usize>().ok()));
+        cpu.vendor = cpu.vendor.take().or_else(|| field("vendor_id"));
+        cpu.microcode = cpu.microcode.take().or_else(|| field("microcode"));
+        cpu.cache = cpu.cache.take().or_else(|| field("cache size"));
+    }
+
+    cpu.sockets = sockets.len().max(1);
+    cpu.cores = match (cores.len(), cores_per_socket) {
+        (0, Some(per_socket)) => per_socket * cpu.sockets,
+        (0, None) => cpu.threads,
+        (count, _) => count,
+    };
+    cpu
+}
+
+// The display controllers in `lspci -k`, each followed by indented lines
+// such as "Kernel driver in use: amdgpu"
+fn parse_lspci_gpus(lspci: &str) -> Vec<Gpu> {
+    let mut gpus: Vec<Gpu> = Vec::new();
+    let mut in_gpu = false;
+
+    for line in lspci.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if in_gpu
+                && let Some(driver) = line.trim().strip_prefix("Kernel driver in use:")
+                && let Some(gpu) = gpus.last_mut()
+            {
+                gpu.driver = Some(driver.trim().to_string());
+            }
+            continue;
+        }
+
+        let Some((slot, rest)) = line.split_once(' ') else {
+            in_gpu = false;
+            continue;
+        };
+        let Some((class, name)) = rest.split_once(": ") else {
+            in_gpu = false;
+            continue;
+        };
+        in_gpu = ["VGA compatible controller", "3D controller", "Display controller"].contains(&class);
+        if in_gpu {
+            let name = match name.rfind(" (rev ") {
+                Some(end) => &name[..end],
+                None => name,
+            };
+            gpus.push(Gpu { slot: slot.to_string(), name: name.to_string(), ..Default::default() });
+        }
+    }
+    gpus
+}
+
+fn gpus(source: &mut dyn Source, snapshot: &Snapshot, kernel: Option<&str>) -> Vec<Gpu> {
+    let mut gpus = match source.run("lspci", &["-k"]) {
+        Ok(output) if output.success => parse_lspci_gpus(&output.stdout),
+        _ => Vec::new(),
+    };
+    // Without lspci, at least the one the dashboard shows
+    if gpus.is_empty() && !snapshot.data.gpu_name.is_empty() && snapshot.data.gpu_name != "Unknown GPU" {
+        gpus.push(Gpu { name: snapshot.data.gpu_name.clone(), ..Default::default() });
+    }
+
+    let nvidia = source
+        .run("nvidia-smi", &["--query-gpu=driver_version", "--format=csv,noheader"])
+        .ok()
+        .filter(|output| output.success)
+        .and_then(|output| output.stdout.lines().next().map(|line| line.trim().to_string()))
+        .filter(|version| !version.is_empty());
+
+    for gpu in &mut gpus {
+        let Some(driver) = gpu.driver.clone() else {
+            continue;
+        };
+        // Out-of-tree modules have a version of their own, in-tree ones are the kernel's
+        gpu.driver_version = match driver.as_str() {
+            "nvidia" => nvidia.clone(),
+            _ => None,
+        }
+        .or_else(|| read(source, &format!("/sys/module/{}/version", driver)))
+        .or_else(|| kernel.map(|kernel| format!("in-kernel ({})", kernel)));
+    }
+    gpus
+}
+
+fn parse_meminfo_mib(meminfo: &str, name: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix(name)?.strip_prefix(':')?;
+        value.split_whitespace().next()?.parse::<u64>().ok().map(|kib| kib / 1024)
+    })
+}
+
+// The MEMORY_DEVICE_<n>_* properties udev reads from the SMBIOS tables for
+// the DMI device, which unlike dmidecode does not need root
+fn parse_memory_modules(properties: &str) -> (Option<usize>, Vec<Module>) {
+    let mut slots = None;
+    let mut devices: Vec<(usize, Vec<(&str, &str)>)> = Vec::new();
+
+    for line in properties.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key == "MEMORY_ARRAY_NUM_DEVICES" {
+            slots = value.parse().ok();
+            continue;
+        }
+        let Some((index, field)) = key.strip_prefix("MEMORY_DEVICE_").and_then(|rest| rest.split_once('_')) else {
+            continue;
+        };
+        let Ok(index) = index.parse::<usize>() else {
+            continue;
+        };
+        match devices.iter_mut().find(|(device, _)| *device == index) {
+            Some((_, fields)) => fields.push((field, value)),
+            None => devices.push((index, vec![(field, value)])),
+        }
+    }
+
+    let modules = devices
+        .iter()
+        .filter(|(_, fields)| !fields.contains(&("PRESENT", "0")))
+        .map(|(index, fields)| {
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.trim().to_string())
+                    .filter(|value| !value.is_empty() && value != "Unknown")
+            };
+            Module {
+                locator: field("LOCATOR").unwrap_or_else(|| format!("#{}", index)),
+                size_mib: field("SIZE").and_then(|bytes| bytes.parse::<u64>().ok()).map(|bytes| bytes / 1024 / 1024),
+                kind: field("TYPE"),
+                speed_mts: field("SPEED_MTS").and_then(|speed| speed.parse().ok()),
+                configured_speed_mts: field("CONFIGURED_SPEED_MTS").and_then(|speed| speed.parse().ok()),
+                manufacturer: field("MANUFACTURER"),
+                part_number: field("PART_NUMBER"),
+            }
+        })
+        .collect();
+    (slots, modules)
+}
+
+fn memory(source: &mut dyn Source) -> Memory {
+    let meminfo = source.read_file("/proc/meminfo").unwrap_or_default();
+    let (slots, modules) = match source.run("udevadm", &["info", "--query=property", "--path=/sys/devices/virtual/dmi/id"]) {
+        Ok(output) if output.success => parse_memory_modules(&output.stdout),
+        _ => (None, Vec::new()),
+    };
+    Memory {
+        total_mib: parse_meminfo_mib(&meminfo, "MemTotal"),
+        swap_mib: parse_meminfo_mib(&meminfo, "SwapTotal"),
+        slots,
+        modules,
+    }
+}
+
+fn drives(source: &mut dyn Source) -> Vec<Drive> {
+    entries("/sys/block")
+        .into_iter()
+        // Not drives, but memory, files and device mapper volumes on top of drives
+        .filter(|name| !["loop", "ram", "zram", "dm-", "md"].iter().any(|prefix| name.starts_with(prefix)))
+        .filter_map(|name| {
+            let sectors = read(source, &format!("/sys/block/{}/size", name))?.parse::<u64>().ok()?;
+            if sectors == 0 {
+                return None;
+            }
+            let rotational = read(source, &format!("/sys/block/{}/queue/rotational", name)).as_deref() == Some("1");
+            Some(Drive {
+                model: read(source, &format!("/sys/block/{}/device/model", name)),
+                kind: if name.starts_with("nvme") {
+                    "NVMe SSD"
+                } else if rotational {
+                    "HDD"
+                } else {
+                    "SSD"
+                },
+                // Sectors in /sys/block are always 512 bytes
+                size_bytes: sectors * 512,
+                firmware: read(source, &format!("/sys/block/{}/device/firmware_rev", name))
+                    .or_else(|| read(source, &format!("/sys/block/{}/device/rev", name))),
+                removable: read(source, &format!("/sys/block/{}/removable", name)).as_deref() == Some("1"),
+                name,
+            })
+        })
+        .collect()
+}
+
+fn hwmon(source: &mut dyn Source) -> Vec<Chip> {
+    entries("/sys/class/hwmon")
+        .into_iter()
+        .map(|hwmon| Chip {
+            name: read(source, &format!("/sys/class/hwmon/{}/name", hwmon)).unwrap_or_else(|| "unknown".to_string()),
+            device: link_name(&format!("/sys/class/hwmon/{}/device", hwmon)),
+            hwmon,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_topology() {
+        let thread = |processor: usize, core: usize| {
+            format!(
+                "processor\t: {}\nvendor_id\t: AuthenticAMD\nmodel name\t: AMD Ryzen 7 5800X3D 8-Core Processor\nmicrocode\t: 0xa201205\ncache size\t: 512 KB\nphysical id\t: 0\ncore id\t\t: {}\ncpu cores\t: 8\n",
+                processor, core
+            )
+        };
+        let cpuinfo = (0..16).map(|processor| thread(processor, processor % 8)).collect::<Vec<_>>().join("\n");
+        let cpu = parse_cpuinfo(&cpuinfo);
+        assert_eq!((cpu.sockets, cpu.cores, cpu.threads), (1, 8, 16));
+        assert_eq!(cpu.microcode.as_deref(), Some("0xa201205"));
+        assert_eq!(cpu.vendor.as_deref(), Some("AuthenticAMD"));
+
+        // No physical or core id, as on ARM
+        let cpu = parse_cpuinfo("processor\t: 0\nBogoMIPS\t: 108.00\n\nprocessor\t: 1\nBogoMIPS\t: 108.00\n");
+        assert_eq!((cpu.sockets, cpu.cores, cpu.threads), (1, 2, 2));
+        assert_eq!(cpu.microcode, None);
+    }
+
+    #[test]
+    fn gpus_and_their_drivers() {
+        let lspci = "\
+00:00.0 Host bridge: Advanced Micro Devices, Inc. [AMD] Device 14d8
+\tSubsystem: ASUSTeK Computer Inc. Device 8877
+01:00.0 VGA compatible controller: NVIDIA Corporation GA104 [GeForce RTX 3070] (rev a1)
+\tSubsystem: Micro-Star International Co., Ltd. [MSI] Device 3904
+\tKernel driver in use: nvidia
+\tKernel modules: nouveau, nvidia_drm, nvidia
+01:00.1 Audio device: NVIDIA Corporation GA104 High Definition Audio Controller (rev a1)
+\tKernel driver in use: snd_hda_intel
+12:00.0 Display controller: Advanced Micro Devices, Inc. [AMD/ATI] Raphael (rev c1)
+\tKernel modules: amdgpu
+";
+        assert_eq!(
+            parse_lspci_gpus(lspci),
+            vec![
+                Gpu {
+                    slot: "01:00.0".to_string(),
+                    name: "NVIDIA Corporation GA104 [GeForce RTX 3070]".to_string(),
+                    driver: Some("nvidia".to_string()),
+                    driver_version: None,
+                },
+                Gpu {
+                    slot: "12:00.0".to_string(),
+                    name: "Advanced Micro Devices, Inc. [AMD/ATI] Raphael".to_string(),
+                    driver: None,
+                    driver_version: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_modules_from_udev() {
+        let properties = "\
+DEVPATH=/devices/virtual/dmi/id
+MEMORY_ARRAY_NUM_DEVICES=4
+MEMORY_DEVICE_0_PRESENT=0
+MEMORY_DEVICE_0_LOCATOR=DIMM 0
+MEMORY_DEVICE_1_PRESENT=1
+MEMORY_DEVICE_1_SIZE=17179869184
+MEMORY_DEVICE_1_TYPE=DDR5
+MEMORY_DEVICE_1_SPEED_MTS=6000
+MEMORY_DEVICE_1_CONFIGURED_SPEED_MTS=4800
+MEMORY_DEVICE_1_MANUFACTURER=Kingston
+MEMORY_DEVICE_1_PART_NUMBER=KF560C36-16
+MEMORY_DEVICE_1_LOCATOR=DIMM 1
+";
+        let (slots, modules) = parse_memory_modules(properties);
+        assert_eq!(slots, Some(4));
+        assert_eq!(
+            modules,
+            vec![Module {
+                locator: "DIMM 1".to_string(),
+                size_mib: Some(16384),
+                kind: Some("DDR5".to_string()),
+                speed_mts: Some(6000),
+                configured_speed_mts: Some(4800),
+                manufacturer: Some("Kingston".to_string()),
+                part_number: Some("KF560C36-16".to_string()),
+            }]
+        );
+    }
+}
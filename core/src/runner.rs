@@ -0,0 +1,149 @@
+use crate::capture::Output;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a program may run before it is killed. nvidia-smi can take a
+/// couple of seconds when the driver is not kept loaded.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+// A missing or hung program is left alone this long before trying it again,
+// instead of on every tick
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// How often a running program is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Runs the programs the sensors are read from. Each run is killed after
+/// the timeout, and a program that is not installed or timed out fails
+/// right away for a minute, since the sampler runs on the main thread.
+pub struct Runner {
+    timeout: Duration,
+    unavailable: HashMap<String, (Instant, io::ErrorKind, String)>,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new(TIMEOUT)
+    }
+}
+
+impl Runner {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, unavailable: HashMap::new() }
+    }
+
+    /// Runs a program with the C locale and returns its exit status, standard
+    /// output and standard error. Not being able to start it, or it not
+    /// finishing in time, is an error; exiting with a failure is not.
+    pub fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+        if let Some((since, kind, message)) = self.unavailable.get(program)
+            && since.elapsed() < RETRY_AFTER
+        {
+            return Err(io::Error::new(*kind, message.clone()));
+        }
+
+        let result = run(program, args, self.timeout);
+        match &result {
+            Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::TimedOut) => {
+                self.unavailable.insert(program.to_string(), (Instant::now(), err.kind(), err.to_string()));
+            }
+            _ => {
+                self.unavailable.remove(program);
+            }
+        }
+        result
+    }
+}
+
+fn run(program: &str, args: &[&str], timeout: Duration) -> io::Result<Output> {
+    // The parsers expect C locale numbers and labels
+    let mut child = Command::new(program)
+        .args(args)
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(io::ErrorKind::NotFound, format!("{} is not installed", program)),
+            _ => err,
+        })?;
+
+    // Read on other threads, so a program filling a pipe cannot stall its own exit
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} did not finish within {:?} and was killed", program, timeout),
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        success: status.success(),
+        code: status.code(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_status_and_stderr() {
+        let output = Runner::default().run("sh", &["-c", "echo out; echo err >&2; exit 3"]).unwrap();
+        assert!(!output.success);
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn hung_programs_are_killed_and_left_alone() {
+        let mut runner = Runner::new(Duration::from_millis(200));
+        let started = Instant::now();
+        let err = runner.run("sleep", &["10"]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // Any arguments, since it is the program that is left alone
+        let err = runner.run("sleep", &["0"]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn missing_programs_are_remembered() {
+        let mut runner = Runner::default();
+        for _ in 0..2 {
+            let err = runner.run("gamer-monitor-no-such-tool", &[]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert_eq!(err.to_string(), "gamer-monitor-no-such-tool is not installed");
+        }
+        assert!(runner.unavailable.contains_key("gamer-monitor-no-such-tool"));
+    }
+}
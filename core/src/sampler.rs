@@ -0,0 +1,263 @@
+use crate::capture::Source;
+use crate::config::SamplingConfig;
+use crate::diagnostics::{Check, Diagnostics};
+use crate::processes::{Process, ProcessTable};
+use crate::sensors::SensorData;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    CpuLoad,
+    Sensors,
+    Gpu,
+    Memory,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::CpuLoad,
+        Subsystem::Sensors,
+        Subsystem::Gpu,
+        Subsystem::Memory,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// One published state of every reading. The sequence number grows by one
+/// with each snapshot, so consumers can tell new data from a repeat.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub sequence: u64,
+    pub timestamp: SystemTime,
+    pub data: SensorData,
+    /// Empty unless the sampler tracks processes, which only the window does
+    pub processes: Arc<Vec<Process>>,
+    /// How the last read of each input went; empty for a remote host
+    pub diagnostics: Arc<Vec<Check>>,
+}
+
+pub struct Sampler {
+    data: SensorData,
+    source: Box<dyn Source>,
+    diagnostics: Diagnostics,
+    rates: SamplingConfig,
+    last_run: [Option<Instant>; 4],
+    sequence: u64,
+    process_table: Option<ProcessTable>,
+    processes: Arc<Vec<Process>>,
+}
+
+impl Sampler {
+    pub fn new(rates: SamplingConfig, mut source: Box<dyn Source>) -> Self {
+        let mut data = SensorData::new();
+        let mut diagnostics = Diagnostics::default();
+        data.identify_hardware(&mut diagnostics.track(source.as_mut()));
+
+        Self {
+            data,
+            source,
+            diagnostics,
+            rates,
+            last_run: [None; 4],
+            sequence: 0,
+            process_table: None,
+            processes: Arc::default(),
+        }
+    }
+
+    /// Reads every process along with the CPU load from now on
+    pub fn track_processes(&mut self) {
+        if self.process_table.is_none() {
+            let mut table = ProcessTable::new();
+            self.processes = Arc::new(table.update());
+            self.process_table = Some(table);
+        }
+    }
+
+    pub fn set_rates(&mut self, rates: SamplingConfig) {
+        self.rates = rates;
+    }
+
+    pub fn interval(&self, subsystem: Subsystem) -> Duration {
+        let seconds = match subsystem {
+            Subsystem::CpuLoad => self.rates.cpu_load,
+            Subsystem::Sensors => self.rates.sensors,
+            Subsystem::Gpu => self.rates.gpu,
+            Subsystem::Memory => self.rates.memory,
+        };
+        Duration::from_secs(seconds as u64)
+    }
+
+    /// Samples every subsystem whose interval has elapsed. Returns a new
+    /// snapshot only when at least one of them was read.
+    pub fn tick(&mut self, now: Instant) -> Option<Snapshot> {
+        let mut sampled = false;
+
+        for subsystem in Subsystem::ALL {
+            let due = match self.last_run[subsystem.index()] {
+                Some(last) => now.duration_since(last) >= self.interval(subsystem),
+                None => true,
+            };
+
+            if due {
+                self.sample(subsystem);
+                self.last_run[subsystem.index()] = Some(now);
+                sampled = true;
+            }
+        }
+
+        sampled.then(|| self.snapshot())
+    }
+
+    /// Reads everything right away, regardless of the intervals
+    pub fn sample_all(&mut self) -> Snapshot {
+        self.data.update(&mut self.diagnostics.track(self.source.as_mut()));
+        self.update_processes();
+        self.last_run = [Some(Instant::now()); 4];
+        self.snapshot()
+    }
+
+    fn sample(&mut self, subsystem: Subsystem) {
+        let mut source = self.diagnostics.track(self.source.as_mut());
+        match subsystem {
+            Subsystem::CpuLoad => {
+                self.data.update_cpu_load(&mut source);
+                self.update_processes();
+            }
+            Subsystem::Sensors => self.data.update_sensors(&mut source),
+            Subsystem::Gpu => self.data.update_gpu(&mut source),
+            Subsystem::Memory => self.data.update_ram(&mut source),
+        }
+    }
+
+    fn update_processes(&mut self) {
+        if let Some(table) = &mut self.process_table {
+            self.processes = Arc::new(table.update());
+        }
+    }
+
+    fn snapshot(&mut self) -> Snapshot {
+        self.sequence += 1;
+        Snapshot {
+            sequence: self.sequence,
+            timestamp: SystemTime::now(),
+            data: self.data.clone(),
+            processes: self.processes.clone(),
+            diagnostics: Arc::new(self.diagnostics.checks().to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::Output;
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    // Notes which subsystem each read belongs to
+    struct Recording(Rc<RefCell<Vec<Subsystem>>>);
+
+    impl Source for Recording {
+        fn read_file(&mut self, path: &str) -> io::Result<String> {
+            if path == "/proc/stat" {
+                self.0.borrow_mut().push(Subsystem::CpuLoad);
+                return Ok("cpu  4705 356 584 3699 23 23 0 0 0 0\n".to_string());
+            }
+            Err(io::ErrorKind::NotFound.into())
+        }
+
+        fn run(&mut self, program: &str, _args: &[&str]) -> io::Result<Output> {
+            let subsystem = match program {
+                "sensors" => Subsystem::Sensors,
+                "nvidia-smi" => Subsystem::Gpu,
+                "free" => Subsystem::Memory,
+                _ => return Err(io::ErrorKind::NotFound.into()),
+            };
+            self.0.borrow_mut().push(subsystem);
+            Ok(Output::default())
+        }
+    }
+
+    fn rates(cpu_load: u32, sensors: u32, gpu: u32, memory: u32) -> SamplingConfig {
+        SamplingConfig { cpu_load, sensors, gpu, memory }
+    }
+
+    fn sampler(rates: SamplingConfig) -> (Sampler, Rc<RefCell<Vec<Subsystem>>>) {
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let mut sampler = Sampler::new(rates, Box::new(Recording(reads.clone())));
+        // Only NVIDIA cards are read through a program on every GPU sample
+        sampler.data.gpu_name = "NVIDIA GeForce RTX 3070".to_string();
+        reads.borrow_mut().clear();
+        (sampler, reads)
+    }
+
+    // The subsystems read by one tick at start + seconds
+    fn tick(sampler: &mut Sampler, reads: &RefCell<Vec<Subsystem>>, start: Instant, seconds: u64) -> Vec<Subsystem> {
+        let snapshot = sampler.tick(start + Duration::from_secs(seconds));
+        let mut read = std::mem::take(&mut *reads.borrow_mut());
+        read.dedup();
+        assert_eq!(snapshot.is_some(), !read.is_empty(), "at {} s", seconds);
+        read
+    }
+
+    #[test]
+    fn each_subsystem_at_its_rate() {
+        use Subsystem::*;
+        let start = Instant::now();
+        let (mut sampler, reads) = sampler(rates(1, 2, 3, 5));
+        assert_eq!(tick(&mut sampler, &reads, start, 0), Subsystem::ALL);
+
+        let expected: [&[Subsystem]; 6] = [
+            &[CpuLoad],
+            &[CpuLoad, Sensors],
+            &[CpuLoad, Gpu],
+            &[CpuLoad, Sensors],
+            &[CpuLoad, Memory],
+            &[CpuLoad, Sensors, Gpu],
+        ];
+        for (seconds, expected) in (1..).zip(expected) {
+            assert_eq!(tick(&mut sampler, &reads, start, seconds), expected, "at {} s", seconds);
+        }
+
+        let sequence = sampler.sequence;
+        // Nothing is due again within the same second
+        assert_eq!(tick(&mut sampler, &reads, start, 6), []);
+        assert_eq!(sampler.sequence, sequence);
+    }
+
+    #[test]
+    fn late_ticks_do_not_catch_up() {
+        use Subsystem::*;
+        let start = Instant::now();
+        let (mut sampler, reads) = sampler(rates(2, 2, 2, 2));
+        tick(&mut sampler, &reads, start, 0);
+        assert_eq!(tick(&mut sampler, &reads, start, 1), []);
+        // A tick that comes five seconds late reads each subsystem once, and
+        // the next interval counts from then
+        assert_eq!(tick(&mut sampler, &reads, start, 7), Subsystem::ALL);
+        assert_eq!(tick(&mut sampler, &reads, start, 8), []);
+        assert_eq!(tick(&mut sampler, &reads, start, 9), [CpuLoad, Sensors, Gpu, Memory]);
+    }
+
+    #[test]
+    fn new_rates_apply_from_the_next_tick() {
+        use Subsystem::*;
+        let start = Instant::now();
+        let (mut sampler, reads) = sampler(rates(10, 10, 10, 10));
+        tick(&mut sampler, &reads, start, 0);
+        assert_eq!(tick(&mut sampler, &reads, start, 3), []);
+
+        sampler.set_rates(rates(1, 10, 3, 10));
+        assert_eq!(sampler.interval(Gpu), Duration::from_secs(3));
+        // Measured from each subsystem's last read, not from the change
+        assert_eq!(tick(&mut sampler, &reads, start, 4), [CpuLoad, Gpu]);
+        assert_eq!(tick(&mut sampler, &reads, start, 5), [CpuLoad]);
+        assert_eq!(tick(&mut sampler, &reads, start, 10), [CpuLoad, Sensors, Gpu, Memory]);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::sampler::{Sampler, Snapshot};
+use glib::{ControlFlow, SourceId, timeout_add_seconds_local};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+// Sampling rates are whole seconds, so checking once per second is enough
+const TICK_SECONDS: u32 = 1;
+
+type Subscriber = Rc<dyn Fn(&Snapshot) -> ControlFlow>;
+
+/// Fans a snapshot out to every subscriber. Like a glib timeout, a
+/// subscriber returning ControlFlow::Break is dropped.
+#[derive(Clone, Default)]
+pub struct Publisher {
+    subscribers: Rc<RefCell<Vec<Subscriber>>>,
+    latest: Rc<RefCell<Option<Snapshot>>>,
+}
+
+impl Publisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, subscriber: impl Fn(&Snapshot) -> ControlFlow + 'static) {
+        self.subscribers.borrow_mut().push(Rc::new(subscriber));
+    }
+
+    pub fn latest(&self) -> Option<Snapshot> {
+        self.latest.borrow().clone()
+    }
+
+    pub fn publish(&self, snapshot: Snapshot) {
+        // Never redraw for a snapshot that was already published
+        if let Some(latest) = self.latest.borrow().as_ref()
+            && latest.sequence >= snapshot.sequence
+        {
+            return;
+        }
+        *self.latest.borrow_mut() = Some(snapshot.clone());
+
+        // Subscribers may subscribe others while running, so work on a copy
+        let subscribers = self.subscribers.borrow().clone();
+        let mut finished = Vec::new();
+
+        for subscriber in &subscribers {
+            if subscriber(&snapshot) == ControlFlow::Break {
+                finished.push(subscriber.clone());
+            }
+        }
+
+        if !finished.is_empty() {
+            self.subscribers
+                .borrow_mut()
+                .retain(|subscriber| !finished.iter().any(|done| Rc::ptr_eq(done, subscriber)));
+        }
+    }
+}
+
+/// The single sampling tick: asks the sampler for whatever is due and
+/// publishes a snapshot only when something was actually read
+pub fn start(sampler: &Rc<RefCell<Sampler>>, publisher: &Publisher) -> SourceId {
+    let sampler = sampler.clone();
+    let publisher = publisher.clone();
+
+    timeout_add_seconds_local(TICK_SECONDS, move || {
+        let snapshot = sampler.borrow_mut().tick(Instant::now());
+        if let Some(snapshot) = snapshot {
+            publisher.publish(snapshot);
+        }
+        ControlFlow::Continue
+    })
+}
@@ -0,0 +1,126 @@
+use crate::alerts::Severity;
+use crate::config::{Config, Section};
+use crate::sensors::SensorData;
+
+/// One line of a section, the counterpart of a grid row in the window,
+/// drawn by the terminal dashboard and sent to the web dashboard
+pub enum Row {
+    Text(String, String, Severity),
+    Bar(String, f64, String, Severity),
+}
+
+/// The same rows, in the same order, as the sections built by the window
+pub fn section_rows(section: Section, data: &SensorData, config: &Config) -> Vec<Row> {
+    let units = config.units;
+    let alerts = &config.alerts;
+    let symbol = units.temperature_symbol();
+
+    match section {
+        Section::Cpu => vec![
+            Row::Text("Model:".to_string(), data.cpu_name.clone(), Severity::Normal),
+            Row::Text(
+                format!("Temperature ({}):", symbol),
+                units.temperature(&data.cpu_temp),
+                alerts.cpu_temp(&data.cpu_temp_sensor).classify_text(&data.cpu_temp),
+            ),
+            Row::Bar(
+                "Load:".to_string(),
+                data.cpu_usage as f64 / 100.0,
+                format!("{:.1}%", data.cpu_usage),
+                alerts.cpu_load.classify(data.cpu_usage),
+            ),
+        ],
+        Section::Gpu => {
+            let mut rows = vec![Row::Text("Model:".to_string(), data.gpu_name.clone(), Severity::Normal)];
+            let edge = Row::Text(
+                format!("Temperature ({}):", symbol),
+                units.temperature(&data.gpu_edge),
+                alerts.gpu_temp(&data.gpu_name).classify_text(&data.gpu_edge),
+            );
+            let fan_severity = alerts.fan(&data.gpu_fan).classify_text(&data.gpu_fan);
+
+            if data.gpu_name.contains("Radeon") {
+                rows.extend([
+                    Row::Text(
+                        format!("Hotspot ({}):", symbol),
+                        units.temperature(&data.gpu_hotspot),
+                        alerts.gpu_hotspot.classify_text(&data.gpu_hotspot),
+                    ),
+                    Row::Text(
+                        format!("Edge ({}):", symbol),
+                        units.temperature(&data.gpu_edge),
+                        alerts.gpu_edge.classify_text(&data.gpu_edge),
+                    ),
+                    Row::Text(
+                        format!("Memory ({}):", symbol),
+                        units.temperature(&data.gpu_memory),
+                        alerts.gpu_memory.classify_text(&data.gpu_memory),
+                    ),
+                    Row::Text("Fan (RPM):".to_string(), data.gpu_fan.clone(), fan_severity),
+                ]);
+            } else if data.gpu_name.to_lowercase().contains("nvidia") {
+                let used = data.gpu_vram_used.parse::<f64>().unwrap_or(0.0);
+                let total = data.gpu_vram_total.parse::<f64>().unwrap_or(1.0).max(1.0);
+
+                rows.extend([
+                    edge,
+                    Row::Text("Fan:".to_string(), data.gpu_fan.clone(), fan_severity),
+                    Row::Bar(
+                        "VRAM:".to_string(),
+                        used / total,
+                        units.vram(&data.gpu_vram_used, &data.gpu_vram_total),
+                        alerts.vram_load.classify((used / total * 100.0) as f32),
+                    ),
+                    Row::Text("Power:".to_string(), units.power(&data.gpu_power), Severity::Normal),
+                    Row::Bar(
+                        "Load:".to_string(),
+                        data.gpu_utilization as f64 / 100.0,
+                        format!("{:.0}%", data.gpu_utilization),
+                        alerts.gpu_load.classify(data.gpu_utilization),
+                    ),
+                ]);
+            } else {
+                rows.push(edge);
+            }
+            rows
+        }
+        Section::Storage => {
+            if data.nvme_temps.is_empty() {
+                return vec![Row::Text("NVMe:".to_string(), "No devices detected".to_string(), Severity::Normal)];
+            }
+            data.nvme_temps
+                .iter()
+                .enumerate()
+                .map(|(i, temp)| {
+                    Row::Text(
+                        format!("NVMe {}:", i + 1),
+                        format!("{} {}", units.temperature(temp), symbol),
+                        alerts.nvme.classify_text(temp),
+                    )
+                })
+                .collect()
+        }
+        Section::Ram => vec![
+            Row::Text("Total:".to_string(), units.ram(data.ram_total), Severity::Normal),
+            Row::Text("Used:".to_string(), units.ram(data.ram_used), Severity::Normal),
+            Row::Text("Free:".to_string(), units.ram(data.ram_free), Severity::Normal),
+            Row::Text("Available:".to_string(), units.ram(data.ram_available), Severity::Normal),
+            Row::Bar(
+                "Load:".to_string(),
+                data.ram_percent as f64 / 100.0,
+                format!("{:.1}%", data.ram_percent),
+                alerts.ram_load.classify(data.ram_percent),
+            ),
+        ],
+        Section::Fans => [
+            (&config.fans.cpu, &data.cpu_fan),
+            (&config.fans.chassis1, &data.chassis_fan1),
+            (&config.fans.chassis2, &data.chassis_fan2),
+        ]
+        .into_iter()
+        .map(|(name, reading)| {
+            Row::Text(format!("{}:", name), reading.clone(), alerts.fan_rpm.classify_text(reading))
+        })
+        .collect(),
+    }
+}
@@ -0,0 +1,787 @@
+use crate::capture::Source;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use once_cell::sync::Lazy;
+
+static RE_TCTL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Tctl:\s+\+([0-9.]+)").unwrap());
+
+static RE_TDIE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Tdie:\s+\+([0-9.]+)").unwrap());
+
+static RE_PACKAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Package id 0:\s+\+([0-9.]+)").unwrap());
+
+static RE_CORE0: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Core 0:\s+\+([0-9.]+)").unwrap());
+
+static RE_NVME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Composite:\s+\+([0-9.]+)").unwrap());
+
+static RE_CPU_FAN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"fan2:\s+([0-9]+)\s+RPM").unwrap());
+
+static RE_CHASSIS1: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"fan3:\s+([0-9]+)\s+RPM").unwrap());
+
+// fan1 of the Nuvoton chip, a few lines below its header. Only lines of
+// its own block are skipped, which ends at the first blank line, so a chip
+// without fan1 never gets the next chip's.
+static RE_CHASSIS2_A: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"nct6799-isa-0290(?:\n.+)*?\nfan1:\s+([0-9]+)\s+RPM").unwrap());
+
+static RE_CHASSIS2_B: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"nct6798-isa-0290(?:\n.+)*?\nfan1:\s+([0-9]+)\s+RPM").unwrap());
+
+/// Serialized for agents, whose viewers draw the same sections from it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorData {
+    pub cpu_name: String,
+    pub cpu_temp: String,
+    pub cpu_temp_sensor: String,
+    pub cpu_usage: f32,
+
+    pub gpu_name: String,
+    pub gpu_edge: String,
+    pub gpu_hotspot: String,
+    pub gpu_memory: String,
+    pub gpu_fan: String,
+    pub gpu_power: String,
+    pub gpu_vram_used: String,
+    pub gpu_vram_total: String,
+    pub gpu_utilization: f32,
+
+    pub nvme_temps: Vec<String>,
+
+    pub cpu_fan: String,
+    pub chassis_fan1: String,
+    pub chassis_fan2: String,
+
+    pub ram_total: f32,
+    pub ram_used: f32,
+    pub ram_free: f32,
+    pub ram_available: f32,
+    pub ram_percent: f32,
+
+    /// The chips `sensors` listed, e.g. "k10temp-pci-00c3"
+    pub chips: Vec<String>,
+
+    // Previous /proc/stat totals, so each CPU load sample is a delta
+    // against the last one instead of a blocking 100 ms measurement
+    #[serde(skip)]
+    cpu_times: Option<(u64, u64)>,
+}
+
+impl SensorData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every input comes from the source: this machine, or a capture being
+    /// replayed
+    pub fn identify_hardware(&mut self, source: &mut dyn Source) {
+        self.identify_processor(source);
+        self.identify_gpu(source);
+    }
+
+    pub fn update(&mut self, source: &mut dyn Source) {
+        self.update_cpu_load(source);
+        self.update_sensors(source);
+        self.update_gpu(source);
+        self.update_ram(source);
+    }
+
+    fn identify_processor(&mut self, source: &mut dyn Source) {
+        if let Ok(cpuinfo) = source.read_file("/proc/cpuinfo")
+            && let Some(name) = parse_cpu_name(&cpuinfo)
+        {
+            self.cpu_name = name;
+        }
+    }
+
+    pub fn update_cpu_load(&mut self, source: &mut dyn Source) {
+        // The very first sample has no baseline yet, so take one now
+        if self.cpu_times.is_none() {
+            self.cpu_times = read_cpu_times(source);
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        let current = read_cpu_times(source);
+
+        if let (Some((total1, idle1)), Some((total2, idle2))) = (self.cpu_times, current) {
+            let total_diff = total2.saturating_sub(total1);
+            let idle_diff = idle2.saturating_sub(idle1).min(total_diff);
+
+            if total_diff > 0 {
+                self.cpu_usage = ((total_diff - idle_diff) as f32 / total_diff as f32) * 100.0;
+            }
+        } else {
+            self.cpu_usage = 0.0;
+        }
+
+        self.cpu_times = current;
+    }
+
+    fn identify_gpu(&mut self, source: &mut dyn Source) {
+        if let Ok(output) = source.run("nvidia-smi", &["--query-gpu=name", "--format=csv,noheader"])
+            && output.success
+        {
+            let name = output.stdout.trim().to_string();
+            if !name.is_empty() {
+                self.gpu_name = name;
+                return;
+            }
+        }
+
+        if let Ok(output) = source.run("lspci", &[])
+            && let Some(name) = parse_lspci_gpu(&output.stdout)
+        {
+            self.gpu_name = name;
+            return;
+        }
+
+        if self.gpu_name.is_empty() {
+            self.gpu_name = "Unknown GPU".to_string();
+        }
+    }
+
+    fn update_nvidia_gpu_info(&mut self, source: &mut dyn Source) {
+
+        if let Ok(output) = source.run(
+            "nvidia-smi",
+            &[
+                "--query-gpu=temperature.gpu,fan.speed,memory.used,memory.total,power.draw,utilization.gpu",
+                "--format=csv,noheader,nounits",
+            ],
+        ) && output.success
+        {
+            self.parse_nvidia_smi(&output.stdout);
+        }
+    }
+
+    // One line of nvidia-smi --format=csv,noheader,nounits: temperature,
+    // fan, memory used and total, power and utilization
+    fn parse_nvidia_smi(&mut self, out: &str) {
+        let parts: Vec<&str> = out.trim().split(',').collect();
+
+        if let Some(temp) = parts.first() {
+            self.gpu_edge = temp.trim().to_string();
+        }
+
+        if let Some(fan) = parts.get(1) {
+            let fan_val = fan.trim();
+            if fan_val == "[N/A]" {
+                self.gpu_fan = "Not exposed by NVIDIA driver".to_string();
+            } else {
+                self.gpu_fan = format!("{}%", fan_val);
+            }
+        }
+
+        if let Some(mem_used) = parts.get(2) {
+            self.gpu_vram_used = mem_used.trim().to_string();
+        }
+
+        if let Some(mem_total) = parts.get(3) {
+            self.gpu_vram_total = mem_total.trim().to_string();
+        }
+
+        if let Some(power) = parts.get(4) {
+            let power_val = power.trim();
+            if power_val == "[N/A]" {
+                self.gpu_power = "N/A".to_string();
+            } else {
+                self.gpu_power = format!("{} W", power_val);
+            }
+        }
+
+        if let Some(util) = parts.get(5) {
+            let util_val = util.trim();
+            if util_val == "[N/A]" {
+                self.gpu_utilization = 0.0;
+            } else {
+                self.gpu_utilization = util_val.parse::<f32>().unwrap_or(0.0);
+            }
+        }
+    }
+
+    fn parse_radeon_gpu_info(&mut self, sensors_output: &str) {
+
+        let lines: Vec<&str> = sensors_output.lines().collect();
+        let mut in_gpu_section = false;
+
+        for line in lines {
+            let trimmed = line.trim_start();
+
+            // Detects a Radeon graphics card
+            if trimmed.starts_with("amdgpu-pci-") {
+                in_gpu_section = true;
+
+                self.gpu_edge.clear();
+                self.gpu_hotspot.clear();
+                self.gpu_memory.clear();
+                self.gpu_fan.clear();
+
+                continue;
+            }
+
+            // Exit if found another PCI device
+            if in_gpu_section
+                && !trimmed.starts_with("amdgpu-pci-")
+                && !trimmed.is_empty()
+                && !trimmed.starts_with(' ')
+                && !trimmed.starts_with('\t')
+                && (trimmed.contains("-isa-") || trimmed.contains("-pci-") || trimmed.contains("-i2c-")) {
+                in_gpu_section = false;
+            }
+
+            if in_gpu_section {
+                let parts: Vec<&str> = trimmed.split_whitespace().collect();
+                if parts.is_empty() {
+                    continue;
+                }
+
+                match parts[0] {
+                    label if label.starts_with("junction") && parts.len() >= 2 => {
+                        self.gpu_hotspot = parts[1].trim_start_matches('+').to_string();
+                    }
+                    label if label.starts_with("edge") && parts.len() >= 2 => {
+                        self.gpu_edge = parts[1].trim_start_matches('+').to_string();
+                    }
+                    label if label.starts_with("mem") && parts.len() >= 2 => {
+                        self.gpu_memory = parts[1].trim_start_matches('+').to_string();
+                    }
+                    label if label.starts_with("fan") && parts.len() >= 2 => {
+                        self.gpu_fan = format!("{} RPM", parts[1]);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// amdgpu readings come from `sensors`, see update_sensors
+    pub fn update_gpu(&mut self, source: &mut dyn Source) {
+        if self.gpu_name.to_lowercase().contains("nvidia") {
+            self.update_nvidia_gpu_info(source);
+        }
+    }
+
+    fn parse_cpu_info(&mut self, sensors_output: &str) {
+        // Parse CPU temperature, remembering which sensor provided it
+        let candidates: [(&str, &Regex); 4] = [
+            ("Tctl", &RE_TCTL),
+            ("Tdie", &RE_TDIE),
+            ("Package id 0", &RE_PACKAGE),
+            ("Core 0", &RE_CORE0),
+        ];
+
+        for (sensor, re) in candidates {
+            if let Some(temp) = self.extract_sensor_value(sensors_output, re) {
+                self.cpu_temp = temp;
+                self.cpu_temp_sensor = sensor.to_string();
+                break;
+            }
+        }
+    }
+
+    fn parse_nvme_info(&mut self, sensors_output: &str) {
+        self.nvme_temps.clear();
+        for cap in RE_NVME.captures_iter(sensors_output) {
+            if let Some(temp) = cap.get(1) {
+                self.nvme_temps.push(temp.as_str().to_string());
+            }
+        }
+    }
+
+    fn parse_fan_info(&mut self, sensors_output: &str) {
+        if let Some(fan) = RE_CPU_FAN.captures(sensors_output).and_then(|c| c.get(1)) {
+            self.cpu_fan = format!("{} RPM", fan.as_str());
+        }
+
+        if let Some(fan) = RE_CHASSIS1.captures(sensors_output).and_then(|c| c.get(1)) {
+            self.chassis_fan1 = format!("{} RPM", fan.as_str());
+        }
+
+        if let Some(fan) = RE_CHASSIS2_A.captures(sensors_output)
+            .or_else(|| RE_CHASSIS2_B.captures(sensors_output))
+            .and_then(|c| c.get(1))
+        {
+            self.chassis_fan2 = format!("{} RPM", fan.as_str());
+        }
+    }
+
+    /// Failures are left to the diagnostics, which see every read
+    pub fn update_sensors(&mut self, source: &mut dyn Source) {
+        // sensors exits with an error when a single chip cannot be read,
+        // but still prints the others
+        if let Ok(output) = source.run("sensors", &[]) {
+            self.parse_sensors(&output.stdout);
+        }
+    }
+
+    // The plain text output of `sensors`
+    fn parse_sensors(&mut self, sensors_output: &str) {
+        self.chips = parse_chips(sensors_output);
+        self.parse_cpu_info(sensors_output);
+        // With an NVIDIA card the amdgpu chip is the CPU's integrated
+        // graphics, and the card's readings come from nvidia-smi instead
+        let nvidia = self.gpu_name.to_lowercase().contains("nvidia");
+        if !nvidia && (self.gpu_name.contains("Radeon") || sensors_output.contains("amdgpu")) {
+            self.parse_radeon_gpu_info(sensors_output);
+        }
+        self.parse_nvme_info(sensors_output);
+        self.parse_fan_info(sensors_output);
+    }
+
+    fn extract_sensor_value(&self, text: &str, re: &Regex) -> Option<String> {
+        let cap = re.captures(text)?;
+        Some(cap.get(1)?.as_str().to_string())
+    }
+
+    pub fn update_ram(&mut self, source: &mut dyn Source) {
+        if let Ok(output) = source.run("free", &["-m"]) {
+            self.parse_free(&output.stdout);
+        }
+    }
+
+    // The output of `free -m`, in MiB
+    fn parse_free(&mut self, free_output: &str) {
+        for line in free_output.lines() {
+            if line.starts_with("Mem:") || line.starts_with("Mem.:") {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+
+                if parts.len() >= 7 {
+                    if let Ok(total) = parts[1].parse::<f32>() {
+                        self.ram_total = total / 1024.0;
+                    }
+                    if let Ok(used) = parts[2].parse::<f32>() {
+                        self.ram_used = used / 1024.0;
+                    }
+                    if let Ok(free) = parts[3].parse::<f32>() {
+                        self.ram_free = free / 1024.0;
+                    }
+                    if let Ok(available) = parts[6].parse::<f32>() {
+                        self.ram_available = available / 1024.0;
+                    }
+
+                    if self.ram_total > 0.0 {
+                        self.ram_percent = (self.ram_used / self.ram_total) * 100.0;
+                    }
+                }
+                break;
+            }
+        }
+    }
+}
+
+// Returns the (total, idle) jiffies of the aggregate "cpu" line of /proc/stat
+fn read_cpu_times(source: &mut dyn Source) -> Option<(u64, u64)> {
+    parse_cpu_times(&source.read_file("/proc/stat").ok()?)
+}
+
+fn parse_cpu_times(stat: &str) -> Option<(u64, u64)> {
+    let values: Vec<u64> = stat
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|s| s.parse().ok())
+        .collect();
+
+    if values.len() < 4 {
+        return None;
+    }
+
+    let idle = values[3] + values.get(4).unwrap_or(&0);
+    let total = values.iter().take(8).sum();
+    Some((total, idle))
+}
+
+// The model name of the first CPU in /proc/cpuinfo
+fn parse_cpu_name(cpuinfo: &str) -> Option<String> {
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split(':').nth(1))
+        .map(|name| name.trim().to_string())
+}
+
+// Each chip in `sensors` output is a name line followed by its adapter
+fn parse_chips(sensors_output: &str) -> Vec<String> {
+    let lines: Vec<&str> = sensors_output.lines().collect();
+    lines
+        .windows(2)
+        .filter(|pair| pair[1].starts_with("Adapter:"))
+        .map(|pair| pair[0].trim().to_string())
+        .collect()
+}
+
+// The first graphics card in `lspci`, preferably by its marketing name:
+// the last bracketed part, as in "Navi 31 [Radeon RX 7900 XT/7900 XTX/7900M]"
+fn parse_lspci_gpu(lspci_out: &str) -> Option<String> {
+    for line in lspci_out.lines() {
+        if line.contains("VGA compatible controller") {
+            let mut last_bracket_content = String::new();
+            let mut current_pos = 0;
+
+            while let Some(start) = line[current_pos..].find('[') {
+                let absolute_start = current_pos + start;
+                if let Some(end) = line[absolute_start..].find(']') {
+                    let absolute_end = absolute_start + end;
+                    last_bracket_content =
+                        line[absolute_start + 1..absolute_end].to_string();
+                    current_pos = absolute_end + 1;
+                } else {
+                    break;
+                }
+            }
+
+            if !last_bracket_content.is_empty() && last_bracket_content != "AMD/ATI" {
+                return Some(last_bracket_content);
+            }
+
+            if let Some(controller_part) = line.split("controller:").nth(1) {
+                let gpu_part = controller_part.trim().to_string();
+                if !gpu_part.is_empty() {
+                    return Some(gpu_part);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Extracts the leading number of a sensor reading such as "+54.0°C",
+/// "1200 RPM", "45%" or "125.50 W"
+pub fn parse_reading(text: &str) -> Option<f32> {
+    let trimmed = text.trim().trim_start_matches('+');
+    let end = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(trimmed.len());
+    trimmed[..end].parse::<f32>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::Output;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    // Serves one machine's outputs from tests/fixtures/<machine>. An input
+    // without a file fails like a program that is not installed.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(machine: &str) -> Self {
+            Self(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(machine))
+        }
+
+        fn read(&self, name: &str) -> io::Result<String> {
+            std::fs::read_to_string(self.0.join(name))
+        }
+    }
+
+    impl Source for Fixture {
+        fn read_file(&mut self, path: &str) -> io::Result<String> {
+            match path {
+                "/proc/cpuinfo" => self.read("cpuinfo.txt"),
+                _ => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn run(&mut self, program: &str, args: &[&str]) -> io::Result<Output> {
+            let name = match (program, args.first()) {
+                ("nvidia-smi", Some(&"--query-gpu=name")) => "nvidia-smi-name.csv",
+                ("nvidia-smi", _) => "nvidia-smi.csv",
+                ("sensors", _) => "sensors.txt",
+                ("lspci", _) => "lspci.txt",
+                ("free", _) => "free.txt",
+                _ => return Err(io::ErrorKind::NotFound.into()),
+            };
+            Ok(Output { success: true, stdout: self.read(name)?, ..Default::default() })
+        }
+    }
+
+    // Everything but the CPU load, which takes two /proc/stat reads
+    fn parse_machine(machine: &str) -> SensorData {
+        let mut source = Fixture::new(machine);
+        let mut data = SensorData::new();
+        data.identify_hardware(&mut source);
+        data.update_sensors(&mut source);
+        data.update_gpu(&mut source);
+        data.update_ram(&mut source);
+        data
+    }
+
+    // The RAM fields for a `free -m` line, computed the way parse_free does
+    fn ram(total: f32, used: f32, free: f32, available: f32) -> SensorData {
+        let (total, used) = (total / 1024.0, used / 1024.0);
+        SensorData {
+            ram_total: total,
+            ram_used: used,
+            ram_free: free / 1024.0,
+            ram_available: available / 1024.0,
+            ram_percent: used / total * 100.0,
+            ..SensorData::default()
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn machines() {
+        let cases = [
+            (
+                "ryzen-7950x-rx7900xtx-nct6799",
+                SensorData {
+                    cpu_name: "AMD Ryzen 9 7950X 16-Core Processor".into(),
+                    cpu_temp: "66.8".into(),
+                    cpu_temp_sensor: "Tctl".into(),
+                    gpu_name: "Radeon RX 7900 XT/7900 XTX/7900M".into(),
+                    gpu_edge: "54.0°C".into(),
+                    gpu_hotspot: "71.0°C".into(),
+                    gpu_memory: "62.0°C".into(),
+                    gpu_fan: "1215 RPM".into(),
+                    nvme_temps: strings(&["38.9", "44.9"]),
+                    cpu_fan: "1290 RPM".into(),
+                    chassis_fan1: "905 RPM".into(),
+                    chassis_fan2: "812 RPM".into(),
+                    chips: strings(&["nvme-pci-0100", "amdgpu-pci-0300", "k10temp-pci-00c3", "nct6799-isa-0290", "nvme-pci-0400"]),
+                    ..ram(63426.0, 14210.0, 38870.0, 49216.0)
+                },
+            ),
+            (
+                "intel-12700k-rtx3070-it87",
+                SensorData {
+                    cpu_name: "12th Gen Intel(R) Core(TM) i7-12700K".into(),
+                    cpu_temp: "41.0".into(),
+                    cpu_temp_sensor: "Package id 0".into(),
+                    gpu_name: "NVIDIA GeForce RTX 3070".into(),
+                    gpu_edge: "47".into(),
+                    gpu_fan: "30%".into(),
+                    gpu_power: "38.52 W".into(),
+                    gpu_vram_used: "1187".into(),
+                    gpu_vram_total: "8192".into(),
+                    gpu_utilization: 7.0,
+                    nvme_temps: strings(&["36.9"]),
+                    cpu_fan: "987 RPM".into(),
+                    chassis_fan1: "642 RPM".into(),
+                    chips: strings(&["coretemp-isa-0000", "it8689-isa-0a40", "acpitz-acpi-0", "nvme-pci-0200"]),
+                    ..ram(31899.0, 6021.0, 20117.0, 25101.0)
+                },
+            ),
+            (
+                // zenpower on first generation Ryzen: Tctl carries the +10 °C offset
+                "ryzen-2700x-zenpower-rx6800xt-nct6798",
+                SensorData {
+                    cpu_name: "AMD Ryzen 7 2700X Eight-Core Processor".into(),
+                    cpu_temp: "58.2".into(),
+                    cpu_temp_sensor: "Tctl".into(),
+                    gpu_name: "Radeon RX 6800/6800 XT / 6900 XT".into(),
+                    gpu_edge: "45.0°C".into(),
+                    gpu_hotspot: "47.0°C".into(),
+                    gpu_memory: "54.0°C".into(),
+                    gpu_fan: "0 RPM".into(),
+                    nvme_temps: strings(&["41.9"]),
+                    cpu_fan: "1102 RPM".into(),
+                    chassis_fan1: "851 RPM".into(),
+                    chassis_fan2: "720 RPM".into(),
+                    chips: strings(&["zenpower-pci-00c3", "amdgpu-pci-0b00", "nct6798-isa-0290", "nvme-pci-0100"]),
+                    ..ram(15937.0, 5218.0, 4471.0, 10027.0)
+                },
+            ),
+            (
+                // Polaris has no junction or memory sensor, and chassis fan 2 is only
+                // read from nct6798 and nct6799 boards
+                "intel-6700k-rx580-nct6775",
+                SensorData {
+                    cpu_name: "Intel(R) Core(TM) i7-6700K CPU @ 4.00GHz".into(),
+                    cpu_temp: "43.0".into(),
+                    cpu_temp_sensor: "Package id 0".into(),
+                    gpu_name: "Radeon RX 470/480/570/570X/580/580X/590".into(),
+                    gpu_edge: "42.0°C".into(),
+                    gpu_fan: "898 RPM".into(),
+                    cpu_fan: "1048 RPM".into(),
+                    chassis_fan1: "733 RPM".into(),
+                    chips: strings(&["coretemp-isa-0000", "amdgpu-pci-0100", "nct6775-isa-0290"]),
+                    ..ram(15990.0, 4102.0, 7315.0, 11027.0)
+                },
+            ),
+            (
+                // The ThinkPad fan is fan1 of thinkpad-isa, which is not a motherboard fan
+                "intel-laptop-iris-xe",
+                SensorData {
+                    cpu_name: "12th Gen Intel(R) Core(TM) i7-1260P".into(),
+                    cpu_temp: "52.0".into(),
+                    cpu_temp_sensor: "Package id 0".into(),
+                    gpu_name: "Iris Xe Graphics".into(),
+                    nvme_temps: strings(&["35.9"]),
+                    chips: strings(&["iwlwifi_1-virtual-0", "thinkpad-isa-0000", "BAT0-acpi-0", "coretemp-isa-0000", "acpitz-acpi-0", "nvme-pci-0400"]),
+                    ..ram(15683.0, 4987.0, 6811.0, 9596.0)
+                },
+            ),
+            (
+                // The integrated Radeon's amdgpu chip is not mistaken for the NVIDIA card
+                "ryzen-laptop-rtx3060",
+                SensorData {
+                    cpu_name: "AMD Ryzen 7 5800H with Radeon Graphics".into(),
+                    cpu_temp: "61.4".into(),
+                    cpu_temp_sensor: "Tctl".into(),
+                    gpu_name: "NVIDIA GeForce RTX 3060 Laptop GPU".into(),
+                    gpu_edge: "52".into(),
+                    gpu_fan: "Not exposed by NVIDIA driver".into(),
+                    gpu_power: "N/A".into(),
+                    gpu_vram_used: "5".into(),
+                    gpu_vram_total: "6144".into(),
+                    nvme_temps: strings(&["39.9"]),
+                    chips: strings(&["amdgpu-pci-0500", "BAT1-acpi-0", "k10temp-pci-00c3", "nvme-pci-0200", "acpitz-acpi-0"]),
+                    ..ram(15334.0, 3712.0, 9105.0, 11205.0)
+                },
+            ),
+        ];
+
+        for (machine, expected) in cases {
+            assert_eq!(parse_machine(machine), expected, "{}", machine);
+        }
+    }
+
+    #[test]
+    fn integrated_radeon_next_to_nvidia() {
+        // Without nvidia-smi's readings, none of the Radeon's may show up
+        let mut source = Fixture::new("ryzen-laptop-rtx3060");
+        let mut data = SensorData::new();
+        data.identify_hardware(&mut source);
+        data.update_sensors(&mut source);
+        assert_eq!(data.gpu_name, "NVIDIA GeForce RTX 3060 Laptop GPU");
+        assert_eq!((data.gpu_edge.as_str(), data.gpu_hotspot.as_str(), data.gpu_fan.as_str()), ("", "", ""));
+        assert!(data.chips.contains(&"amdgpu-pci-0500".to_string()));
+
+        // The same chip is the GPU when the Radeon is the only one
+        let mut data = SensorData::new();
+        data.gpu_name = "Radeon Vega Series / Radeon Vega Mobile Series".to_string();
+        data.parse_sensors(&source.read("sensors.txt").unwrap());
+        assert_eq!(data.gpu_edge, "51.0°C");
+    }
+
+    #[test]
+    fn lspci_gpu_names() {
+        let cases = [
+            (
+                "03:00.0 VGA compatible controller: Advanced Micro Devices, Inc. [AMD/ATI] Navi 31 [Radeon RX 7900 XT/7900 XTX/7900M] (rev c8)",
+                Some("Radeon RX 7900 XT/7900 XTX/7900M"),
+            ),
+            (
+                "01:00.0 VGA compatible controller: NVIDIA Corporation GA104 [GeForce RTX 3070] (rev a1)",
+                Some("GeForce RTX 3070"),
+            ),
+            (
+                "00:02.0 VGA compatible controller: Intel Corporation Alder Lake-P GT2 [Iris Xe Graphics] (rev 0c)",
+                Some("Iris Xe Graphics"),
+            ),
+            (
+                "00:02.0 VGA compatible controller: Intel Corporation HD Graphics 630 (rev 04)",
+                Some("Intel Corporation HD Graphics 630 (rev 04)"),
+            ),
+            // A card newer than the pci.ids database only has the vendor in brackets
+            (
+                "03:00.0 VGA compatible controller: Advanced Micro Devices, Inc. [AMD/ATI] Device 7550 (rev c0)",
+                Some("Advanced Micro Devices, Inc. [AMD/ATI] Device 7550 (rev c0)"),
+            ),
+            (
+                "01:00.0 3D controller: NVIDIA Corporation GA107M [GeForce RTX 3050 Mobile] (rev a1)",
+                None,
+            ),
+        ];
+
+        for (lspci, expected) in cases {
+            assert_eq!(parse_lspci_gpu(lspci).as_deref(), expected, "{}", lspci);
+        }
+    }
+
+    #[test]
+    fn nvidia_smi_lines() {
+        let cases = [
+            ("61, 45, 10240, 24564, 285.40, 98", "61", "45%", "285.40 W", 98.0),
+            ("52, [N/A], 5, 6144, [N/A], 0", "52", "Not exposed by NVIDIA driver", "N/A", 0.0),
+            ("35, 0, 300, 4096, 10.12, [N/A]", "35", "0%", "10.12 W", 0.0),
+        ];
+
+        for (line, edge, fan, power, utilization) in cases {
+            let mut data = SensorData::new();
+            data.parse_nvidia_smi(line);
+            assert_eq!(
+                (data.gpu_edge.as_str(), data.gpu_fan.as_str(), data.gpu_power.as_str(), data.gpu_utilization),
+                (edge, fan, power, utilization),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn radeon_section_ends_at_the_next_chip() {
+        let sensors = "\
+amdgpu-pci-0300
+Adapter: PCI adapter
+edge:         +50.0°C  (crit = +100.0°C, hyst = -273.1°C)
+
+nct6799-isa-0290
+Adapter: ISA adapter
+fan1:          812 RPM  (min =    0 RPM)
+";
+        let mut data = SensorData::new();
+        data.parse_radeon_gpu_info(sensors);
+        assert_eq!(data.gpu_edge, "50.0°C");
+        assert_eq!(data.gpu_fan, "");
+    }
+
+    #[test]
+    fn chassis_fan2_stays_in_its_chip() {
+        let sensors = "\
+nct6799-isa-0290
+Adapter: ISA adapter
+fan2:         1290 RPM  (min =    0 RPM)
+fan3:          905 RPM  (min =    0 RPM)
+
+amdgpu-pci-0300
+Adapter: PCI adapter
+fan1:         1215 RPM  (min =    0 RPM, max = 3300 RPM)
+";
+        let mut data = SensorData::new();
+        data.parse_fan_info(sensors);
+        assert_eq!((data.cpu_fan.as_str(), data.chassis_fan1.as_str()), ("1290 RPM", "905 RPM"));
+        assert_eq!(data.chassis_fan2, "");
+
+        let mut data = SensorData::new();
+        data.parse_fan_info(&sensors.replacen("fan3:", "fan1:          812 RPM  (min =    0 RPM)\nfan3:", 1));
+        assert_eq!(data.chassis_fan2, "812 RPM");
+    }
+
+    #[test]
+    fn cpu_times() {
+        let stat = "cpu  73634 12 9011 662146 310 0 171 0 0 0\ncpu0 36817 6 4505 331073 155 0 85 0 0 0\n";
+        assert_eq!(parse_cpu_times(stat), Some((73634 + 12 + 9011 + 662146 + 310 + 171, 662146 + 310)));
+        assert_eq!(parse_cpu_times("cpu  1 2\n"), None);
+        assert_eq!(parse_cpu_times(""), None);
+    }
+
+    #[test]
+    fn readings() {
+        let cases = [
+            ("+54.0°C", Some(54.0)),
+            ("1200 RPM", Some(1200.0)),
+            ("45%", Some(45.0)),
+            ("125.50 W", Some(125.5)),
+            ("N/A", None),
+            ("", None),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse_reading(text), expected, "{}", text);
+        }
+    }
+}
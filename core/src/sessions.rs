@@ -0,0 +1,546 @@
+use crate::config::Config;
+use crate::games::{Detector, Game};
+use crate::history::Store;
+use crate::metrics;
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use crate::sensors::{SensorData, parse_reading};
+use crate::timestamp;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Shorter sessions are a launcher starting and closing, not a game played
+const MIN_DURATION: Duration = Duration::from_secs(60);
+// Games that restart themselves (a launcher handing over to the game, a
+// settings change) keep their session if they are back within this time
+const GRACE: Duration = Duration::from_secs(30);
+// How often the running session is saved, so a crash loses at most this
+const CHECKPOINT: Duration = Duration::from_secs(60);
+
+/// Average and peak of one reading over a session
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Stat {
+    pub average: f32,
+    pub peak: f32,
+    pub samples: u64,
+}
+
+impl Stat {
+    fn add(&mut self, value: Option<f32>) {
+        let Some(value) = value.filter(|value| value.is_finite()) else {
+            return;
+        };
+        self.samples += 1;
+        self.average += (value - self.average) / self.samples as f32;
+        self.peak = if self.samples == 1 { value } else { self.peak.max(value) };
+    }
+
+    // Weighted by samples, so a three hour session counts more than a short one
+    fn merge(&mut self, other: &Stat) {
+        if other.samples == 0 {
+            return;
+        }
+        let total = self.samples + other.samples;
+        self.average = (self.average * self.samples as f32 + other.average * other.samples as f32) / total as f32;
+        self.peak = if self.samples == 0 { other.peak } else { self.peak.max(other.peak) };
+        self.samples = total;
+    }
+}
+
+/// One line of sessions.jsonl. Temperatures are in Celsius and loads in
+/// percent, whatever the display units are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub game: String,
+    pub launcher: String,
+    pub start: String,
+    pub end: String,
+    pub unix_start: u64,
+    pub unix_end: u64,
+    pub cpu_temp: Stat,
+    pub gpu_temp: Stat,
+    pub gpu_hotspot: Stat,
+    pub cpu_load: Stat,
+    pub gpu_load: Stat,
+    pub ram_load: Stat,
+}
+
+impl Session {
+    fn new(game: &Game, now: SystemTime) -> Self {
+        Self {
+            game: game.name.clone(),
+            launcher: game.launcher.name().to_string(),
+            start: timestamp::rfc3339(now),
+            end: timestamp::rfc3339(now),
+            unix_start: unix_seconds(now),
+            unix_end: unix_seconds(now),
+            cpu_temp: Stat::default(),
+            gpu_temp: Stat::default(),
+            gpu_hotspot: Stat::default(),
+            cpu_load: Stat::default(),
+            gpu_load: Stat::default(),
+            ram_load: Stat::default(),
+        }
+    }
+
+    fn add(&mut self, data: &SensorData, now: SystemTime) {
+        self.cpu_temp.add(parse_reading(&data.cpu_temp));
+        self.gpu_temp.add(parse_reading(&data.gpu_edge));
+        self.gpu_hotspot.add(parse_reading(&data.gpu_hotspot));
+        self.cpu_load.add(Some(data.cpu_usage));
+        // Only nvidia-smi reports a GPU load; elsewhere it stays at 0
+        self.gpu_load.add(Some(data.gpu_utilization).filter(|_| metrics::gpu_vendor(&data.gpu_name) == "nvidia"));
+        self.ram_load.add(Some(data.ram_percent));
+        self.end = timestamp::rfc3339(now);
+        self.unix_end = unix_seconds(now);
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.unix_end.saturating_sub(self.unix_start))
+    }
+}
+
+struct Active {
+    game: Game,
+    session: Session,
+    started: SystemTime,
+    // When the game's process went away, while waiting for it to return
+    missing_since: Option<SystemTime>,
+    saved: SystemTime,
+}
+
+/// Follows the published process lists, opens a session when a game starts
+/// and appends it to the session log once the game has quit
+pub struct Tracker {
+    path: Option<PathBuf>,
+    history: Option<Store>,
+    detector: Detector,
+    active: Option<Active>,
+}
+
+impl Tracker {
+    /// The game being played and since when
+    pub fn current(&self) -> Option<(&Game, SystemTime)> {
+        self.active
+            .as_ref()
+            .filter(|active| active.missing_since.is_none())
+            .map(|active| (&active.game, active.started))
+    }
+
+    fn update(&mut self, snapshot: &Snapshot) {
+        // Not tracking processes in this mode
+        if snapshot.processes.is_empty() {
+            return;
+        }
+        let now = snapshot.timestamp;
+        let detected = self.detector.detect(&snapshot.processes);
+
+        if let Some(active) = &mut self.active {
+            match &detected {
+                Some(game) if game.name == active.game.name => {
+                    active.game = game.clone();
+                    active.missing_since = None;
+                    active.session.add(&snapshot.data, now);
+                }
+                // Another game started right away: this one is over
+                Some(_) => self.finish(),
+                None => {
+                    let missing_since = *active.missing_since.get_or_insert(now);
+                    if now.duration_since(missing_since).unwrap_or_default() >= GRACE {
+                        self.finish();
+                    }
+                }
+            }
+        }
+
+        if self.active.is_none()
+            && let Some(game) = detected
+        {
+            let mut session = Session::new(&game, now);
+            session.add(&snapshot.data, now);
+            self.active = Some(Active {
+                game,
+                session,
+                started: now,
+                missing_since: None,
+                saved: now,
+            });
+        }
+
+        if let Some(active) = &mut self.active
+            && active.missing_since.is_none()
+            && now.duration_since(active.saved).unwrap_or_default() >= CHECKPOINT
+            && let Some(path) = &self.path
+        {
+            active.saved = now;
+            if let Err(err) = write_checkpoint(&checkpoint_path(path), &active.session) {
+                log::error!("Cannot save the game session: {}", err);
+            }
+        }
+    }
+
+    // The session ends with its last sample, not when the grace ran out
+    fn finish(&mut self) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        if let Some(path) = &self.path {
+            record(path, self.history.as_ref(), &active.session);
+            let _ = fs::remove_file(checkpoint_path(path));
+        }
+    }
+}
+
+/// Starts detecting games in the published snapshots. A session the last
+/// run could not finish (a crash, a shutdown mid-game) is recorded first,
+/// as far as its last checkpoint.
+pub fn start(publisher: &Publisher, config: &Config, history: Option<Store>) -> Rc<RefCell<Tracker>> {
+    let path = config.games.path();
+    match &path {
+        Some(path) => recover(path, history.as_ref()),
+        None => log::warn!("Cannot record game sessions: neither $XDG_DATA_HOME nor $HOME is set"),
+    }
+
+    let tracker = Rc::new(RefCell::new(Tracker {
+        path,
+        history,
+        detector: Detector::new(),
+        active: None,
+    }));
+
+    let tracker_ref = tracker.clone();
+    publisher.subscribe(move |snapshot| {
+        tracker_ref.borrow_mut().update(snapshot);
+        glib::ControlFlow::Continue
+    });
+    tracker
+}
+
+fn recover(path: &Path, history: Option<&Store>) {
+    let checkpoint = checkpoint_path(path);
+    let Ok(text) = fs::read_to_string(&checkpoint) else {
+        return;
+    };
+    if let Ok(session) = serde_json::from_str::<Session>(&text) {
+        record(path, history, &session);
+    }
+    let _ = fs::remove_file(checkpoint);
+}
+
+// Appends the session to the log, and to the history database when it is on
+fn record(path: &Path, history: Option<&Store>, session: &Session) {
+    if session.duration() < MIN_DURATION {
+        return;
+    }
+    if let Err(err) = append(path, session) {
+        log::error!("Cannot record the game session in {}: {}", path.display(), err);
+    }
+    if let Some(history) = history {
+        history.add_session(session);
+    }
+}
+
+fn append(path: &Path, session: &Session) -> io::Result<()> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(session).map_err(io::Error::other)?)
+}
+
+fn checkpoint_path(path: &Path) -> PathBuf {
+    path.with_extension("current.json")
+}
+
+// Written beside the log and renamed into place, so it is never half written
+fn write_checkpoint(checkpoint: &Path, session: &Session) -> io::Result<()> {
+    if let Some(dir) = checkpoint.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = checkpoint.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(session).map_err(io::Error::other)?)?;
+    fs::rename(&tmp, checkpoint)
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Session>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut sessions = Vec::new();
+    for line in BufReader::new(file).lines() {
+        // A line cut short by a full disk is skipped, not fatal
+        if let Ok(session) = serde_json::from_str::<Session>(&line?) {
+            sessions.push(session);
+        }
+    }
+    Ok(sessions)
+}
+
+// Every session of one game, merged
+#[derive(Default)]
+struct Totals {
+    sessions: u64,
+    played: Duration,
+    last_played: u64,
+    cpu_temp: Stat,
+    gpu_temp: Stat,
+    gpu_hotspot: Stat,
+    cpu_load: Stat,
+    gpu_load: Stat,
+}
+
+/// The --games table: every game's sessions merged, most recently played first
+pub fn report(config: &Config) -> Result<String, String> {
+    let path = config
+        .games
+        .path()
+        .ok_or("neither $XDG_DATA_HOME nor $HOME is set")?;
+    let sessions = load(&path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    if sessions.is_empty() {
+        return Ok(format!("No game sessions recorded yet in {}\n", path.display()));
+    }
+
+    let mut games: BTreeMap<&str, Totals> = BTreeMap::new();
+    for session in &sessions {
+        let totals = games.entry(&session.game).or_default();
+        totals.sessions += 1;
+        totals.played += session.duration();
+        totals.last_played = totals.last_played.max(session.unix_end);
+        totals.cpu_temp.merge(&session.cpu_temp);
+        totals.gpu_temp.merge(&session.gpu_temp);
+        totals.gpu_hotspot.merge(&session.gpu_hotspot);
+        totals.cpu_load.merge(&session.cpu_load);
+        totals.gpu_load.merge(&session.gpu_load);
+    }
+    let mut games: Vec<_> = games.into_iter().collect();
+    games.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.last_played));
+
+    let unit = config.units.temperature;
+    let temperature = |stat: &Stat| match stat.samples {
+        0 => "—".to_string(),
+        _ => format!("{:.0} / {:.0}", unit.convert(stat.average), unit.convert(stat.peak)),
+    };
+    let percent = |stat: &Stat| match stat.samples {
+        0 => "—".to_string(),
+        _ => format!("{:.0}%", stat.average),
+    };
+
+    let header = [
+        "Game".to_string(),
+        "Sessions".to_string(),
+        "Played".to_string(),
+        format!("CPU {} avg / peak", unit.symbol()),
+        format!("GPU {} avg / peak", unit.symbol()),
+        format!("Hotspot {} peak", unit.symbol()),
+        "CPU load".to_string(),
+        "GPU load".to_string(),
+    ];
+    let mut rows = vec![header];
+    for (game, total) in &games {
+        rows.push([
+            game.to_string(),
+            total.sessions.to_string(),
+            format_duration(total.played),
+            temperature(&total.cpu_temp),
+            temperature(&total.gpu_temp),
+            match total.gpu_hotspot.samples {
+                0 => "—".to_string(),
+                _ => format!("{:.0}", unit.convert(total.gpu_hotspot.peak)),
+            },
+            percent(&total.cpu_load),
+            percent(&total.gpu_load),
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// "2 h 05 min", "12 min", or "under a minute"
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "under a minute".to_string(),
+        (0, minutes) => format!("{} min", minutes),
+        (hours, minutes) => format!("{} h {:02} min", hours, minutes),
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::Launcher;
+    use crate::processes::Process;
+    use std::sync::Arc;
+
+    fn session(data: &SensorData) -> Session {
+        let game = Game { name: "Elden Ring".to_string(), launcher: Launcher::Steam, pid: 1 };
+        let mut session = Session::new(&game, UNIX_EPOCH);
+        session.add(data, UNIX_EPOCH + Duration::from_secs(1));
+        session
+    }
+
+    #[test]
+    fn gpu_load_only_from_nvidia() {
+        let mut data = SensorData::new();
+        data.gpu_name = "Radeon RX 7900 XT/7900 XTX/7900M".to_string();
+        data.gpu_edge = "+52.0°C".to_string();
+        data.cpu_usage = 40.0;
+        let radeon = session(&data);
+        assert_eq!(radeon.gpu_load.samples, 0);
+        assert_eq!(radeon.gpu_temp.samples, 1);
+        assert_eq!(radeon.cpu_load.samples, 1);
+
+        data.gpu_name = "NVIDIA GeForce RTX 3070".to_string();
+        data.gpu_utilization = 97.0;
+        let nvidia = session(&data);
+        assert_eq!(nvidia.gpu_load.samples, 1);
+        assert_eq!(nvidia.gpu_load.average, 97.0);
+    }
+
+    const T: u64 = 1_700_000_000;
+
+    fn tracker(name: &str) -> (Tracker, PathBuf) {
+        let path = std::env::temp_dir().join(format!("gamer-monitor-{}-{}.jsonl", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(checkpoint_path(&path));
+        let tracker = Tracker { path: Some(path.clone()), history: None, detector: Detector::new(), active: None };
+        (tracker, path)
+    }
+
+    // One sample at T + seconds, with the given game running or none
+    fn sample(tracker: &mut Tracker, seconds: u64, game: Option<&str>) {
+        let mut processes = vec![Process {
+            pid: 1,
+            parent: 0,
+            name: "systemd".to_string(),
+            command: "/sbin/init".to_string(),
+            cpu_percent: 0.0,
+            memory_bytes: 0,
+            gpu_percent: None,
+            vram_bytes: None,
+        }];
+        if let Some(game) = game {
+            processes.push(Process {
+                // The detector follows a pid, so every game gets its own
+                pid: 100 + game.len() as u32,
+                parent: 1,
+                name: format!("{}.exe", game),
+                command: format!("C:\\Games\\{}.exe", game),
+                ..processes[0].clone()
+            });
+        }
+        let mut data = SensorData::new();
+        data.cpu_usage = 50.0;
+        tracker.update(&Snapshot {
+            sequence: seconds,
+            timestamp: UNIX_EPOCH + Duration::from_secs(T + seconds),
+            data,
+            processes: Arc::new(processes),
+            diagnostics: Arc::new(Vec::new()),
+        });
+    }
+
+    #[test]
+    fn a_restart_within_the_grace_keeps_the_session() {
+        let (mut tracker, path) = tracker("grace");
+        for seconds in (0..=120).step_by(10) {
+            sample(&mut tracker, seconds, Some("Hades"));
+        }
+        // Gone for 20 seconds, then back
+        sample(&mut tracker, 130, None);
+        assert!(tracker.current().is_none());
+        sample(&mut tracker, 150, Some("Hades"));
+        assert_eq!(tracker.current().map(|(game, started)| (game.name.as_str(), started)), Some(("Hades", UNIX_EPOCH + Duration::from_secs(T))));
+
+        sample(&mut tracker, 160, None);
+        sample(&mut tracker, 189, None);
+        assert!(tracker.active.is_some());
+        assert!(load(&path).unwrap().is_empty());
+        sample(&mut tracker, 190, None);
+        assert!(tracker.active.is_none());
+
+        let sessions = load(&path).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].game, "Hades");
+        // Ends with the last sample, not when the grace ran out
+        assert_eq!((sessions[0].unix_start, sessions[0].unix_end), (T, T + 150));
+        assert_eq!(sessions[0].cpu_load.samples, 14);
+        assert!(!checkpoint_path(&path).exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn short_sessions_are_not_recorded() {
+        let (mut tracker, path) = tracker("short");
+        for seconds in (0..=50).step_by(10) {
+            sample(&mut tracker, seconds, Some("Launcher"));
+        }
+        // Another game right away ends the first without waiting for the grace
+        sample(&mut tracker, 60, Some("Hades"));
+        assert_eq!(tracker.current().map(|(game, _)| game.name.clone()), Some("Hades".to_string()));
+        sample(&mut tracker, 119, Some("Hades"));
+        sample(&mut tracker, 120, None);
+        sample(&mut tracker, 150, None);
+        assert!(tracker.active.is_none());
+
+        assert!(load(&path).unwrap().is_empty());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn an_unfinished_session_is_recovered() {
+        let (mut tracker, path) = tracker("recover");
+        let checkpoint = checkpoint_path(&path);
+        for seconds in (0..60).step_by(10) {
+            sample(&mut tracker, seconds, Some("Hades"));
+        }
+        assert!(!checkpoint.exists());
+        for seconds in (60..=90).step_by(10) {
+            sample(&mut tracker, seconds, Some("Hades"));
+        }
+        assert!(checkpoint.exists());
+        assert!(!checkpoint.with_extension("json.tmp").exists());
+
+        // The last run crashed mid-game: the next one records it up to its last checkpoint
+        drop(tracker);
+        recover(&path, None);
+        let sessions = load(&path).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].unix_start, sessions[0].unix_end), (T, T + 60));
+        assert!(!checkpoint.exists());
+
+        // And only once
+        recover(&path, None);
+        assert_eq!(load(&path).unwrap().len(), 1);
+        let _ = fs::remove_file(&path);
+    }
+}
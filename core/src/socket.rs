@@ -0,0 +1,505 @@
+use crate::config::Config;
+use crate::history::Store;
+use crate::metrics;
+use crate::output;
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use crate::timestamp;
+use crate::client::PROTOCOL_VERSION;
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, ToSocketAddrs};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NO_READINGS: i64 = 1;
+const UNAUTHORIZED: i64 = 2;
+
+const MAX_LABEL: usize = 200;
+
+// Longer request lines close the connection; real requests are far shorter
+const MAX_LINE: usize = 64 * 1024;
+
+// How long a TCP peer has to send hello with the agent's token
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Lines waiting for a connection's writer. A subscriber this far behind
+// stops getting notifications instead of being buffered forever.
+const QUEUE_LENGTH: usize = 64;
+
+// A peer that does not read for this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What the connection threads can see. Snapshots are rendered to JSON on
+// the main thread as they are published, like the Prometheus page.
+struct Shared {
+    history: VecDeque<(SystemTime, Value)>,
+    markers: VecDeque<(SystemTime, Value)>,
+    retention: Duration,
+    // Markers are also saved there, so they outlive the process
+    store: Option<Store>,
+    // One line channel per subscribed connection
+    subscribers: Vec<SyncSender<String>>,
+}
+
+impl Shared {
+    fn prune(&mut self, now: SystemTime) {
+        let expired = |time: &SystemTime| now.duration_since(*time).unwrap_or_default() > self.retention;
+        while self.history.front().is_some_and(|(time, _)| expired(time)) {
+            self.history.pop_front();
+        }
+        while self.markers.front().is_some_and(|(time, _)| expired(time)) {
+            self.markers.pop_front();
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) {
+        let line = json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string();
+        // Full or gone alike: never block the publisher on a slow reader
+        self.subscribers.retain(|subscriber| subscriber.try_send(line.clone()).is_ok());
+    }
+}
+
+/// Answers JSON-RPC requests from other programs: on a Unix socket for this
+/// machine, and on TCP for viewers on other machines when running as an
+/// agent. Both listeners share one history.
+#[derive(Clone)]
+pub struct Server {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Server {
+    pub fn new(publisher: &Publisher, config: &Config, store: Option<Store>) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            history: VecDeque::new(),
+            markers: VecDeque::new(),
+            retention: Duration::from_secs(config.exporter.socket.history_minutes * 60),
+            store,
+            subscribers: Vec::new(),
+        }));
+
+        let update = {
+            let shared = shared.clone();
+            let fans = config.fans.clone();
+            move |snapshot: &Snapshot| {
+                let document = document(snapshot, &metrics::collect(&snapshot.data, &fans));
+                let mut shared = shared.lock().unwrap();
+                shared.history.push_back((snapshot.timestamp, document.clone()));
+                shared.prune(snapshot.timestamp);
+                shared.notify("snapshot", &document);
+            }
+        };
+        if let Some(snapshot) = publisher.latest() {
+            update(&snapshot);
+        }
+        publisher.subscribe(move |snapshot| {
+            update(snapshot);
+            glib::ControlFlow::Continue
+        });
+
+        Self { shared }
+    }
+
+    /// A socket left behind by a crashed instance is replaced, but one that
+    /// still answers belongs to another instance and is left alone
+    pub fn listen_unix(&self, path: &Path) -> io::Result<()> {
+        let listener = bind(path)?;
+        let shared = self.shared.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    if let Ok(reader) = stream.try_clone() {
+                        let _ = serve(reader, stream, &shared, None, || {});
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// With a token, every request but "hello" is refused until a hello
+    /// carrying the same token was received on the connection, and a peer
+    /// that does not send one within a few seconds is disconnected
+    pub fn listen_tcp(&self, address: &str, token: Option<String>) -> io::Result<SocketAddr> {
+        let listener = bind_tcp(address)?;
+        let local = listener.local_addr()?;
+        let shared = self.shared.clone();
+        let token: Option<Arc<str>> = token.map(Arc::from);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = shared.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    let _ = stream.set_nodelay(true);
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    let (Ok(reader), Ok(watched)) = (stream.try_clone(), stream.try_clone()) else {
+                        return;
+                    };
+
+                    let authenticated = Arc::new(AtomicBool::new(token.is_none()));
+                    if token.is_some() {
+                        let _ = stream.set_read_timeout(Some(HELLO_TIMEOUT));
+                        // The read timeout alone would let a peer trickle in a byte at a time
+                        let authenticated = authenticated.clone();
+                        let watched = watched.try_clone();
+                        thread::spawn(move || {
+                            thread::sleep(HELLO_TIMEOUT);
+                            if !authenticated.load(Ordering::Acquire)
+                                && let Ok(watched) = watched
+                            {
+                                let _ = watched.shutdown(Shutdown::Both);
+                            }
+                        });
+                    }
+
+                    let _ = serve(reader, stream, &shared, token.as_deref(), move || {
+                        authenticated.store(true, Ordering::Release);
+                        let _ = watched.set_read_timeout(None);
+                    });
+                });
+            }
+        });
+        Ok(local)
+    }
+}
+
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is listening there"));
+        }
+        fs::remove_file(path)?;
+    }
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    // Only the user's own programs, even outside $XDG_RUNTIME_DIR
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn bind_tcp(address: &str) -> io::Result<TcpListener> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpListener::bind(addr) {
+            Ok(listener) => return Ok(listener),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::other("no address to listen on")))
+}
+
+// Replies and notifications share one writer thread, so a subscriber's
+// stream never interleaves with a reply in the middle of a line.
+// `authenticated` is called once the token was checked.
+fn serve(
+    reader: impl Read,
+    mut writer: impl Write + Send + 'static,
+    shared: &Arc<Mutex<Shared>>,
+    token: Option<&str>,
+    authenticated: impl FnOnce(),
+) -> io::Result<()> {
+    let (sender, receiver) = mpsc::sync_channel::<String>(QUEUE_LENGTH);
+    thread::spawn(move || {
+        for line in receiver {
+            if writeln!(writer, "{}", line).and_then(|()| writer.flush()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session {
+        token,
+        authenticated: token.is_none(),
+        subscribed: false,
+    };
+    let mut authenticated = Some(authenticated).filter(|_| token.is_some());
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        // One byte more than allowed, to tell a full line from a cut one
+        if (&mut reader).take(MAX_LINE as u64 + 1).read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.len() > MAX_LINE {
+            let _ = sender.send(error(Value::Null, INVALID_REQUEST, &format!("request longer than {} bytes", MAX_LINE)).to_string());
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = handle(&line, shared, &sender, &mut session);
+        if session.authenticated
+            && let Some(authenticated) = authenticated.take()
+        {
+            authenticated();
+        }
+        if let Some(reply) = reply
+            && sender.send(reply.to_string()).is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Session<'a> {
+    token: Option<&'a str>,
+    authenticated: bool,
+    subscribed: bool,
+}
+
+// Answers one request line; notifications sent by the client (no id) get
+// no reply, as JSON-RPC requires
+fn handle(line: &str, shared: &Arc<Mutex<Shared>>, sender: &SyncSender<String>, session: &mut Session) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return Some(error(Value::Null, PARSE_ERROR, &err.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Some(error(id.unwrap_or_default(), INVALID_REQUEST, "missing method"));
+    };
+    let params = request.get("params").cloned().unwrap_or_default();
+
+    let result = match method {
+        "hello" => {
+            if let Some(expected) = session.token {
+                let given = params.get("token").and_then(Value::as_str).unwrap_or_default();
+                session.authenticated = same_token(given, expected);
+            }
+            if session.authenticated {
+                Ok(json!({ "protocol": PROTOCOL_VERSION, "version": env!("CARGO_PKG_VERSION") }))
+            } else {
+                Err((UNAUTHORIZED, "wrong or missing token".to_string()))
+            }
+        }
+        _ if !session.authenticated => Err((UNAUTHORIZED, "send hello with the agent's token first".to_string())),
+        "snapshot" => match shared.lock().unwrap().history.back() {
+            Some((_, document)) => Ok(document.clone()),
+            None => Err((NO_READINGS, "no readings yet".to_string())),
+        },
+        "history" => history(&params, &shared.lock().unwrap()),
+        "add_marker" => add_marker(&params, &mut shared.lock().unwrap()),
+        "subscribe" => {
+            if !session.subscribed {
+                shared.lock().unwrap().subscribers.push(sender.clone());
+                session.subscribed = true;
+            }
+            Ok(json!({ "subscribed": true }))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+    };
+
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error(id, code, &message),
+    })
+}
+
+// Takes as long for a wrong first byte as for a wrong last one
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// since and until are Unix times in seconds; either may be left out
+fn history(params: &Value, shared: &Shared) -> Result<Value, (i64, String)> {
+    let bound = |name: &str| match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_f64()
+            .map(Some)
+            .ok_or((INVALID_PARAMS, format!("{} must be a Unix time in seconds", name))),
+    };
+    let (since, until) = (bound("since")?, bound("until")?);
+    let in_range = |time: &SystemTime| {
+        let seconds = unix_time(*time);
+        since.is_none_or(|since| seconds >= since) && until.is_none_or(|until| seconds <= until)
+    };
+    let select = |entries: &VecDeque<(SystemTime, Value)>| -> Vec<Value> {
+        entries
+            .iter()
+            .filter(|(time, _)| in_range(time))
+            .map(|(_, value)| value.clone())
+            .collect()
+    };
+
+    Ok(json!({ "snapshots": select(&shared.history), "markers": select(&shared.markers) }))
+}
+
+fn add_marker(params: &Value, shared: &mut Shared) -> Result<Value, (i64, String)> {
+    let label = params
+        .get("label")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|label| !label.is_empty() && label.len() <= MAX_LABEL)
+        .ok_or((INVALID_PARAMS, format!("label must be a non-empty string of up to {} bytes", MAX_LABEL)))?;
+
+    let now = SystemTime::now();
+    let marker = json!({
+        "timestamp": timestamp::rfc3339(now),
+        "unix_time": unix_time(now),
+        "label": label,
+    });
+    if let Some(store) = &shared.store {
+        store.add_marker(now, label);
+    }
+    shared.markers.push_back((now, marker.clone()));
+    shared.notify("marker", &marker);
+    Ok(marker)
+}
+
+// The --format json object plus what a client needs to order and filter
+// it, and the raw sensor data a remote window draws its sections from
+fn document(snapshot: &Snapshot, readings: &[metrics::Reading]) -> Value {
+    let mut document = output::document(snapshot, readings);
+    document["sequence"] = json!(snapshot.sequence);
+    document["unix_time"] = json!(unix_time(snapshot.timestamp));
+    document["sensors"] = serde_json::to_value(&snapshot.data).unwrap_or_default();
+    document
+}
+
+fn unix_time(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+
+    fn shared() -> Arc<Mutex<Shared>> {
+        Arc::new(Mutex::new(Shared {
+            history: VecDeque::new(),
+            markers: VecDeque::new(),
+            retention: Duration::from_secs(60),
+            store: None,
+            subscribers: Vec::new(),
+        }))
+    }
+
+    // Replies as the text a client reads, for the given requests in order
+    fn replies(token: Option<&str>, shared: &Arc<Mutex<Shared>>, requests: &[&str]) -> (Vec<String>, Receiver<String>) {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LENGTH);
+        let mut session = Session {
+            token,
+            authenticated: token.is_none(),
+            subscribed: false,
+        };
+        let replies = requests
+            .iter()
+            .map(|request| handle(request, shared, &sender, &mut session).map(|reply| reply.to_string()).unwrap_or_default())
+            .collect();
+        (replies, receiver)
+    }
+
+    #[test]
+    fn errors() {
+        let (errors, _) = replies(
+            None,
+            &shared(),
+            &[
+                r#"{"jsonrpc":"2.0","id":1,"method":"reboot"}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"history","params":{"since":"yesterday"}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"add_marker","params":{"label":"  "}}"#,
+                r#"{"jsonrpc":"2.0","id":4,"method":"snapshot"}"#,
+                r#"{"jsonrpc":"2.0","id":"a","params":{}}"#,
+                r#"{"jsonrpc":"2.0","method":"reboot"}"#,
+            ],
+        );
+        assert_eq!(
+            errors,
+            [
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"unknown method 'reboot'"}}"#,
+                r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32602,"message":"since must be a Unix time in seconds"}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"error":{"code":-32602,"message":"label must be a non-empty string of up to 200 bytes"}}"#,
+                r#"{"jsonrpc":"2.0","id":4,"error":{"code":1,"message":"no readings yet"}}"#,
+                r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32600,"message":"missing method"}}"#,
+                // A notification gets no reply, not even an error
+                "",
+            ]
+        );
+
+        // The message comes from serde_json, so only the code is checked
+        let (parse_error, _) = replies(None, &shared(), &[r#"{"jsonrpc":"2.0","id":5,"method""#]);
+        let reply: Value = serde_json::from_str(&parse_error[0]).unwrap();
+        assert_eq!((&reply["id"], &reply["error"]["code"]), (&Value::Null, &json!(PARSE_ERROR)));
+    }
+
+    #[test]
+    fn token() {
+        let (replies, _) = replies(
+            Some("secret"),
+            &shared(),
+            &[
+                r#"{"jsonrpc":"2.0","id":1,"method":"snapshot"}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"hello","params":{"token":"secreT"}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"hello","params":{"token":"secret"}}"#,
+                r#"{"jsonrpc":"2.0","id":4,"method":"history","params":{"since":0}}"#,
+            ],
+        );
+        assert_eq!(replies[0], r#"{"jsonrpc":"2.0","id":1,"error":{"code":2,"message":"send hello with the agent's token first"}}"#);
+        assert_eq!(replies[1], r#"{"jsonrpc":"2.0","id":2,"error":{"code":2,"message":"wrong or missing token"}}"#);
+        assert_eq!(
+            replies[2],
+            format!(r#"{{"jsonrpc":"2.0","id":3,"result":{{"protocol":{},"version":"{}"}}}}"#, PROTOCOL_VERSION, env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(replies[3], r#"{"jsonrpc":"2.0","id":4,"result":{"snapshots":[],"markers":[]}}"#);
+    }
+
+    #[test]
+    fn subscription() {
+        let shared = shared();
+        let (replies, receiver) = replies(
+            None,
+            &shared,
+            &[
+                r#"{"jsonrpc":"2.0","id":1,"method":"subscribe"}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"subscribe"}"#,
+            ],
+        );
+        assert_eq!(replies[1], r#"{"jsonrpc":"2.0","id":2,"result":{"subscribed":true}}"#);
+        assert_eq!(shared.lock().unwrap().subscribers.len(), 1);
+
+        shared.lock().unwrap().notify("marker", &json!({ "label": "boss fight" }));
+        assert_eq!(receiver.try_recv().unwrap(), r#"{"jsonrpc":"2.0","method":"marker","params":{"label":"boss fight"}}"#);
+
+        // A subscriber that stopped reading is dropped once its queue is full
+        for _ in 0..QUEUE_LENGTH {
+            shared.lock().unwrap().notify("marker", &json!({}));
+        }
+        assert_eq!(shared.lock().unwrap().subscribers.len(), 1);
+        shared.lock().unwrap().notify("marker", &json!({}));
+        assert!(shared.lock().unwrap().subscribers.is_empty());
+    }
+}
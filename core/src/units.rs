@@ -0,0 +1,272 @@
+use crate::metrics::Quantity;
+use crate::sensors::parse_reading;
+use serde::{Deserialize, Serialize};
+
+const BTU_PER_HOUR_PER_WATT: f32 = 3.412_142;
+const MIB: f64 = 1024.0 * 1024.0;
+const GIB: f64 = 1024.0 * MIB;
+
+/// Sensors always report Celsius, MiB and watts. Everything in this module
+/// converts at display time, so the collected readings never change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub memory: MemoryUnit,
+    pub power: PowerUnit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+/// IEC prefixes (MiB, GiB) are powers of 1024, SI prefixes (MB, GB) powers of 1000
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryUnit {
+    #[default]
+    Iec,
+    Si,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerUnit {
+    #[default]
+    Watts,
+    #[serde(rename = "btu")]
+    BtuPerHour,
+}
+
+impl TemperatureUnit {
+    pub const ALL: [TemperatureUnit; 3] = [
+        TemperatureUnit::Celsius,
+        TemperatureUnit::Fahrenheit,
+        TemperatureUnit::Kelvin,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+            TemperatureUnit::Kelvin => "K",
+        }
+    }
+
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+}
+
+impl MemoryUnit {
+    pub const ALL: [MemoryUnit; 2] = [MemoryUnit::Iec, MemoryUnit::Si];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MemoryUnit::Iec => "GiB / MiB",
+            MemoryUnit::Si => "GB / MB",
+        }
+    }
+
+    fn giga(self) -> (f64, &'static str) {
+        match self {
+            MemoryUnit::Iec => (GIB, "GiB"),
+            MemoryUnit::Si => (1e9, "GB"),
+        }
+    }
+
+    fn mega(self) -> (f64, &'static str) {
+        match self {
+            MemoryUnit::Iec => (MIB, "MiB"),
+            MemoryUnit::Si => (1e6, "MB"),
+        }
+    }
+}
+
+impl PowerUnit {
+    pub const ALL: [PowerUnit; 2] = [PowerUnit::Watts, PowerUnit::BtuPerHour];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PowerUnit::Watts => "W",
+            PowerUnit::BtuPerHour => "BTU/h",
+        }
+    }
+
+    pub fn convert(self, watts: f32) -> f32 {
+        match self {
+            PowerUnit::Watts => watts,
+            PowerUnit::BtuPerHour => watts * BTU_PER_HOUR_PER_WATT,
+        }
+    }
+}
+
+impl Units {
+    pub fn temperature_symbol(&self) -> &'static str {
+        self.temperature.symbol()
+    }
+
+    /// Converts a raw Celsius reading such as "54.0" or "+54.0°C".
+    /// Celsius readings are shown exactly as the sensor reported them.
+    pub fn temperature(&self, text: &str) -> String {
+        match (self.temperature, parse_reading(text)) {
+            (TemperatureUnit::Celsius, _) | (_, None) => text.to_string(),
+            (unit, Some(celsius)) => format!("{:.1}", unit.convert(celsius)),
+        }
+    }
+
+    /// RAM is collected in GiB (see SensorData::update_ram)
+    pub fn ram(&self, gib: f32) -> String {
+        let (divisor, symbol) = self.memory.giga();
+        format!("{:.1} {}", gib as f64 * GIB / divisor, symbol)
+    }
+
+    /// VRAM is collected in MiB as reported by nvidia-smi
+    pub fn vram(&self, used_mib: &str, total_mib: &str) -> String {
+        let (divisor, symbol) = self.memory.mega();
+
+        match (self.memory, used_mib.parse::<f64>(), total_mib.parse::<f64>()) {
+            (MemoryUnit::Iec, _, _) | (_, Err(_), _) | (_, _, Err(_)) => {
+                format!("{} / {} {}", used_mib, total_mib, symbol)
+            }
+            (_, Ok(used), Ok(total)) => format!(
+                "{:.0} / {:.0} {}",
+                used * MIB / divisor,
+                total * MIB / divisor,
+                symbol
+            ),
+        }
+    }
+
+    /// A byte count such as a process's memory: MiB below one GiB, GiB above
+    pub fn bytes(&self, bytes: u64) -> String {
+        let (giga, giga_symbol) = self.memory.giga();
+        let (mega, mega_symbol) = self.memory.mega();
+        if bytes as f64 >= giga {
+            format!("{:.1} {}", bytes as f64 / giga, giga_symbol)
+        } else {
+            format!("{:.0} {}", bytes as f64 / mega, mega_symbol)
+        }
+    }
+
+    /// Power draw such as "125.50 W"; non-numeric values like "N/A" are kept
+    pub fn power(&self, text: &str) -> String {
+        match (self.power, parse_reading(text)) {
+            (PowerUnit::Watts, _) | (_, None) => text.to_string(),
+            (unit, Some(watts)) => format!("{:.0} {}", unit.convert(watts), unit.symbol()),
+        }
+    }
+
+    /// Formats a numeric reading from metrics::collect together with its unit
+    pub fn reading(&self, quantity: Quantity, value: f64) -> String {
+        match quantity {
+            Quantity::Celsius => format!(
+                "{:.1} {}",
+                self.temperature.convert(value as f32),
+                self.temperature_symbol()
+            ),
+            Quantity::Percent => format!("{:.1}%", value),
+            Quantity::Rpm => format!("{:.0} RPM", value),
+            Quantity::Watts => format!("{:.1} {}", self.power.convert(value as f32), self.power.symbol()),
+            Quantity::Mebibytes => {
+                let (divisor, symbol) = self.memory.mega();
+                format!("{:.0} {}", value * MIB / divisor, symbol)
+            }
+            Quantity::Gibibytes => self.ram(value as f32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(temperature: TemperatureUnit, memory: MemoryUnit, power: PowerUnit) -> Units {
+        Units { temperature, memory, power }
+    }
+
+    #[test]
+    fn temperatures() {
+        use TemperatureUnit::*;
+        for (unit, text, expected) in [
+            (Celsius, "+54.0°C", "+54.0°C"),
+            (Celsius, "54", "54"),
+            (Fahrenheit, "+54.0°C", "129.2"),
+            (Fahrenheit, "-40", "-40.0"),
+            (Fahrenheit, "100.0", "212.0"),
+            (Kelvin, "+54.0°C", "327.1"),
+            (Kelvin, "0", "273.1"),
+            (Fahrenheit, "N/A", "N/A"),
+            (Kelvin, "", ""),
+        ] {
+            let units = units(unit, MemoryUnit::Iec, PowerUnit::Watts);
+            assert_eq!(units.temperature(text), expected, "{:?} {}", unit, text);
+        }
+    }
+
+    #[test]
+    fn power() {
+        for (unit, text, expected) in [
+            (PowerUnit::Watts, "125.50 W", "125.50 W"),
+            (PowerUnit::BtuPerHour, "125.50 W", "428 BTU/h"),
+            (PowerUnit::BtuPerHour, "100", "341 BTU/h"),
+            (PowerUnit::BtuPerHour, "N/A", "N/A"),
+            (PowerUnit::BtuPerHour, "[N/A]", "[N/A]"),
+        ] {
+            let units = units(TemperatureUnit::Celsius, MemoryUnit::Iec, unit);
+            assert_eq!(units.power(text), expected, "{:?} {}", unit, text);
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let iec = units(TemperatureUnit::Celsius, MemoryUnit::Iec, PowerUnit::Watts);
+        let si = units(TemperatureUnit::Celsius, MemoryUnit::Si, PowerUnit::Watts);
+
+        assert_eq!(iec.ram(15.5), "15.5 GiB");
+        assert_eq!(si.ram(15.5), "16.6 GB");
+        // nvidia-smi's MiB are shown as reported, or converted to MB
+        assert_eq!(iec.vram("2048", "8192"), "2048 / 8192 MiB");
+        assert_eq!(si.vram("2048", "8192"), "2147 / 8590 MB");
+        assert_eq!(si.vram("N/A", "8192"), "N/A / 8192 MB");
+        assert_eq!(iec.bytes(300 << 20), "300 MiB");
+        assert_eq!(iec.bytes(3 << 30), "3.0 GiB");
+        assert_eq!(si.bytes(999_000_000), "999 MB");
+        assert_eq!(si.bytes(1_500_000_000), "1.5 GB");
+    }
+
+    #[test]
+    fn readings() {
+        let metric = units(TemperatureUnit::Celsius, MemoryUnit::Iec, PowerUnit::Watts);
+        let imperial = units(TemperatureUnit::Fahrenheit, MemoryUnit::Si, PowerUnit::BtuPerHour);
+        let kelvin = units(TemperatureUnit::Kelvin, MemoryUnit::Iec, PowerUnit::Watts);
+        for (quantity, value, expected_metric, expected_imperial) in [
+            (Quantity::Celsius, 54.0, "54.0 °C", "129.2 °F"),
+            (Quantity::Percent, 97.25, "97.2%", "97.2%"),
+            (Quantity::Rpm, 1187.6, "1188 RPM", "1188 RPM"),
+            (Quantity::Watts, 220.0, "220.0 W", "750.7 BTU/h"),
+            (Quantity::Mebibytes, 8192.0, "8192 MiB", "8590 MB"),
+            (Quantity::Gibibytes, 31.3, "31.3 GiB", "33.6 GB"),
+        ] {
+            assert_eq!(metric.reading(quantity, value), expected_metric, "{:?}", quantity);
+            assert_eq!(imperial.reading(quantity, value), expected_imperial, "{:?}", quantity);
+        }
+        assert_eq!(kelvin.reading(Quantity::Celsius, 54.0), "327.1 K");
+    }
+
+    #[test]
+    fn config_names() {
+        let units: Units = toml::from_str("temperature = \"fahrenheit\"\nmemory = \"si\"\npower = \"btu\"\n").unwrap();
+        assert_eq!(units, self::units(TemperatureUnit::Fahrenheit, MemoryUnit::Si, PowerUnit::BtuPerHour));
+        assert!(toml::from_str::<Units>("power = \"btu/h\"\n").is_err());
+    }
+}
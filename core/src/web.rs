@@ -0,0 +1,192 @@
+use crate::config::Config;
+use crate::http::{self, Request, Response};
+use crate::sampler::Snapshot;
+use crate::scheduler::Publisher;
+use crate::sensors::parse_reading;
+use crate::timestamp;
+use crate::sections::{self, Row};
+use crate::websocket::{self, CLOSE, PING, PONG, TEXT};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+// The page and its assets are compiled into the binary
+const INDEX: &str = include_str!("web/index.html");
+const SCRIPT: &str = include_str!("web/dashboard.js");
+const STYLE: &str = include_str!("web/dashboard.css");
+
+// Views sent to a new page so its charts start filled, about five minutes
+// at the default sampling rates
+const HISTORY: usize = 150;
+// A phone that went to sleep mid-frame is dropped instead of waited for
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Shared {
+    // Rendered views, oldest first
+    history: VecDeque<String>,
+    // One frame channel per open page
+    subscribers: Vec<Sender<Vec<u8>>>,
+}
+
+/// Serves the dashboard and pushes a view of every snapshot to open pages
+/// over a WebSocket. Views are rendered on the main thread, like the
+/// Prometheus page, with the same rows as the window's sections.
+pub fn start(address: &str, publisher: &Publisher, config: &Config) -> io::Result<SocketAddr> {
+    let shared = Arc::new(Mutex::new(Shared {
+        history: VecDeque::new(),
+        subscribers: Vec::new(),
+    }));
+
+    let update = {
+        let shared = shared.clone();
+        let config = config.clone();
+        move |snapshot: &Snapshot| {
+            let view = view(snapshot, &config).to_string();
+            let frame = websocket::frame(TEXT, view.as_bytes());
+
+            let mut shared = shared.lock().unwrap();
+            shared.history.push_back(view);
+            while shared.history.len() > HISTORY {
+                shared.history.pop_front();
+            }
+            shared.subscribers.retain(|subscriber| subscriber.send(frame.clone()).is_ok());
+        }
+    };
+    if let Some(snapshot) = publisher.latest() {
+        update(&snapshot);
+    }
+    publisher.subscribe(move |snapshot| {
+        update(snapshot);
+        glib::ControlFlow::Continue
+    });
+
+    http::serve(
+        address,
+        Arc::new(move |request| match request.path.as_str() {
+            "/" | "/index.html" => Response::new(200, "text/html; charset=utf-8", INDEX),
+            "/dashboard.js" => Response::new(200, "text/javascript; charset=utf-8", SCRIPT),
+            "/dashboard.css" => Response::new(200, "text/css; charset=utf-8", STYLE),
+            "/view.json" => match shared.lock().unwrap().history.back() {
+                Some(view) => Response::new(200, "application/json", view.clone()),
+                None => Response::text(503, "No readings yet\n"),
+            },
+            "/ws" => upgrade(request, &shared),
+            _ => Response::not_found(),
+        }),
+    )
+}
+
+fn upgrade(request: &Request, shared: &Arc<Mutex<Shared>>) -> Response {
+    let wants_websocket = request
+        .header("upgrade")
+        .is_some_and(|protocol| protocol.eq_ignore_ascii_case("websocket"));
+    let Some(key) = request.header("sec-websocket-key").filter(|_| wants_websocket) else {
+        return Response::text(426, "This endpoint only speaks WebSocket\n").with_header("Upgrade", "websocket");
+    };
+
+    let shared = shared.clone();
+    Response::switching_protocols(
+        vec![
+            ("Upgrade", "websocket".to_string()),
+            ("Sec-WebSocket-Accept", websocket::accept_key(key)),
+        ],
+        Box::new(move |stream| stream_views(stream, &shared)),
+    )
+}
+
+// Frames from the reader thread (pongs, the closing handshake) and views
+// from the main thread share one channel, so they never interleave
+fn stream_views(stream: TcpStream, shared: &Arc<Mutex<Shared>>) {
+    let Ok(mut reader) = stream.try_clone() else {
+        return;
+    };
+    // The page only talks when it leaves, so reads wait indefinitely
+    let _ = stream.set_read_timeout(None);
+    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    {
+        let mut shared = shared.lock().unwrap();
+        for view in &shared.history {
+            let _ = sender.send(websocket::frame(TEXT, view.as_bytes()));
+        }
+        shared.subscribers.push(sender.clone());
+    }
+
+    thread::spawn(move || {
+        loop {
+            match websocket::read_frame(&mut reader) {
+                Ok((PING, payload)) => {
+                    if sender.send(websocket::frame(PONG, &payload)).is_err() {
+                        break;
+                    }
+                }
+                Ok((CLOSE, _)) | Err(_) => {
+                    let _ = sender.send(websocket::frame(CLOSE, &[]));
+                    break;
+                }
+                Ok(_) => {}
+            }
+        }
+    });
+
+    let mut writer = &stream;
+    for frame in receiver {
+        let closing = frame[0] & 0x0F == CLOSE;
+        if writer.write_all(&frame).and_then(|()| writer.flush()).is_err() || closing {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+// What the page draws: the window's sections, row by row, with each
+// severity and a number for the charts where the row has one
+fn view(snapshot: &Snapshot, config: &Config) -> Value {
+    let data = &snapshot.data;
+    let sections: Vec<Value> = config
+        .sections
+        .iter()
+        .map(|&section| {
+            let rows: Vec<Value> = sections::section_rows(section, data, config)
+                .iter()
+                .map(|row| match row {
+                    Row::Text(label, value, severity) => json!({
+                        "label": label,
+                        "value": value,
+                        "severity": severity.key(),
+                        "number": parse_reading(value),
+                    }),
+                    Row::Bar(label, fraction, text, severity) => json!({
+                        "label": label,
+                        "value": text,
+                        "severity": severity.key(),
+                        "fraction": fraction.clamp(0.0, 1.0),
+                        "number": fraction * 100.0,
+                    }),
+                })
+                .collect();
+            json!({ "key": section.key(), "title": section.title(), "rows": rows })
+        })
+        .collect();
+
+    let seconds = snapshot
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0);
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "sequence": snapshot.sequence,
+        "timestamp": timestamp::rfc3339(snapshot.timestamp),
+        "unix_time": seconds,
+        "hardware": { "cpu": data.cpu_name, "gpu": data.gpu_name },
+        "sections": sections,
+    })
+}
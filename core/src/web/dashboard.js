@@ -0,0 +1,111 @@
+// Draws the views pushed on /ws: the window's sections, plus a small
+// chart per reading built from the views received so far.
+"use strict";
+
+const HISTORY = 150;
+const RETRY_MS = 3000;
+
+const sectionsElement = document.getElementById("sections");
+const statusElement = document.getElementById("status");
+const updatedElement = document.getElementById("updated");
+
+// "section/label" -> recent numbers, oldest first
+const series = new Map();
+
+function connect() {
+  const scheme = location.protocol === "https:" ? "wss:" : "ws:";
+  const socket = new WebSocket(`${scheme}//${location.host}/ws`);
+
+  socket.onopen = () => setStatus("Live", false);
+  socket.onmessage = (event) => show(JSON.parse(event.data));
+  socket.onclose = () => {
+    setStatus("Disconnected, retrying…", true);
+    setTimeout(connect, RETRY_MS);
+  };
+}
+
+function setStatus(text, offline) {
+  statusElement.textContent = text;
+  statusElement.classList.toggle("offline", offline);
+}
+
+function show(view) {
+  for (const section of view.sections) {
+    for (const row of section.rows) {
+      if (typeof row.number !== "number") continue;
+      const key = `${section.key}/${row.label}`;
+      const values = series.get(key) || [];
+      values.push(row.number);
+      if (values.length > HISTORY) values.shift();
+      series.set(key, values);
+    }
+  }
+
+  sectionsElement.replaceChildren(...view.sections.map(renderSection));
+  updatedElement.textContent = `Updated ${new Date(view.unix_time * 1000).toLocaleTimeString()} · v${view.version}`;
+}
+
+function renderSection(section) {
+  const element = document.createElement("section");
+  const title = document.createElement("h2");
+  title.textContent = section.title;
+  element.append(title);
+
+  for (const row of section.rows) {
+    const line = document.createElement("div");
+    line.className = "row";
+
+    const label = document.createElement("span");
+    label.textContent = row.label;
+    line.append(label, renderValue(row), renderChart(series.get(`${section.key}/${row.label}`), row));
+    element.append(line);
+  }
+  return element;
+}
+
+function renderValue(row) {
+  if (typeof row.fraction !== "number") {
+    const value = document.createElement("span");
+    value.textContent = row.value;
+    value.className = row.severity;
+    return value;
+  }
+
+  const bar = document.createElement("div");
+  bar.className = `bar ${row.severity}`;
+  const fill = document.createElement("div");
+  fill.style.width = `${row.fraction * 100}%`;
+  const text = document.createElement("span");
+  text.textContent = row.value;
+  bar.append(fill, text);
+  return bar;
+}
+
+// A line scaled to the values shown, or an empty cell for text rows
+function renderChart(values, row) {
+  const canvas = document.createElement("canvas");
+  if (!values || values.length < 2) return canvas;
+
+  const ratio = window.devicePixelRatio || 1;
+  canvas.width = 96 * ratio;
+  canvas.height = 24 * ratio;
+  const context = canvas.getContext("2d");
+  context.scale(ratio, ratio);
+
+  const min = Math.min(...values);
+  const span = Math.max(...values) - min || 1;
+  context.strokeStyle = getComputedStyle(document.documentElement)
+    .getPropertyValue(row.severity === "normal" ? "--accent" : `--${row.severity}`);
+  context.lineWidth = 1.5;
+  context.beginPath();
+  values.forEach((value, i) => {
+    const x = ((HISTORY - values.length + i) / (HISTORY - 1)) * 96;
+    const y = 22 - ((value - min) / span) * 20;
+    if (i === 0) context.moveTo(x, y);
+    else context.lineTo(x, y);
+  });
+  context.stroke();
+  return canvas;
+}
+
+connect();
@@ -0,0 +1,215 @@
+use std::io::{self, Read};
+
+// The parts of RFC 6455 a server pushing text needs: the handshake key,
+// unmasked server frames and reading the (masked) client frames.
+// Fragmented client messages are not reassembled, since the dashboard
+// only sends control frames.
+
+pub const TEXT: u8 = 0x1;
+pub const CLOSE: u8 = 0x8;
+pub const PING: u8 = 0x9;
+pub const PONG: u8 = 0xA;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Control frames are at most 125 bytes; anything much larger is not ours
+const MAX_PAYLOAD: u64 = 64 * 1024;
+
+/// Sec-WebSocket-Accept for the client's Sec-WebSocket-Key
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+pub fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// The opcode and unmasked payload of the next frame
+pub fn read_frame(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut bytes = [0u8; 2];
+            reader.read_exact(&mut bytes)?;
+            u16::from_be_bytes(bytes) as u64
+        }
+        127 => {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            u64::from_be_bytes(bytes)
+        }
+        len => len as u64,
+    };
+    if len > MAX_PAYLOAD {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+
+    Ok((opcode, payload))
+}
+
+// Only used for the handshake, where SHA-1 is what the protocol requires
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A client frame with the given mask, as a browser would send it
+    fn masked(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+        let mut frame = frame(opcode, payload);
+        let start = frame.len() - payload.len();
+        frame[1] |= 0x80;
+        for (i, byte) in frame[start..].iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        frame.splice(start..start, mask);
+        frame
+    }
+
+    #[test]
+    fn handshake() {
+        // The example from RFC 6455 section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(accept_key(" dGhlIHNhbXBsZSBub25jZQ==\r"), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn sha1_and_base64() {
+        let hex = |bytes: [u8; 20]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // Two blocks of padding
+        assert_eq!(hex(sha1(&[b'a'; 56])), "c2db330f6083854c99d4b5bfb6e8f29f201be699");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn frame_lengths() {
+        assert_eq!(frame(TEXT, b"Hello"), [0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+
+        let cases: [(usize, &[u8]); 5] = [
+            (125, &[0x81, 125]),
+            (126, &[0x81, 126, 0x00, 126]),
+            (65_535, &[0x81, 126, 0xFF, 0xFF]),
+            (65_536, &[0x81, 127, 0, 0, 0, 0, 0, 0x01, 0x00, 0x00]),
+            (0, &[0x81, 0]),
+        ];
+        for (len, head) in cases {
+            let frame = frame(TEXT, &vec![b'x'; len]);
+            assert_eq!(&frame[..head.len()], head, "payload of {} bytes", len);
+            assert_eq!(frame.len(), head.len() + len);
+        }
+    }
+
+    #[test]
+    fn read_client_frames() {
+        // The masked "Hello" from RFC 6455 section 5.7
+        let hello = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        assert_eq!(read_frame(&mut &hello[..]).unwrap(), (TEXT, b"Hello".to_vec()));
+
+        for len in [125, 126, 65_535, 65_536] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let bytes = masked(PING, &payload, [1, 2, 3, 4]);
+            assert_eq!(read_frame(&mut &bytes[..]).unwrap(), (PING, payload), "payload of {} bytes", len);
+        }
+
+        let large = masked(TEXT, &[0; 65_537], [1, 2, 3, 4]);
+        assert_eq!(read_frame(&mut &large[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let truncated = &hello[..6];
+        assert_eq!(read_frame(&mut &truncated[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,344 @@
+use gamer_monitor_core::bar::BarFormat;
+use gamer_monitor_core::capture::{self, Source};
+use gamer_monitor_core::config::{Config, SamplingConfig};
+use gamer_monitor_core::exporters;
+use gamer_monitor_core::output::Format;
+use gamer_monitor_core::report;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: gamer-monitor [OPTIONS]
+
+Without options the graphical monitor is opened.
+
+Options:
+  --once               Print a single snapshot and exit
+  --watch              Print a snapshot every interval until interrupted
+  --tui                Show the monitor in the terminal (q to quit)
+  --headless           Keep sampling for the exporters without a window or output
+  --bar FORMAT         Stream a status bar module: waybar, i3bar or polybar
+  --agent              Serve readings to monitors on other machines (needs --listen)
+  --format FORMAT      Output format: text (default), json or csv
+  --template TEMPLATE  Bar text, e.g. '{cpu_temp}° {gpu_temp}°' (default: from config)
+  --interval SECONDS   Sampling interval when running continuously (default: from config)
+  --log FILE           Append every sample to a CSV file (starts recording in the window)
+  --exporter ADDRESS   Serve Prometheus metrics on http://ADDRESS/metrics
+  --push ENDPOINT      Push readings to udp://host:port or tcp://host:port
+  --web ADDRESS        Serve a live dashboard on http://ADDRESS/
+  --mqtt BROKER        Publish readings to an MQTT broker at host:port
+  --listen ADDRESS     Address the agent listens on, e.g. 0.0.0.0:7878
+  --token TOKEN        Token the agent requires, or sends with --connect
+  --connect ADDRESS    Also show an agent's readings in the window (repeatable)
+  --capture FILE       Record the raw sensor inputs to FILE, for --replay
+  --replay FILE        Read the sensors from a capture instead of this machine
+  --games              Compare the recorded game sessions and exit
+  --report FORMAT      Print a hardware report for support tickets: markdown, json or html
+  -h, --help           Show this help
+  -V, --version        Show the version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Gui,
+    Once,
+    Watch,
+    Tui,
+    Headless,
+    Bar,
+    Agent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub mode: Mode,
+    pub format: Format,
+    pub bar: BarFormat,
+    // Overrides bar.template from the configuration
+    pub template: Option<String>,
+    // Overrides every sampling rate from the configuration
+    pub interval: Option<u32>,
+    pub log: Option<PathBuf>,
+    // Overrides exporter.prometheus from the configuration
+    pub exporter: Option<String>,
+    // Overrides exporter.push.endpoint from the configuration
+    pub push: Option<String>,
+    // Overrides exporter.web from the configuration
+    pub web: Option<String>,
+    // Overrides exporter.mqtt.broker from the configuration
+    pub mqtt: Option<String>,
+    // Overrides agent.listen from the configuration
+    pub listen: Option<String>,
+    // Overrides agent.token, or the token of every --connect host
+    pub token: Option<String>,
+    // Agents shown next to the configured remotes
+    pub connect: Vec<String>,
+    // Where the raw sensor inputs are recorded to, or replayed from
+    pub capture: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run(Box<Options>),
+    // Print the per-game session summary
+    Games,
+    // Print the hardware report of this machine
+    Report(report::Format),
+    Help,
+    Version,
+}
+
+const EXCLUSIVE: &str = "--once, --watch, --tui, --headless, --bar and --agent cannot be combined";
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut mode = Mode::Gui;
+    let mut format = None;
+    let mut bar = None;
+    let mut template = None;
+    let mut interval = None;
+    let mut log = None;
+    let mut exporter = None;
+    let mut push = None;
+    let mut web = None;
+    let mut mqtt = None;
+    let mut listen = None;
+    let mut token = None;
+    let mut connect = Vec::new();
+    let mut capture = None;
+    let mut replay = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Accept both "--format json" and "--format=json"
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--games" => return Ok(Command::Games),
+            "--report" => {
+                let name = value("--report")?;
+                return report::Format::parse(&name)
+                    .map(Command::Report)
+                    .ok_or_else(|| format!("unknown report format '{}' (expected markdown, json or html)", name));
+            }
+            "--once" | "--watch" | "--tui" | "--headless" | "--agent" => {
+                if inline_value.is_some() {
+                    return Err(format!("{} does not take a value", flag));
+                }
+                let requested = match flag.as_str() {
+                    "--once" => Mode::Once,
+                    "--watch" => Mode::Watch,
+                    "--tui" => Mode::Tui,
+                    "--agent" => Mode::Agent,
+                    _ => Mode::Headless,
+                };
+                if mode != Mode::Gui && mode != requested {
+                    return Err(EXCLUSIVE.to_string());
+                }
+                mode = requested;
+            }
+            "--bar" => {
+                let name = value("--bar")?;
+                if mode != Mode::Gui && mode != Mode::Bar {
+                    return Err(EXCLUSIVE.to_string());
+                }
+                mode = Mode::Bar;
+                bar = Some(
+                    BarFormat::parse(&name)
+                        .ok_or_else(|| format!("unknown bar '{}' (expected waybar, i3bar or polybar)", name))?,
+                );
+            }
+            "--format" => {
+                let name = value("--format")?;
+                format = Some(
+                    Format::parse(&name)
+                        .ok_or_else(|| format!("unknown format '{}' (expected text, json or csv)", name))?,
+                );
+            }
+            "--interval" => {
+                let seconds = value("--interval")?;
+                match seconds.parse::<u32>() {
+                    Ok(seconds) if (1..=3600).contains(&seconds) => interval = Some(seconds),
+                    _ => return Err(format!("invalid interval '{}' (expected 1 to 3600 seconds)", seconds)),
+                }
+            }
+            "--template" => template = Some(value("--template")?),
+            "--log" => log = Some(PathBuf::from(value("--log")?)),
+            "--exporter" => exporter = Some(value("--exporter")?),
+            "--push" => push = Some(value("--push")?),
+            "--web" => web = Some(value("--web")?),
+            "--mqtt" => mqtt = Some(value("--mqtt")?),
+            "--listen" => listen = Some(value("--listen")?),
+            "--token" => token = Some(value("--token")?),
+            "--connect" => connect.push(value("--connect")?),
+            "--capture" => capture = Some(PathBuf::from(value("--capture")?)),
+            "--replay" => replay = Some(PathBuf::from(value("--replay")?)),
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    if !matches!(mode, Mode::Once | Mode::Watch) && format.is_some() {
+        return Err("--format requires --once or --watch".to_string());
+    }
+    if mode != Mode::Bar && template.is_some() {
+        return Err("--template requires --bar".to_string());
+    }
+    if !matches!(mode, Mode::Watch | Mode::Tui | Mode::Headless | Mode::Bar | Mode::Agent) && interval.is_some() {
+        return Err("--interval requires --watch, --tui, --headless, --bar or --agent".to_string());
+    }
+    if mode != Mode::Agent && listen.is_some() {
+        return Err("--listen requires --agent".to_string());
+    }
+    if mode != Mode::Gui && !connect.is_empty() {
+        return Err("--connect only works in the window".to_string());
+    }
+    if mode != Mode::Agent && connect.is_empty() && token.is_some() {
+        return Err("--token requires --agent or --connect".to_string());
+    }
+    if mode == Mode::Once && (exporter.is_some() || push.is_some() || web.is_some() || mqtt.is_some()) {
+        return Err("--exporter, --push, --web and --mqtt cannot be used with --once".to_string());
+    }
+    if capture.is_some() && replay.is_some() {
+        return Err("--capture and --replay cannot be combined".to_string());
+    }
+
+    Ok(Command::Run(Box::new(Options {
+        mode,
+        format: format.unwrap_or(Format::Text),
+        bar: bar.unwrap_or(BarFormat::Waybar),
+        template,
+        interval,
+        log,
+        exporter,
+        push,
+        web,
+        mqtt,
+        listen,
+        token,
+        connect,
+        capture,
+        replay,
+    })))
+}
+
+impl Options {
+    // The configured sampling rates, or --interval for every subsystem
+    pub fn sampling(&self, configured: &SamplingConfig) -> SamplingConfig {
+        match self.interval {
+            Some(seconds) => SamplingConfig {
+                cpu_load: seconds,
+                sensors: seconds,
+                gpu: seconds,
+                memory: seconds,
+            },
+            None => configured.clone(),
+        }
+    }
+
+    // The replay or capture asked for, or this machine
+    pub fn source(&self) -> Result<Box<dyn Source>, String> {
+        capture::open(self.replay.as_deref(), self.capture.as_deref())
+    }
+
+    // The exporters this mode runs, with the addresses given on the command line
+    pub fn exporters(&self, config: &Config) -> Result<exporters::Options, String> {
+        // Checked first, so a missing address fails before anything listens
+        let agent = match self.mode {
+            Mode::Agent => Some(
+                self.listen
+                    .clone()
+                    .or_else(|| config.agent.listen.clone())
+                    .ok_or("--agent needs --listen ADDRESS or agent.listen in the configuration")?,
+            ),
+            _ => None,
+        };
+
+        Ok(exporters::Options {
+            prometheus: self.exporter.clone(),
+            web: self.web.clone(),
+            push: self.push.clone(),
+            mqtt: self.mqtt.clone(),
+            // The window registers on its GApplication's connection instead, and
+            // bars often run one instance per monitor, which would fight over the name
+            dbus_name: !matches!(self.mode, Mode::Gui | Mode::Bar),
+            socket: self.mode != Mode::Bar,
+            agent,
+            token: self.token.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match run(args) {
+            Ok(Command::Run(options)) => *options,
+            other => panic!("{:?} parsed as {:?}", args, other),
+        }
+    }
+
+    #[test]
+    fn modes_and_values() {
+        assert_eq!(options(&[]).mode, Mode::Gui);
+        // The same mode twice is fine
+        assert_eq!(options(&["--tui", "--tui"]).mode, Mode::Tui);
+
+        let watch = options(&["--watch", "--format=csv", "--interval", "5", "--log", "readings.csv"]);
+        assert_eq!((watch.mode, watch.format, watch.interval), (Mode::Watch, Format::Csv, Some(5)));
+        assert_eq!(watch.log, Some(PathBuf::from("readings.csv")));
+
+        let bar = options(&["--bar", "i3bar", "--template", "{cpu_temp}°"]);
+        assert_eq!((bar.mode, bar.bar, bar.template.as_deref()), (Mode::Bar, BarFormat::I3bar, Some("{cpu_temp}°")));
+
+        let window = options(&["--connect", "a:7878", "--connect=b:7878", "--token", "secret"]);
+        assert_eq!(window.connect, ["a:7878", "b:7878"]);
+
+        assert_eq!(run(&["--games", "--bogus"]), Ok(Command::Games));
+        assert_eq!(run(&["--once", "-V"]), Ok(Command::Version));
+        assert_eq!(run(&["--report", "json"]), Ok(Command::Report(report::Format::Json)));
+    }
+
+    #[test]
+    fn errors() {
+        for (args, expected) in [
+            (&["--once", "--tui"][..], EXCLUSIVE),
+            (&["--bar", "waybar", "--agent"], EXCLUSIVE),
+            (&["--headless", "--bar", "waybar"], EXCLUSIVE),
+            (&["--replay", "a.capture", "--capture", "b.capture"], "--capture and --replay cannot be combined"),
+            (&["--format"], "--format requires a value"),
+            (&["--once", "--log"], "--log requires a value"),
+            (&["--frobnicate"], "unknown option '--frobnicate'"),
+            (&["--once=yes"], "--once does not take a value"),
+            (&["-x"], "unknown option '-x'"),
+            (&["--once", "--format", "xml"], "unknown format 'xml' (expected text, json or csv)"),
+            (&["--bar", "lemonbar"], "unknown bar 'lemonbar' (expected waybar, i3bar or polybar)"),
+            (&["--report", "pdf"], "unknown report format 'pdf' (expected markdown, json or html)"),
+            (&["--watch", "--interval", "0"], "invalid interval '0' (expected 1 to 3600 seconds)"),
+            (&["--watch", "--interval", "fast"], "invalid interval 'fast' (expected 1 to 3600 seconds)"),
+            (&["--format", "json"], "--format requires --once or --watch"),
+            (&["--template", "{cpu_temp}"], "--template requires --bar"),
+            (&["--once", "--interval", "2"], "--interval requires --watch, --tui, --headless, --bar or --agent"),
+            (&["--listen", "0.0.0.0:7878"], "--listen requires --agent"),
+            (&["--tui", "--connect", "a:7878"], "--connect only works in the window"),
+            (&["--headless", "--token", "secret"], "--token requires --agent or --connect"),
+            (&["--once", "--web", "127.0.0.1:9956"], "--exporter, --push, --web and --mqtt cannot be used with --once"),
+        ] {
+            assert_eq!(run(args), Err(expected.to_string()), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::style::set_severity;
+use gamer_monitor_core::alerts::Severity;
+use gamer_monitor_core::diagnostics::{self, Check, Finding, Status};
+use gamer_monitor_core::sampler::Snapshot;
+use gamer_monitor_core::scheduler::Publisher;
+use gtk4::prelude::*;
+use gtk4::{Align, Box, Grid, Label, Orientation, ScrolledWindow};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+const FINDING_COLUMNS: [&str; 4] = ["Reading", "Source", "Status", "Details"];
+const CHECK_COLUMNS: [&str; 5] = ["Input", "Status", "Took", "Last read", "Details"];
+
+struct View {
+    // One label per column for each row, created as they are first needed
+    findings: Vec<[Label; 4]>,
+    checks: Vec<[Label; 5]>,
+    findings_grid: Grid,
+    checks_grid: Grid,
+}
+
+// The Diagnostics page: why a reading of this computer is blank and what to
+// do about it, and below every file and program the readings come from,
+// with how the last read of each went
+pub fn create(publisher: &Publisher) -> ScrolledWindow {
+    let page = Box::new(Orientation::Vertical, 10);
+
+    let findings_grid = grid(&FINDING_COLUMNS);
+    page.append(&heading("Readings", "Where each group of readings comes from, and what to do when it is blank."));
+    page.append(&findings_grid);
+
+    let checks_grid = grid(&CHECK_COLUMNS);
+    page.append(&heading("Inputs", "Every file and program the readings come from, and how the last read of each went."));
+    page.append(&checks_grid);
+
+    let view = Rc::new(RefCell::new(View {
+        findings: Vec::new(),
+        checks: Vec::new(),
+        findings_grid,
+        checks_grid,
+    }));
+
+    let redraw = {
+        let publisher = publisher.clone();
+        let page = page.downgrade();
+        Rc::new(move || {
+            // Nobody is looking, the map handler catches up later
+            if !page.upgrade().is_some_and(|page| page.is_mapped()) {
+                return;
+            }
+            if let Some(snapshot) = publisher.latest() {
+                draw(&mut view.borrow_mut(), &snapshot);
+            }
+        })
+    };
+
+    {
+        let redraw = redraw.clone();
+        page.connect_map(move |_| redraw());
+    }
+
+    let page_ref = page.downgrade();
+    publisher.subscribe(move |_| {
+        if page_ref.upgrade().is_none() {
+            return glib::ControlFlow::Break;
+        }
+        redraw();
+        glib::ControlFlow::Continue
+    });
+
+    let scrolled_window = ScrolledWindow::new();
+    scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+    scrolled_window.set_vexpand(true);
+    scrolled_window.set_child(Some(&page));
+    scrolled_window
+}
+
+fn heading(title: &str, description: &str) -> Box {
+    let heading = Box::new(Orientation::Vertical, 2);
+    heading.set_margin_top(6);
+    let title_label = Label::new(None);
+    title_label.set_markup(&format!("<b>{}</b>", title));
+    title_label.set_halign(Align::Start);
+    let description_label = Label::new(Some(description));
+    description_label.add_css_class("dim-label");
+    description_label.set_halign(Align::Start);
+    description_label.set_wrap(true);
+    heading.append(&title_label);
+    heading.append(&description_label);
+    heading
+}
+
+fn grid(columns: &[&str]) -> Grid {
+    let grid = Grid::new();
+    grid.set_row_spacing(4);
+    grid.set_column_spacing(16);
+    grid.set_margin_start(4);
+    grid.set_margin_end(4);
+    for (column, title) in columns.iter().enumerate() {
+        let header = Label::new(Some(title));
+        header.add_css_class("dim-label");
+        header.set_halign(Align::Start);
+        grid.attach(&header, column as i32, 0, 1, 1);
+    }
+    grid
+}
+
+fn draw(view: &mut View, snapshot: &Snapshot) {
+    let findings = diagnostics::explain(&snapshot.data, &snapshot.diagnostics);
+    draw_findings(view, &findings);
+    draw_checks(view, &snapshot.diagnostics);
+}
+
+fn draw_findings(view: &mut View, findings: &[Finding]) {
+    for (index, finding) in findings.iter().enumerate() {
+        if index == view.findings.len() {
+            let row = add_row(&view.findings_grid, index as i32 + 1);
+            view.findings.push(row);
+        }
+        let details = [finding.error.as_deref(), finding.hint.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+        let cells = [
+            finding.reading.to_string(),
+            finding.source.clone(),
+            finding.status.label().to_string(),
+            details,
+        ];
+
+        let labels = &view.findings[index];
+        for (label, text) in labels.iter().zip(cells) {
+            label.set_text(&text);
+            label.set_visible(true);
+        }
+        set_severity(&labels[2], severity(finding.status));
+    }
+
+    // Readings can come and go, e.g. the GPU's before it is identified
+    for labels in view.findings.iter().skip(findings.len()) {
+        for label in labels {
+            label.set_visible(false);
+        }
+    }
+}
+
+fn draw_checks(view: &mut View, checks: &[Check]) {
+    let now = SystemTime::now();
+
+    for (index, check) in checks.iter().enumerate() {
+        if index == view.checks.len() {
+            let row = add_row(&view.checks_grid, index as i32 + 1);
+            view.checks.push(row);
+        }
+        let age = now.duration_since(check.time).unwrap_or_default().as_secs();
+        // A program that worked can still have complained about a chip
+        let details = match &check.error {
+            Some(error) => error.clone(),
+            None => check.stderr.lines().rev().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default().to_string(),
+        };
+        let cells = [
+            check.input.clone(),
+            check.status.label().to_string(),
+            format!("{} ms", check.duration.as_millis()),
+            format!("{} s ago", age),
+            details,
+        ];
+
+        let labels = &view.checks[index];
+        for (label, text) in labels.iter().zip(cells) {
+            label.set_text(&text);
+        }
+        set_severity(&labels[1], severity(check.status));
+        let stderr = check.stderr.trim();
+        labels[4].set_tooltip_text((!stderr.is_empty()).then_some(stderr));
+    }
+}
+
+// A missing program or chip is expected on some machines, e.g. nvidia-smi
+// without an NVIDIA card, or NVMe temperatures without an NVMe drive
+fn severity(status: Status) -> Severity {
+    match status {
+        Status::Ok => Severity::Normal,
+        Status::NotInstalled | Status::NoMatchingChip => Severity::Warm,
+        Status::PermissionDenied | Status::TimedOut | Status::Failed | Status::ParseFailed => Severity::Critical,
+    }
+}
+
+// The last column holds errors and hints, which wrap and can be copied
+fn add_row<const N: usize>(grid: &Grid, row: i32) -> [Label; N] {
+    std::array::from_fn(|column| {
+        let label = Label::new(None);
+        label.set_halign(Align::Start);
+        label.set_valign(Align::Start);
+        label.set_xalign(0.0);
+        if column == N - 1 {
+            label.set_hexpand(true);
+            label.set_wrap(true);
+            label.set_selectable(true);
+        }
+        grid.attach(&label, column as i32, row, 1, 1);
+        label
+    })
+}
@@ -0,0 +1,97 @@
+use crate::cli::{Mode, Options};
+use gamer_monitor_core::bar::Bar;
+use gamer_monitor_core::config::Config;
+use gamer_monitor_core::exporters;
+use gamer_monitor_core::history;
+use gamer_monitor_core::logger::{self, CsvLogger};
+use gamer_monitor_core::output::Output;
+use gamer_monitor_core::sampler::{Sampler, Snapshot};
+use gamer_monitor_core::scheduler::{self, Publisher};
+use gamer_monitor_core::sessions;
+use glib::{ControlFlow, MainLoop};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::rc::Rc;
+
+// Turns a snapshot into the text printed for it: --format output or a bar update
+type Render = Box<dyn FnMut(&Snapshot) -> String>;
+
+// Runs without GTK: no display server is needed, only the glib main loop
+// for --watch, --headless, --bar and --agent, which reuses the same scheduler as the window.
+pub fn run(options: &Options, config: &Config) -> ExitCode {
+    let source = match options.source() {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("gamer-monitor: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let sampler = Rc::new(RefCell::new(Sampler::new(options.sampling(&config.sampling), source)));
+    let publisher = Publisher::new();
+    let main_loop = MainLoop::new(None, false);
+
+    // The history and games are recorded where the monitor keeps running
+    // in the background, but never from a replay
+    let background = matches!(options.mode, Mode::Headless | Mode::Agent) && options.replay.is_none();
+    let history = background.then(|| history::start(&publisher, config)).flatten();
+    if background && config.games.detect {
+        sampler.borrow_mut().track_processes();
+        sessions::start(&publisher, config, history.clone());
+    }
+
+    let logger = RefCell::new(options.log.clone().map(|path| CsvLogger::new(path, config)));
+    publisher.subscribe(move |snapshot| {
+        logger::record(&mut logger.borrow_mut(), snapshot);
+        ControlFlow::Continue
+    });
+
+    if !matches!(options.mode, Mode::Headless | Mode::Agent) {
+        let render: RefCell<Render> = if options.mode == Mode::Bar {
+            let template = options.template.as_deref().unwrap_or(&config.bar.template);
+            let mut bar = match Bar::new(options.bar, template, config) {
+                Ok(bar) => bar,
+                Err(err) => {
+                    eprintln!("gamer-monitor: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            RefCell::new(Box::new(move |snapshot| bar.render(snapshot)))
+        } else {
+            let mut output = Output::new(options.format, config);
+            RefCell::new(Box::new(move |snapshot| output.render(snapshot)))
+        };
+        let main_loop = main_loop.clone();
+
+        publisher.subscribe(move |snapshot| {
+            // A closed pipe (e.g. `| head`, or the bar restarting) ends quietly
+            if print(&(render.borrow_mut())(snapshot)).is_err() {
+                main_loop.quit();
+                return ControlFlow::Break;
+            }
+            ControlFlow::Continue
+        });
+    }
+
+    publisher.publish(sampler.borrow_mut().sample_all());
+    if options.mode == Mode::Once {
+        return ExitCode::SUCCESS;
+    }
+
+    let started = options.exporters(config).and_then(|enabled| exporters::start(&enabled, config, &publisher, history.as_ref()));
+    if let Err(err) = started {
+        eprintln!("gamer-monitor: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    scheduler::start(&sampler, &publisher);
+    main_loop.run();
+
+    ExitCode::SUCCESS
+}
+
+fn print(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
@@ -0,0 +1,387 @@
+use gamer_monitor_core::config::Config;
+use gamer_monitor_core::history::{Series, Store};
+use gamer_monitor_core::sessions;
+use gamer_monitor_core::units::Units;
+use gtk4::prelude::*;
+use gtk4::{Align, Box, Button, DrawingArea, DropDown, Entry, Frame, Label, Orientation, ScrolledWindow, cairo};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Points per line, about one per pixel of a maximized chart
+const MAX_POINTS: i64 = 720;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+const PRESETS: [(&str, i64); 5] = [
+    ("Last hour", 3600),
+    ("Last 6 hours", 6 * 3600),
+    ("Last 24 hours", 86_400),
+    ("Last 7 days", 7 * 86_400),
+    ("Last 30 days", 30 * 86_400),
+];
+
+// Same hues as the web dashboard's charts
+const PALETTE: [(f64, f64, f64); 6] = [
+    (0.21, 0.52, 0.89),
+    (0.90, 0.38, 0.00),
+    (0.18, 0.76, 0.49),
+    (0.75, 0.38, 0.85),
+    (0.96, 0.76, 0.07),
+    (0.75, 0.11, 0.16),
+];
+
+// One chart per unit; memory totals are left to the sections
+const CHARTS: [(&str, &str); 4] = [
+    ("celsius", "Temperatures"),
+    ("percent", "Load"),
+    ("rpm", "Fans"),
+    ("watts", "Power"),
+];
+
+#[derive(Default)]
+struct Range {
+    from: i64,
+    to: i64,
+    series: Vec<Series>,
+    // Game sessions shaded on every chart, markers drawn as lines
+    sessions: Vec<(i64, i64)>,
+    markers: Vec<i64>,
+}
+
+// The History page: a date range from the store, drawn as one chart per
+// unit, with the game sessions, markers and alerts of that range
+pub fn create(store: &Store, config: &Rc<RefCell<Config>>) -> Box {
+    let page = Box::new(Orientation::Vertical, 10);
+
+    let toolbar = Box::new(Orientation::Horizontal, 10);
+    let labels: Vec<&str> = PRESETS.iter().map(|(label, _)| *label).chain(["Custom range"]).collect();
+    let preset = DropDown::from_strings(&labels);
+    preset.set_selected(2);
+    let from_entry = Entry::new();
+    let to_entry = Entry::new();
+    for entry in [&from_entry, &to_entry] {
+        entry.set_placeholder_text(Some("YYYY-MM-DD HH:MM"));
+        entry.set_width_chars(16);
+    }
+    let load_button = Button::with_label("Load");
+    toolbar.append(&preset);
+    toolbar.append(&Label::new(Some("From")));
+    toolbar.append(&from_entry);
+    toolbar.append(&Label::new(Some("to")));
+    toolbar.append(&to_entry);
+    toolbar.append(&load_button);
+    page.append(&toolbar);
+
+    let status = Label::new(None);
+    status.set_halign(Align::Start);
+    status.add_css_class("dim-label");
+    page.append(&status);
+
+    let content = Box::new(Orientation::Vertical, 10);
+    let range = Rc::new(RefCell::new(Range::default()));
+    let mut legends = Vec::new();
+    let mut areas = Vec::new();
+    for (unit, title) in CHARTS {
+        let frame = Frame::new(Some(title));
+        let chart = Box::new(Orientation::Vertical, 4);
+        chart.set_margin_top(6);
+        chart.set_margin_bottom(6);
+        chart.set_margin_start(6);
+        chart.set_margin_end(6);
+
+        let legend = Label::new(None);
+        legend.set_halign(Align::Start);
+        legend.set_wrap(true);
+        chart.append(&legend);
+
+        let area = DrawingArea::new();
+        area.set_content_height(180);
+        area.set_hexpand(true);
+        {
+            let range = range.clone();
+            let config = config.clone();
+            area.set_draw_func(move |_, cr, width, height| {
+                draw_chart(cr, width as f64, height as f64, &range.borrow(), unit, config.borrow().units);
+            });
+        }
+        chart.append(&area);
+        frame.set_child(Some(&chart));
+        content.append(&frame);
+        legends.push((unit, frame, legend));
+        areas.push(area);
+    }
+
+    let events_frame = Frame::new(Some("Sessions, markers and alerts"));
+    let events = Label::new(None);
+    events.set_halign(Align::Start);
+    events.set_margin_top(6);
+    events.set_margin_bottom(6);
+    events.set_margin_start(6);
+    events.set_margin_end(6);
+    events.set_selectable(true);
+    events_frame.set_child(Some(&events));
+    content.append(&events_frame);
+
+    let scrolled_window = ScrolledWindow::new();
+    scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+    scrolled_window.set_vexpand(true);
+    scrolled_window.set_child(Some(&content));
+    page.append(&scrolled_window);
+
+    let load = {
+        let store = store.clone();
+        let from_entry = from_entry.clone();
+        let to_entry = to_entry.clone();
+        Rc::new(move || {
+            let (Some(from), Some(to)) = (parse_time(&from_entry.text()), parse_time(&to_entry.text())) else {
+                status.set_text("Enter both times as YYYY-MM-DD HH:MM");
+                status.set_visible(true);
+                return;
+            };
+            if from >= to {
+                status.set_text("The range must end after it starts");
+                status.set_visible(true);
+                return;
+            }
+
+            let loaded = store.series(from, to, MAX_POINTS).and_then(|series| {
+                Ok((series, store.sessions(from, to)?, store.markers(from, to)?, store.alerts(from, to)?))
+            });
+            let (series, game_sessions, markers, alerts) = match loaded {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    status.set_text(&format!("Cannot read the history: {}", err));
+                    status.set_visible(true);
+                    return;
+                }
+            };
+
+            status.set_text(&match (series.is_empty(), store.first_time()) {
+                (true, Some(first)) => format!("Nothing recorded in this range; the history starts at {}", format_time(first)),
+                (true, None) => "Nothing recorded yet".to_string(),
+                (false, _) => String::new(),
+            });
+            status.set_visible(!status.text().is_empty());
+
+            for (unit, frame, legend) in &legends {
+                let entries: Vec<String> = series
+                    .iter()
+                    .filter(|series| series.unit == *unit)
+                    .enumerate()
+                    .map(|(index, series)| {
+                        let (r, g, b) = PALETTE[index % PALETTE.len()];
+                        format!(
+                            "<span foreground=\"#{:02x}{:02x}{:02x}\">●</span> {}",
+                            (r * 255.0) as u8,
+                            (g * 255.0) as u8,
+                            (b * 255.0) as u8,
+                            glib::markup_escape_text(&series.title)
+                        )
+                    })
+                    .collect();
+                legend.set_markup(&entries.join("   "));
+                frame.set_visible(!entries.is_empty());
+            }
+
+            let mut lines = Vec::new();
+            for session in &game_sessions {
+                lines.push((
+                    session.unix_start as i64,
+                    format!(
+                        "{}  Played {} ({}) for {}",
+                        format_time(session.unix_start as i64),
+                        session.game,
+                        session.launcher,
+                        sessions::format_duration(session.duration())
+                    ),
+                ));
+            }
+            for (time, label) in &markers {
+                lines.push((*time, format!("{}  Marker: {}", format_time(*time), label)));
+            }
+            for alert in &alerts {
+                lines.push((
+                    alert.time,
+                    format!("{}  {} {}: {}", format_time(alert.time), alert.reading, alert.severity, alert.value),
+                ));
+            }
+            lines.sort_by_key(|(time, _)| *time);
+            let text: Vec<String> = lines.into_iter().map(|(_, line)| line).collect();
+            events.set_text(&if text.is_empty() { "None in this range".to_string() } else { text.join("\n") });
+
+            *range.borrow_mut() = Range {
+                from,
+                to,
+                series,
+                sessions: game_sessions
+                    .iter()
+                    .map(|session| (session.unix_start as i64, session.unix_end as i64))
+                    .collect(),
+                markers: markers.iter().map(|(time, _)| *time).collect(),
+            };
+            for area in &areas {
+                area.queue_draw();
+            }
+        })
+    };
+
+    // A preset fills in the range and loads it right away
+    let apply_preset = {
+        let load = load.clone();
+        let from_entry = from_entry.clone();
+        let to_entry = to_entry.clone();
+        move |selected: u32| {
+            let Some((_, span)) = PRESETS.get(selected as usize) else {
+                return;
+            };
+            let now = unix_now();
+            from_entry.set_text(&format_time(now - span));
+            to_entry.set_text(&format_time(now));
+            load();
+        }
+    };
+    apply_preset(preset.selected());
+    preset.connect_selected_notify(move |preset| apply_preset(preset.selected()));
+
+    {
+        let load = load.clone();
+        load_button.connect_clicked(move |_| load());
+    }
+    for entry in [&from_entry, &to_entry] {
+        let load = load.clone();
+        let preset = preset.clone();
+        entry.connect_activate(move |_| {
+            preset.set_selected(PRESETS.len() as u32);
+            load();
+        });
+    }
+
+    page
+}
+
+fn draw_chart(cr: &cairo::Context, width: f64, height: f64, range: &Range, unit: &str, units: Units) {
+    let (left, right, top, bottom) = (48.0, 8.0, 6.0, 20.0);
+    let plot_width = (width - left - right).max(1.0);
+    let plot_height = (height - top - bottom).max(1.0);
+    if range.to <= range.from {
+        return;
+    }
+    let span = (range.to - range.from) as f64;
+    let x = |time: i64| left + (time - range.from) as f64 / span * plot_width;
+
+    // Temperatures follow the configured unit, everything else is as stored
+    let convert = |value: f64| match unit {
+        "celsius" => units.temperature.convert(value as f32) as f64,
+        _ => value,
+    };
+    let lines: Vec<&Series> = range.series.iter().filter(|series| series.unit == unit).collect();
+    let values = lines.iter().flat_map(|series| series.points.iter().map(|&(_, value)| convert(value)));
+    let (mut low, mut high) = values.fold((f64::MAX, f64::MIN), |(low, high), value| (low.min(value), high.max(value)));
+    if unit == "percent" {
+        (low, high) = (0.0, 100.0);
+    } else if low > high {
+        return;
+    } else {
+        let pad = ((high - low) * 0.1).max(1.0);
+        (low, high) = ((low - pad).max(0.0), high + pad);
+    }
+    let y = |value: f64| top + (1.0 - (convert(value) - low) / (high - low)) * plot_height;
+
+    // Game sessions behind everything else
+    cr.set_source_rgba(0.5, 0.5, 0.5, 0.15);
+    for &(start, end) in &range.sessions {
+        let (start, end) = (x(start.max(range.from)), x(end.min(range.to)));
+        cr.rectangle(start, top, (end - start).max(1.0), plot_height);
+        let _ = cr.fill();
+    }
+
+    // Grid with the value and time scales
+    cr.set_font_size(10.0);
+    cr.set_line_width(1.0);
+    for step in 0..=4 {
+        let fraction = step as f64 / 4.0;
+        let level = top + fraction * plot_height;
+        cr.set_source_rgba(0.5, 0.5, 0.5, 0.25);
+        cr.move_to(left, level.round() + 0.5);
+        cr.line_to(left + plot_width, level.round() + 0.5);
+        let _ = cr.stroke();
+
+        cr.set_source_rgba(0.5, 0.5, 0.5, 1.0);
+        cr.move_to(4.0, level + 3.0);
+        let _ = cr.show_text(&format!("{:.0}", high - fraction * (high - low)));
+
+        let time = range.from + (fraction * span) as i64;
+        let label = format_axis_time(time, range.to - range.from);
+        let offset = match step {
+            0 => 0.0,
+            4 => label.len() as f64 * 6.0,
+            _ => label.len() as f64 * 3.0,
+        };
+        cr.move_to(x(time) - offset, height - 5.0);
+        let _ = cr.show_text(&label);
+    }
+
+    // Markers as dashed lines
+    cr.set_source_rgba(0.5, 0.5, 0.5, 0.8);
+    cr.set_dash(&[4.0, 3.0], 0.0);
+    for &time in &range.markers {
+        cr.move_to(x(time).round() + 0.5, top);
+        cr.line_to(x(time).round() + 0.5, top + plot_height);
+        let _ = cr.stroke();
+    }
+    cr.set_dash(&[], 0.0);
+
+    // A gap much longer than the spacing of the points is a time nothing
+    // was recorded, and is left blank instead of bridged
+    let gap = (range.to - range.from) / MAX_POINTS * 3 + 180;
+    cr.set_line_width(1.5);
+    for (index, series) in lines.iter().enumerate() {
+        let (r, g, b) = PALETTE[index % PALETTE.len()];
+        cr.set_source_rgb(r, g, b);
+        let mut previous: Option<i64> = None;
+        for &(time, value) in &series.points {
+            if previous.is_some_and(|previous| time - previous <= gap) {
+                cr.line_to(x(time), y(value));
+            } else {
+                cr.move_to(x(time), y(value));
+            }
+            previous = Some(time);
+        }
+        let _ = cr.stroke();
+    }
+}
+
+// Local time, as typed in the range entries
+fn parse_time(text: &str) -> Option<i64> {
+    let (date, time) = text.trim().split_once(' ')?;
+    let mut date = date.split('-').map(|part| part.parse::<i32>().ok());
+    let mut time = time.trim().split(':').map(|part| part.parse::<i32>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (hour, minute) = (time.next()??, time.next()??);
+    glib::DateTime::from_local(year, month, day, hour, minute, 0.0)
+        .ok()
+        .map(|time| time.to_unix())
+}
+
+fn format_time(unix: i64) -> String {
+    glib::DateTime::from_unix_local(unix)
+        .and_then(|time| time.format(TIME_FORMAT))
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+// Hours only within a day, the date as well across days
+fn format_axis_time(unix: i64, span: i64) -> String {
+    let format = if span <= 86_400 { "%H:%M" } else { "%m-%d %H:%M" };
+    glib::DateTime::from_unix_local(unix)
+        .and_then(|time| time.format(format))
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs() as i64
+}
@@ -1,31 +1,187 @@
-mod sensors;
-
-use glib::timeout_add_seconds_local;
+mod cli;
+mod diagnostics_page;
+mod headless;
+mod history_page;
+mod preferences;
+mod process_page;
+mod style;
+mod tui;
+
+use gamer_monitor_core::{APP_ID, alerts, capture, config, dbus, exporters, history, logger, remote, report, sampler, scheduler, sensors, sessions, timestamp};
+use alerts::{AlertThresholds, Severity};
+use cli::{Command, Mode};
+use config::{Config, RemoteConfig, Section};
 use gtk4::prelude::*;
-use gtk4::{Application, ApplicationWindow, Box, Frame, Grid, Label, Orientation, ProgressBar, ScrolledWindow};
+use gtk4::{
+    Application, ApplicationWindow, Box, Frame, Grid, HeaderBar, Label, MenuButton, Notebook,
+    Orientation, ProgressBar, ScrolledWindow, Stack, StackSwitcher, ToggleButton, gio,
+};
+use logger::CsvLogger;
+use report::Report;
+use sampler::{Sampler, Snapshot};
+use scheduler::Publisher;
 use sensors::SensorData;
+use style::set_severity;
 use std::cell::RefCell;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
-const APP_ID: &str = "com.lunx.GamerMonitor";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const APP_TITLE: &str = "Gamer Monitor";
 
-fn main() {
+// The library reports what happens in the background through `log`; the
+// messages are printed as they are, like the frontends' own
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info && metadata.target().starts_with("gamer_monitor_core")
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn main() -> ExitCode {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+    let args: Vec<String> = std::env::args().collect();
+
+    let options = match cli::parse(args.iter().skip(1).cloned()) {
+        Ok(Command::Run(options)) => *options,
+        Ok(Command::Games) => {
+            return match sessions::report(&load_config().0) {
+                Ok(report) => {
+                    print!("{}", report);
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("gamer-monitor: {}", err);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Ok(Command::Report(format)) => {
+            // Always this machine: the report is what a support ticket asks about
+            let (config, _) = load_config();
+            let mut sampler = Sampler::new(config.sampling.clone(), std::boxed::Box::new(capture::System::default()));
+            let snapshot = sampler.sample_all();
+            let report = Report::gather(&mut capture::System::default(), &snapshot, &config);
+            print!("{}", report.render(format, &config));
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("gamer-monitor {}", VERSION);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("gamer-monitor: {}\n\n{}", err, cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let (config, broken) = load_config();
+
+    match options.mode {
+        Mode::Gui => {}
+        Mode::Tui => return tui::run(&options, &config),
+        Mode::Once | Mode::Watch | Mode::Headless | Mode::Bar | Mode::Agent => return headless::run(&options, &config),
+    }
+
+    // Before GTK starts, so a bad capture fails like a bad option
+    let source = match options.source() {
+        Ok(source) => RefCell::new(Some(source)),
+        Err(err) => {
+            eprintln!("gamer-monitor: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let config = Rc::new(RefCell::new(config));
+
     let app = Application::builder()
         .application_id(APP_ID)
         .build();
 
-    app.connect_activate(build_ui);
-    app.run();
+    app.connect_activate(move |app| {
+        // Launching again activates this instance, which keeps its window
+        match source.borrow_mut().take() {
+            Some(source) => build_ui(app, &config, &options, source, broken.clone()),
+            None => {
+                if let Some(window) = app.active_window() {
+                    window.present();
+                }
+            }
+        }
+    });
+
+    // Our own flags were handled above, don't let GTK reject them
+    app.run_with_args(&args[..1]).into()
+}
+
+// The configuration, or the defaults and why the file could not be used.
+// The window never saves over such a file without asking first.
+fn load_config() -> (Config, Option<String>) {
+    match Config::load() {
+        Ok(config) => (config, None),
+        Err(err) => {
+            eprintln!("Invalid configuration, using defaults: {}", err);
+            (Config::default(), Some(err.to_string()))
+        }
+    }
 }
 
-fn build_ui(app: &Application) {
+fn build_ui(
+    app: &Application,
+    config: &Rc<RefCell<Config>>,
+    options: &cli::Options,
+    source: std::boxed::Box<dyn capture::Source>,
+    broken: Option<String>,
+) {
+
+    // Create the sampler and take a first full reading. Only the window
+    // has a Processes page, so only the window reads every process.
+    let sampler = Rc::new(RefCell::new(Sampler::new(config.borrow().sampling.clone(), source)));
+    sampler.borrow_mut().track_processes();
+    let publisher = Publisher::new();
+    // A replay is someone else's machine, not history or games played here
+    let replaying = options.replay.is_some();
+    let history = (!replaying).then(|| history::start(&publisher, &config.borrow())).flatten();
+    let games = (config.borrow().games.detect && !replaying)
+        .then(|| sessions::start(&publisher, &config.borrow(), history.clone()));
+    publisher.publish(sampler.borrow_mut().sample_all());
+
+    // Exporters keep running while the window is open
+    let started = options.exporters(&config.borrow()).and_then(|enabled| exporters::start(&enabled, &config.borrow(), &publisher, history.as_ref()));
+    if let Err(err) = started {
+        eprintln!("{}", err);
+    }
 
-    // Create sensor data
-    let sensor_data = Rc::new(RefCell::new(SensorData::new()));
-    sensor_data.borrow_mut().identify_hardware();
-    sensor_data.borrow_mut().update();
+    // GApplication already owns the bus name, so only the object is added
+    if config.borrow().exporter.dbus
+        && let Some(connection) = app.dbus_connection()
+        && let Err(err) = dbus::register(&connection, &publisher, &config.borrow())
+    {
+        eprintln!("Cannot export readings on D-Bus: {}", err);
+    }
+
+    // Severity CSS classes and theme
+    style::load_css();
+    style::apply_theme(config.borrow().appearance.theme);
 
     let app_title = APP_TITLE.to_string() + " v" + VERSION;
 
@@ -33,10 +189,30 @@ fn build_ui(app: &Application) {
     let window = ApplicationWindow::builder()
         .application(app)
         .title(app_title)
-        .default_width(600)
-        .default_height(700)
+        .default_width(config.borrow().window.width)
+        .default_height(config.borrow().window.height)
         .build();
 
+    // Header bar with the application menu
+    let menu = gio::Menu::new();
+    menu.append(Some("Generate Report…"), Some("app.report"));
+    menu.append(Some("Preferences"), Some("app.preferences"));
+
+    let menu_button = MenuButton::new();
+    menu_button.set_icon_name("open-menu-symbolic");
+    menu_button.set_menu_model(Some(&menu));
+
+    // The sensors and the Processes page, switched from the header bar
+    let stack = Stack::new();
+    let switcher = StackSwitcher::new();
+    switcher.set_stack(Some(&stack));
+
+    let header_bar = HeaderBar::new();
+    header_bar.set_title_widget(Some(&switcher));
+    header_bar.pack_end(&menu_button);
+    header_bar.pack_end(&create_record_button(config, &publisher, options.log.clone()));
+    window.set_titlebar(Some(&header_bar));
+
     // Main container
     let main_box = Box::new(Orientation::Vertical, 10);
     main_box.set_margin_top(10);
@@ -49,43 +225,421 @@ fn build_ui(app: &Application) {
     title.add_css_class("title-1");
     main_box.append(&title);
 
-    // CPU Section
-    let cpu_frame = create_cpu_section(&sensor_data);
-    main_box.append(&cpu_frame);
-
-    // GPU Section
-    let gpu_frame = create_gpu_section(&sensor_data);
-    main_box.append(&gpu_frame);
+    if let Some(path) = &options.replay {
+        let replay = Label::new(Some(&format!("Replaying {}", path.display())));
+        replay.add_css_class("dim-label");
+        main_box.append(&replay);
+    }
 
-    // Storage Section
-    let storage_frame = create_storage_section(&sensor_data);
-    main_box.append(&storage_frame);
+    // The game being played, while one is
+    if let Some(games) = games {
+        main_box.append(&create_game_label(&games, &publisher));
+    }
 
-    // RAM Section
-    let ram_frame = create_ram_section(&sensor_data);
-    main_box.append(&ram_frame);
+    // Sections, in the order set in the configuration
+    let sections_box = Box::new(Orientation::Vertical, 10);
+    populate_sections(&sections_box, &publisher, &Rc::new(config.borrow().clone()));
+
+    // Every host's sections, rebuilt together when the preferences change
+    let mut hosts = vec![(sections_box.clone(), publisher.clone())];
+
+    // Agents get a tab each, next to this computer's
+    let mut remotes = config.borrow().remotes.clone();
+    remotes.extend(options.connect.iter().map(|address| RemoteConfig {
+        name: None,
+        address: address.clone(),
+        token: options.token.clone(),
+    }));
+    if remotes.is_empty() {
+        main_box.append(&sections_box);
+    } else {
+        let notebook = Notebook::new();
+        notebook.set_scrollable(true);
+        notebook.append_page(&sections_box, Some(&Label::new(Some("This computer"))));
+        for remote in &remotes {
+            let (page, host) = create_remote_page(remote, config);
+            notebook.append_page(&page, Some(&Label::new(Some(remote.title()))));
+            hosts.push(host);
+        }
+        main_box.append(&notebook);
+    }
 
     let scrolled_window = ScrolledWindow::new();
     scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
     scrolled_window.set_child(Some(&main_box));
-    window.set_child(Some(&scrolled_window));
+    stack.add_titled(&scrolled_window, Some("sensors"), "Sensors");
+
+    let processes = process_page::create(&publisher, config);
+    processes.set_margin_top(10);
+    processes.set_margin_bottom(10);
+    processes.set_margin_start(10);
+    processes.set_margin_end(10);
+    stack.add_titled(&processes, Some("processes"), "Processes");
+
+    if let Some(history) = &history {
+        let page = history_page::create(history, config);
+        page.set_margin_top(10);
+        page.set_margin_bottom(10);
+        page.set_margin_start(10);
+        page.set_margin_end(10);
+        stack.add_titled(&page, Some("history"), "History");
+    }
 
-    let sensor_data_clone = sensor_data.clone();
+    let diagnostics = diagnostics_page::create(&publisher);
+    diagnostics.set_margin_top(10);
+    diagnostics.set_margin_bottom(10);
+    diagnostics.set_margin_start(10);
+    diagnostics.set_margin_end(10);
+    stack.add_titled(&diagnostics, Some("diagnostics"), "Diagnostics");
+    window.set_child(Some(&stack));
 
-    sensor_data_clone.borrow_mut().identify_hardware();
+    // A single tick samples whatever is due and publishes the snapshot
+    scheduler::start(&sampler, &publisher);
 
-    // Update every 5 seconds
-    timeout_add_seconds_local(5, move || {
-        sensor_data_clone.borrow_mut().update();
-        glib::ControlFlow::Continue
+    // Why the configuration file could not be loaded, until it is replaced
+    let broken = Rc::new(RefCell::new(broken));
+
+    // Settings changed in the preferences window are applied live
+    let apply: preferences::ApplyFn = {
+        let config = config.clone();
+        let sampler = sampler.clone();
+
+        Rc::new(move |mut new_config: Config| {
+            // The window size is tracked separately on close
+            new_config.window = config.borrow().window.clone();
+            let old_config = config.replace(new_config.clone());
+
+            if old_config.sampling != new_config.sampling {
+                sampler.borrow_mut().set_rates(new_config.sampling.clone());
+            }
+
+            if old_config.appearance.theme != new_config.appearance.theme {
+                style::apply_theme(new_config.appearance.theme);
+            }
+
+            // Rebuilding drops every section's widgets, so only when what
+            // they show changed, not on each keystroke in another page
+            if old_config.sections != new_config.sections
+                || old_config.units != new_config.units
+                || old_config.fans != new_config.fans
+                || old_config.alerts != new_config.alerts
+            {
+                let shared_config = Rc::new(new_config);
+                for (sections_box, publisher) in &hosts {
+                    populate_sections(sections_box, publisher, &shared_config);
+                }
+            }
+        })
+    };
+
+    // Saved once when the preferences window closes, rather than on every change
+    let save: preferences::SaveFn = {
+        let config = config.clone();
+        let broken = broken.clone();
+
+        Rc::new(move || {
+            if broken.borrow().is_none()
+                && let Err(err) = config.borrow().save()
+            {
+                eprintln!("Could not save configuration: {}", err);
+            }
+        })
+    };
+
+    // Asked for in the preferences window when the file could not be loaded
+    let replace: preferences::ReplaceFn = {
+        let config = config.clone();
+        let broken = broken.clone();
+
+        Rc::new(move || {
+            config.borrow().save().map_err(|err| err.to_string())?;
+            *broken.borrow_mut() = None;
+            Ok(())
+        })
+    };
+
+    let preferences_action = gio::SimpleAction::new("preferences", None);
+    let window_ref = window.downgrade();
+    let config_clone = config.clone();
+    let broken_clone = broken.clone();
+    preferences_action.connect_activate(move |_, _| {
+        if let Some(window) = window_ref.upgrade() {
+            let broken = broken_clone.borrow().clone().map(|reason| (reason, replace.clone()));
+            preferences::show(&window, &config_clone.borrow(), apply.clone(), save.clone(), broken);
+        }
+    });
+    app.add_action(&preferences_action);
+    app.set_accels_for_action("app.preferences", &["<Control>comma"]);
+
+    let report_action = gio::SimpleAction::new("report", None);
+    // A replay is someone else's machine, and the report would describe this one
+    report_action.set_enabled(!replaying);
+    let window_ref = window.downgrade();
+    let config_clone = config.clone();
+    let publisher_clone = publisher.clone();
+    report_action.connect_activate(move |_, _| {
+        if let Some(window) = window_ref.upgrade() {
+            save_report(&window, &config_clone, &publisher_clone);
+        }
+    });
+    app.add_action(&report_action);
+
+    // Remember the window size for the next start, unless that would save
+    // the defaults over a file that could not be loaded
+    let config_clone = config.clone();
+    window.connect_close_request(move |window| {
+        let (width, height) = window.default_size();
+        let mut config = config_clone.borrow_mut();
+        if width != config.window.width || height != config.window.height {
+            config.window.width = width;
+            config.window.height = height;
+            if broken.borrow().is_none()
+                && config.validate().is_ok()
+                && let Err(err) = config.save()
+            {
+                eprintln!("Could not save configuration: {}", err);
+            }
+        }
+        glib::Propagation::Proceed
     });
 
     window.present();
 }
 
-fn create_cpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
+// Asks where to save the hardware report, in the format of the file's
+// extension: .json, .html, or Markdown for anything else
+fn save_report(window: &ApplicationWindow, config: &Rc<RefCell<Config>>, publisher: &Publisher) {
+    let dialog = gtk4::FileChooserNative::new(
+        Some("Generate Report"),
+        Some(window),
+        gtk4::FileChooserAction::Save,
+        Some("_Save"),
+        Some("_Cancel"),
+    );
+    dialog.set_current_name(&format!("gamer-monitor-report-{}.md", timestamp::compact(SystemTime::now())));
+
+    // A native dialog is not a widget, so nothing else keeps it alive while it is open
+    let open = Rc::new(RefCell::new(Some(dialog.clone())));
+    let window = window.downgrade();
+    let config = config.clone();
+    let publisher = publisher.clone();
+    dialog.connect_response(move |dialog, response| {
+        open.borrow_mut().take();
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        let (Some(path), Some(snapshot)) = (dialog.file().and_then(|file| file.path()), publisher.latest()) else {
+            return;
+        };
+
+        // Gathering runs lspci, glxinfo, nvidia-smi and udevadm, each of which
+        // may take until its timeout, so it stays off the main loop
+        let config = config.borrow().clone();
+        let task = gtk4::gio::spawn_blocking(move || {
+            let report = Report::gather(&mut capture::System::default(), &snapshot, &config);
+            let text = report.render(report::Format::from_path(&path), &config);
+            std::fs::write(&path, text).map_err(|err| format!("Cannot write {}: {}", path.display(), err))
+        });
+
+        let window = window.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let Ok(Err(message)) = task.await else {
+                return;
+            };
+            eprintln!("{}", message);
+            let alert = gtk4::MessageDialog::builder()
+                .modal(true)
+                .message_type(gtk4::MessageType::Error)
+                .buttons(gtk4::ButtonsType::Close)
+                .text("The report was not saved")
+                .secondary_text(message)
+                .build();
+            alert.set_transient_for(window.upgrade().as_ref());
+            alert.connect_response(|alert, _| alert.destroy());
+            alert.present();
+        });
+    });
+    dialog.show();
+}
 
-    let frame = Frame::new(Some("CPU"));
+// Toggles CSV logging of every published snapshot. --log starts recording
+// right away; otherwise the path comes from the [logging] configuration.
+fn create_record_button(
+    config: &Rc<RefCell<Config>>,
+    publisher: &Publisher,
+    log: Option<PathBuf>,
+) -> ToggleButton {
+    let record_button = ToggleButton::new();
+    record_button.set_icon_name("media-record-symbolic");
+    record_button.set_tooltip_text(Some("Record readings to CSV"));
+
+    let recorder: Rc<RefCell<Option<CsvLogger>>> = Rc::new(RefCell::new(None));
+    let start_now = log.is_some();
+
+    {
+        let config = config.clone();
+        let publisher = publisher.clone();
+        let recorder = recorder.clone();
+
+        record_button.connect_toggled(move |button| {
+            if !button.is_active() {
+                recorder.replace(None);
+                button.set_tooltip_text(Some("Record readings to CSV"));
+                return;
+            }
+
+            let config = config.borrow();
+            let Some(path) = log.clone().or_else(|| config.logging.path()) else {
+                eprintln!("Cannot record: neither $XDG_DATA_HOME nor $HOME is set");
+                button.set_active(false);
+                return;
+            };
+
+            button.set_tooltip_text(Some(&format!("Recording to {}", path.display())));
+            recorder.replace(Some(CsvLogger::new(path, &config)));
+
+            // Start the file with the current readings instead of waiting a tick
+            let recording = match publisher.latest() {
+                Some(snapshot) => logger::record(&mut recorder.borrow_mut(), &snapshot),
+                None => true,
+            };
+            if !recording {
+                button.set_active(false);
+            }
+        });
+    }
+
+    let button_ref = record_button.downgrade();
+    publisher.subscribe(move |snapshot| {
+        let Some(button) = button_ref.upgrade() else {
+            return glib::ControlFlow::Break;
+        };
+
+        if !logger::record(&mut recorder.borrow_mut(), snapshot) {
+            button.set_active(false);
+        }
+        glib::ControlFlow::Continue
+    });
+
+    record_button.set_active(start_now);
+    record_button
+}
+
+fn create_game_label(games: &Rc<RefCell<sessions::Tracker>>, publisher: &Publisher) -> Label {
+    let label = Label::new(None);
+    label.add_css_class("dim-label");
+
+    let update = {
+        let games = games.clone();
+        let label = label.downgrade();
+        move || {
+            let Some(label) = label.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            match games.borrow().current() {
+                Some((game, started)) => {
+                    let elapsed = SystemTime::now().duration_since(started).unwrap_or_default();
+                    label.set_text(&format!(
+                        "Playing {} ({}) for {}",
+                        game.name,
+                        game.launcher.name(),
+                        sessions::format_duration(elapsed)
+                    ));
+                    label.set_visible(true);
+                }
+                None => label.set_visible(false),
+            }
+            glib::ControlFlow::Continue
+        }
+    };
+    update();
+    publisher.subscribe(move |_| update());
+    label
+}
+
+// A tab following one agent. Its snapshots go through a publisher of their
+// own, numbered here so an agent restarting from sequence 1 is not ignored.
+fn create_remote_page(remote: &RemoteConfig, config: &Rc<RefCell<Config>>) -> (Box, (Box, Publisher)) {
+    let page = Box::new(Orientation::Vertical, 10);
+
+    let status = Label::new(Some(&format!("Connecting to {}…", remote.address)));
+    status.set_halign(gtk4::Align::Start);
+    status.add_css_class("dim-label");
+    page.append(&status);
+
+    let sections_box = Box::new(Orientation::Vertical, 10);
+    page.append(&sections_box);
+
+    let publisher = Publisher::new();
+    let events = remote::watch(remote.address.clone(), remote.token.clone());
+    let address = remote.address.clone();
+    let sequence = RefCell::new(0);
+    {
+        let sections_box = sections_box.clone();
+        let publisher = publisher.clone();
+        let config = config.clone();
+
+        glib::timeout_add_local(Duration::from_millis(250), move || {
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    remote::Event::Connected => status.set_text(&format!("Connected to {}", address)),
+                    remote::Event::Snapshot { timestamp, data } => {
+                        *sequence.borrow_mut() += 1;
+                        let first = publisher.latest().is_none();
+                        publisher.publish(Snapshot {
+                            sequence: *sequence.borrow(),
+                            timestamp,
+                            data: *data,
+                            processes: Default::default(),
+                            diagnostics: Default::default(),
+                        });
+                        if first {
+                            populate_sections(&sections_box, &publisher, &Rc::new(config.borrow().clone()));
+                        }
+                    }
+                    remote::Event::Disconnected(reason) => {
+                        status.set_text(&format!("Cannot reach {}: {} (retrying)", address, reason));
+                    }
+                }
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    (page, (sections_box, publisher))
+}
+
+fn populate_sections(sections_box: &Box, publisher: &Publisher, config: &Rc<Config>) {
+    while let Some(child) = sections_box.first_child() {
+        sections_box.remove(&child);
+    }
+
+    let Some(snapshot) = publisher.latest() else {
+        return;
+    };
+    let data = &snapshot.data;
+
+    for section in &config.sections {
+        let frame = match section {
+            Section::Cpu => create_cpu_section(data, publisher, config),
+            Section::Gpu => create_gpu_section(data, publisher, config),
+            Section::Storage => create_storage_section(data, publisher, config),
+            Section::Ram => create_ram_section(data, publisher, config),
+            Section::Fans => create_fan_section(data, publisher, config),
+        };
+        sections_box.append(&frame);
+    }
+}
+
+// True once the preferences rebuilt the sections and dropped this frame,
+// which unsubscribes the section from the publisher
+fn section_removed(frame: &glib::WeakRef<Frame>) -> bool {
+    frame.upgrade().is_none_or(|frame| frame.parent().is_none())
+}
+
+fn create_cpu_section(data: &SensorData, publisher: &Publisher, config: &Rc<Config>) -> Frame {
+
+    let frame = Frame::new(Some(Section::Cpu.title()));
     let grid = Grid::new();
     grid.set_margin_top(10);
     grid.set_margin_bottom(10);
@@ -98,18 +652,20 @@ fn create_cpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     let cpu_name_label = Label::new(Some("Model:"));
     cpu_name_label.set_halign(gtk4::Align::Start);
 
-    let cpu_name_value = Label::new(Some(&sensor_data.borrow().cpu_name));
+    let cpu_name_value = Label::new(Some(&data.cpu_name));
     cpu_name_value.set_halign(gtk4::Align::Start);
     cpu_name_value.add_css_class("dim-label");
     grid.attach(&cpu_name_label, 0, 0, 1, 1);
     grid.attach(&cpu_name_value, 1, 0, 2, 1);
 
     // CPU Temperature
-    let cpu_temp_label = Label::new(Some("Temperature (°C):"));
+    let units = config.units;
+    let cpu_temp_label = Label::new(Some(&format!("Temperature ({}):", units.temperature_symbol())));
     cpu_temp_label.set_halign(gtk4::Align::Start);
     
-    let cpu_temp_value = Label::new(Some(&sensor_data.borrow().cpu_temp));
+    let cpu_temp_value = Label::new(Some(&units.temperature(&data.cpu_temp)));
     cpu_temp_value.set_halign(gtk4::Align::Start);
+    set_severity(&cpu_temp_value, cpu_temp_severity(data, &config.alerts));
     grid.attach(&cpu_temp_label, 0, 1, 1, 1);
     grid.attach(&cpu_temp_value, 1, 1, 1, 1);
 
@@ -119,21 +675,29 @@ fn create_cpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
 
     let cpu_progress = ProgressBar::new();
     cpu_progress.set_hexpand(true);
-    cpu_progress.set_fraction(sensor_data.borrow().cpu_usage as f64 / 100.0);
+    cpu_progress.set_fraction(data.cpu_usage as f64 / 100.0);
     cpu_progress.set_show_text(true);
-    cpu_progress.set_text(Some(&format!("{:.1}%", sensor_data.borrow().cpu_usage)));
+    cpu_progress.set_text(Some(&format!("{:.1}%", data.cpu_usage)));
+    set_severity(&cpu_progress, config.alerts.cpu_load.classify(data.cpu_usage));
     grid.attach(&cpu_usage_label, 0, 2, 1, 1);
     grid.attach(&cpu_progress, 1, 2, 2, 1);
 
-    // Update closure
-    let sensor_data_clone = sensor_data.clone();
+    // Redraw whenever a new snapshot is published
+    let config = config.clone();
+    let frame_ref = frame.downgrade();
     
-    timeout_add_seconds_local(2, move || {
-        let data = sensor_data_clone.borrow();
+    publisher.subscribe(move |snapshot| {
+        if section_removed(&frame_ref) {
+            return glib::ControlFlow::Break;
+        }
+
+        let data = &snapshot.data;
         cpu_name_value.set_text(&data.cpu_name);
-        cpu_temp_value.set_text(&data.cpu_temp);
+        cpu_temp_value.set_text(&units.temperature(&data.cpu_temp));
+        set_severity(&cpu_temp_value, cpu_temp_severity(data, &config.alerts));
         cpu_progress.set_fraction(data.cpu_usage as f64 / 100.0);
         cpu_progress.set_text(Some(&format!("{:.1}%", data.cpu_usage)));
+        set_severity(&cpu_progress, config.alerts.cpu_load.classify(data.cpu_usage));
         glib::ControlFlow::Continue
     });
 
@@ -141,9 +705,9 @@ fn create_cpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     frame
 }
 
-fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
+fn create_gpu_section(data: &SensorData, publisher: &Publisher, config: &Rc<Config>) -> Frame {
 
-    let frame = Frame::new(Some("GPU (graphics card)"));
+    let frame = Frame::new(Some(Section::Gpu.title()));
     let grid = Grid::new();
     grid.set_margin_top(10);
     grid.set_margin_bottom(10);
@@ -157,7 +721,7 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     // GPU Name
     let gpu_name_label = Label::new(Some("Model:"));
     gpu_name_label.set_halign(gtk4::Align::Start);
-    let gpu_name_value = Label::new(Some(&sensor_data.borrow().gpu_name));
+    let gpu_name_value = Label::new(Some(&data.gpu_name));
     gpu_name_value.set_halign(gtk4::Align::Start);
     gpu_name_value.add_css_class("dim-label");
     grid.attach(&gpu_name_label, 0, row, 1, 1);
@@ -165,33 +729,41 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     row += 1;
 
     // Check GPU vendor
-    let is_radeon = sensor_data.borrow().gpu_name.contains("Radeon");
-    let is_nvidia = sensor_data.borrow().gpu_name.to_lowercase().contains("nvidia");
-
-    let hotspot_value = Label::new(Some(&sensor_data.borrow().gpu_hotspot));
-    let edge_value = Label::new(Some(&sensor_data.borrow().gpu_edge));
-    let memory_value = Label::new(Some(&sensor_data.borrow().gpu_memory));
-    let fan_value = Label::new(Some(&sensor_data.borrow().gpu_fan));
-    let power_value = Label::new(Some(&sensor_data.borrow().gpu_power));
-    let vram_used = sensor_data.borrow().gpu_vram_used.clone();
-    let vram_total = sensor_data.borrow().gpu_vram_total.clone();
+    let is_radeon = data.gpu_name.contains("Radeon");
+    let is_nvidia = data.gpu_name.to_lowercase().contains("nvidia");
+
+    let units = config.units;
+    let hotspot_value = Label::new(Some(&units.temperature(&data.gpu_hotspot)));
+    let edge_value = Label::new(Some(&units.temperature(&data.gpu_edge)));
+    let memory_value = Label::new(Some(&units.temperature(&data.gpu_memory)));
+    let fan_value = Label::new(Some(&data.gpu_fan));
+    let power_value = Label::new(Some(&units.power(&data.gpu_power)));
+    let vram_used = data.gpu_vram_used.clone();
+    let vram_total = data.gpu_vram_total.clone();
     let vram_fraction = vram_used.parse::<f64>().unwrap_or(0.0)
         / vram_total.parse::<f64>().unwrap_or(1.0).max(1.0);
     let vram_progress = ProgressBar::new();
     vram_progress.set_hexpand(true);
     vram_progress.set_fraction(vram_fraction);
     vram_progress.set_show_text(true);
-    vram_progress.set_text(Some(&format!("{} / {} MiB", vram_used, vram_total)));
+    vram_progress.set_text(Some(&units.vram(&vram_used, &vram_total)));
 
     let gpu_util_progress = ProgressBar::new();
     gpu_util_progress.set_hexpand(true);
-    gpu_util_progress.set_fraction(sensor_data.borrow().gpu_utilization as f64 / 100.0);
+    gpu_util_progress.set_fraction(data.gpu_utilization as f64 / 100.0);
     gpu_util_progress.set_show_text(true);
-    gpu_util_progress.set_text(Some(&format!("{:.0}%", sensor_data.borrow().gpu_utilization)));
+    gpu_util_progress.set_text(Some(&format!("{:.0}%", data.gpu_utilization)));
+
+    apply_gpu_severity(
+        data,
+        &config.alerts,
+        [&hotspot_value, &edge_value, &memory_value, &fan_value],
+        [&vram_progress, &gpu_util_progress],
+    );
 
     if is_radeon {
         // Hotspot
-        let hotspot_label = Label::new(Some("Hotspot (°C):"));
+        let hotspot_label = Label::new(Some(&format!("Hotspot ({}):", units.temperature_symbol())));
         hotspot_label.set_halign(gtk4::Align::Start);
         hotspot_value.set_halign(gtk4::Align::Start);
         grid.attach(&hotspot_label, 0, row, 1, 1);
@@ -199,7 +771,7 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
         row += 1;
 
         // Edge
-        let edge_label = Label::new(Some("Edge (°C):"));
+        let edge_label = Label::new(Some(&format!("Edge ({}):", units.temperature_symbol())));
         edge_label.set_halign(gtk4::Align::Start);
         edge_value.set_halign(gtk4::Align::Start);
         grid.attach(&edge_label, 0, row, 1, 1);
@@ -207,7 +779,7 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
         row += 1;
 
         // Memory
-        let memory_label = Label::new(Some("Memory (°C):"));
+        let memory_label = Label::new(Some(&format!("Memory ({}):", units.temperature_symbol())));
         memory_label.set_halign(gtk4::Align::Start);
         memory_value.set_halign(gtk4::Align::Start);
         grid.attach(&memory_label, 0, row, 1, 1);
@@ -222,7 +794,7 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
         grid.attach(&fan_value, 1, row, 1, 1);
     } else if is_nvidia {
         // Temperature
-        let temp_label = Label::new(Some("Temperature (°C):"));
+        let temp_label = Label::new(Some(&format!("Temperature ({}):", units.temperature_symbol())));
         temp_label.set_halign(gtk4::Align::Start);
         edge_value.set_halign(gtk4::Align::Start);
         grid.attach(&temp_label, 0, row, 1, 1);
@@ -259,30 +831,41 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
         grid.attach(&gpu_util_progress, 1, row, 2, 1);
     } else {
         // Fallback for Intel/unknown
-        let temp_label = Label::new(Some("Temperature (°C):"));
+        let temp_label = Label::new(Some(&format!("Temperature ({}):", units.temperature_symbol())));
         temp_label.set_halign(gtk4::Align::Start);
         edge_value.set_halign(gtk4::Align::Start);
         grid.attach(&temp_label, 0, row, 1, 1);
         grid.attach(&edge_value, 1, row, 1, 1);
     }
 
-    // Update closure
-    let sensor_data_clone = sensor_data.clone();
+    // Redraw whenever a new snapshot is published
+    let config = config.clone();
+    let frame_ref = frame.downgrade();
+
+    publisher.subscribe(move |snapshot| {
+        if section_removed(&frame_ref) {
+            return glib::ControlFlow::Break;
+        }
 
-    timeout_add_seconds_local(2, move || {
-        let data = sensor_data_clone.borrow();
+        let data = &snapshot.data;
         gpu_name_value.set_text(&data.gpu_name);
-        hotspot_value.set_text(&data.gpu_hotspot);
-        edge_value.set_text(&data.gpu_edge);
-        memory_value.set_text(&data.gpu_memory);
+        hotspot_value.set_text(&units.temperature(&data.gpu_hotspot));
+        edge_value.set_text(&units.temperature(&data.gpu_edge));
+        memory_value.set_text(&units.temperature(&data.gpu_memory));
         fan_value.set_text(&data.gpu_fan);
-        power_value.set_text(&data.gpu_power);
+        power_value.set_text(&units.power(&data.gpu_power));
         let used = data.gpu_vram_used.parse::<f64>().unwrap_or(0.0);
         let total = data.gpu_vram_total.parse::<f64>().unwrap_or(1.0).max(1.0);
         vram_progress.set_fraction(used / total);
-        vram_progress.set_text(Some(&format!("{} / {} MiB", data.gpu_vram_used, data.gpu_vram_total)));
+        vram_progress.set_text(Some(&units.vram(&data.gpu_vram_used, &data.gpu_vram_total)));
         gpu_util_progress.set_fraction(data.gpu_utilization as f64 / 100.0);
         gpu_util_progress.set_text(Some(&format!("{:.0}%", data.gpu_utilization)));
+        apply_gpu_severity(
+            data,
+            &config.alerts,
+            [&hotspot_value, &edge_value, &memory_value, &fan_value],
+            [&vram_progress, &gpu_util_progress],
+        );
         glib::ControlFlow::Continue
     });
 
@@ -290,8 +873,8 @@ fn create_gpu_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     frame
 }
 
-fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
-    let frame = Frame::new(Some("Storage (NVMe)"));
+fn create_storage_section(data: &SensorData, publisher: &Publisher, config: &Rc<Config>) -> Frame {
+    let frame = Frame::new(Some(Section::Storage.title()));
     let storage_box = Box::new(Orientation::Vertical, 5);
     storage_box.set_margin_top(10);
     storage_box.set_margin_bottom(10);
@@ -299,7 +882,8 @@ fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     storage_box.set_margin_end(10);
 
     // Create initial labels based on detected NVMe drives
-    let nvme_count = sensor_data.borrow().nvme_temps.len();
+    let units = config.units;
+    let nvme_count = data.nvme_temps.len();
     let mut initial_labels: Vec<Label> = Vec::new();
 
     if nvme_count == 0 {
@@ -309,9 +893,10 @@ fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
         initial_labels.push(label);
     } else {
         for i in 0..nvme_count {
-            let temp = &sensor_data.borrow().nvme_temps[i];
-            let label = Label::new(Some(&format!("NVMe {}: {} °C", i + 1, temp)));
+            let temp = &data.nvme_temps[i];
+            let label = Label::new(Some(&format!("NVMe {}: {} {}", i + 1, units.temperature(temp), units.temperature_symbol())));
             label.set_halign(gtk4::Align::Start);
+            set_severity(&label, config.alerts.nvme.classify_text(temp));
             storage_box.append(&label);
             initial_labels.push(label);
         }
@@ -319,13 +904,18 @@ fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
 
     let nvme_labels = Rc::new(RefCell::new(initial_labels));
 
-    // Update closure
-    let sensor_data_clone = sensor_data.clone();
+    // Redraw whenever a new snapshot is published
     let storage_box_clone = storage_box.clone();
     let nvme_labels_clone = nvme_labels.clone();
+    let config = config.clone();
+    let frame_ref = frame.downgrade();
+
+    publisher.subscribe(move |snapshot| {
+        if section_removed(&frame_ref) {
+            return glib::ControlFlow::Break;
+        }
 
-    timeout_add_seconds_local(2, move || {
-        let data = sensor_data_clone.borrow();
+        let data = &snapshot.data;
         let mut labels = nvme_labels_clone.borrow_mut();
         let count = data.nvme_temps.len();
 
@@ -342,8 +932,9 @@ fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
             labels.push(label);
         } else {
             for (i, temp) in data.nvme_temps.iter().enumerate() {
-                let label = Label::new(Some(&format!("NVMe {}: {} °C", i + 1, temp)));
+                let label = Label::new(Some(&format!("NVMe {}: {} {}", i + 1, units.temperature(temp), units.temperature_symbol())));
                 label.set_halign(gtk4::Align::Start);
+                set_severity(&label, config.alerts.nvme.classify_text(temp));
                 storage_box_clone.append(&label);
                 labels.push(label);
             }
@@ -356,8 +947,8 @@ fn create_storage_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     frame
 }
 
-fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
-    let frame = Frame::new(Some("RAM Memory"));
+fn create_ram_section(data: &SensorData, publisher: &Publisher, config: &Rc<Config>) -> Frame {
+    let frame = Frame::new(Some(Section::Ram.title()));
     let grid = Grid::new();
     grid.set_margin_top(10);
     grid.set_margin_bottom(10);
@@ -366,10 +957,12 @@ fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     grid.set_row_spacing(8);
     grid.set_column_spacing(10);
 
+    let units = config.units;
+
     // Total
     let total_label = Label::new(Some("Total:"));
     total_label.set_halign(gtk4::Align::Start);
-    let total_value = Label::new(Some(&format!("{:.1} GB", sensor_data.borrow().ram_total)));
+    let total_value = Label::new(Some(&units.ram(data.ram_total)));
     total_value.set_halign(gtk4::Align::Start);
     grid.attach(&total_label, 0, 0, 1, 1);
     grid.attach(&total_value, 1, 0, 1, 1);
@@ -377,7 +970,7 @@ fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     // Used
     let used_label = Label::new(Some("Used:"));
     used_label.set_halign(gtk4::Align::Start);
-    let used_value = Label::new(Some(&format!("{:.1} GB", sensor_data.borrow().ram_used)));
+    let used_value = Label::new(Some(&units.ram(data.ram_used)));
     used_value.set_halign(gtk4::Align::Start);
     grid.attach(&used_label, 0, 1, 1, 1);
     grid.attach(&used_value, 1, 1, 1, 1);
@@ -385,7 +978,7 @@ fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     // Free
     let free_label = Label::new(Some("Free:"));
     free_label.set_halign(gtk4::Align::Start);
-    let free_value = Label::new(Some(&format!("{:.1} GB", sensor_data.borrow().ram_free)));
+    let free_value = Label::new(Some(&units.ram(data.ram_free)));
     free_value.set_halign(gtk4::Align::Start);
     grid.attach(&free_label, 0, 2, 1, 1);
     grid.attach(&free_value, 1, 2, 1, 1);
@@ -393,7 +986,7 @@ fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     // Available
     let available_label = Label::new(Some("Available:"));
     available_label.set_halign(gtk4::Align::Start);
-    let available_value = Label::new(Some(&format!("{:.1} GB", sensor_data.borrow().ram_available)));
+    let available_value = Label::new(Some(&units.ram(data.ram_available)));
     available_value.set_halign(gtk4::Align::Start);
     grid.attach(&available_label, 0, 3, 1, 1);
     grid.attach(&available_value, 1, 3, 1, 1);
@@ -403,27 +996,109 @@ fn create_ram_section(sensor_data: &Rc<RefCell<SensorData>>) -> Frame {
     load_label.set_halign(gtk4::Align::Start);
     let ram_progress = ProgressBar::new();
     ram_progress.set_hexpand(true);
-    ram_progress.set_fraction(sensor_data.borrow().ram_percent as f64 / 100.0);
+    ram_progress.set_fraction(data.ram_percent as f64 / 100.0);
     ram_progress.set_show_text(true);
-    ram_progress.set_text(Some(&format!("{:.1}%", sensor_data.borrow().ram_percent)));
+    ram_progress.set_text(Some(&format!("{:.1}%", data.ram_percent)));
+    set_severity(&ram_progress, config.alerts.ram_load.classify(data.ram_percent));
     grid.attach(&load_label, 0, 4, 1, 1);
     grid.attach(&ram_progress, 1, 4, 2, 1);
 
-    // Update closure
-    let sensor_data_clone = sensor_data.clone();
+    // Redraw whenever a new snapshot is published
+    let config = config.clone();
+    let frame_ref = frame.downgrade();
 
-    timeout_add_seconds_local(5, move || {
+    publisher.subscribe(move |snapshot| {
+        if section_removed(&frame_ref) {
+            return glib::ControlFlow::Break;
+        }
 
-        let data = sensor_data_clone.borrow();
-        total_value.set_text(&format!("{:.1} GB", data.ram_total));
-        used_value.set_text(&format!("{:.1} GB", data.ram_used));
-        free_value.set_text(&format!("{:.1} GB", data.ram_free));
-        available_value.set_text(&format!("{:.1} GB", data.ram_available));
+        let data = &snapshot.data;
+        total_value.set_text(&units.ram(data.ram_total));
+        used_value.set_text(&units.ram(data.ram_used));
+        free_value.set_text(&units.ram(data.ram_free));
+        available_value.set_text(&units.ram(data.ram_available));
         ram_progress.set_fraction(data.ram_percent as f64 / 100.0);
         ram_progress.set_text(Some(&format!("{:.1}%", data.ram_percent)));
+        set_severity(&ram_progress, config.alerts.ram_load.classify(data.ram_percent));
+        glib::ControlFlow::Continue
+    });
+
+    frame.set_child(Some(&grid));
+    frame
+}
+
+fn create_fan_section(data: &SensorData, publisher: &Publisher, config: &Rc<Config>) -> Frame {
+    let frame = Frame::new(Some(Section::Fans.title()));
+    let grid = Grid::new();
+    grid.set_margin_top(10);
+    grid.set_margin_bottom(10);
+    grid.set_margin_start(10);
+    grid.set_margin_end(10);
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(10);
+
+    let names = [&config.fans.cpu, &config.fans.chassis1, &config.fans.chassis2];
+    let mut values: Vec<Label> = Vec::new();
+
+    for (row, (name, reading)) in names.iter().zip(fan_readings(data)).enumerate() {
+        let name_label = Label::new(Some(&format!("{}:", name)));
+        name_label.set_halign(gtk4::Align::Start);
+        let value = Label::new(Some(&reading));
+        value.set_halign(gtk4::Align::Start);
+        set_severity(&value, config.alerts.fan_rpm.classify_text(&reading));
+        grid.attach(&name_label, 0, row as i32, 1, 1);
+        grid.attach(&value, 1, row as i32, 1, 1);
+        values.push(value);
+    }
+
+    // Redraw whenever a new snapshot is published
+    let config = config.clone();
+    let frame_ref = frame.downgrade();
+
+    publisher.subscribe(move |snapshot| {
+        if section_removed(&frame_ref) {
+            return glib::ControlFlow::Break;
+        }
+
+        let data = &snapshot.data;
+        for (value, reading) in values.iter().zip(fan_readings(data)) {
+            value.set_text(&reading);
+            set_severity(value, config.alerts.fan_rpm.classify_text(&reading));
+        }
         glib::ControlFlow::Continue
     });
 
     frame.set_child(Some(&grid));
     frame
 }
+
+fn fan_readings(data: &SensorData) -> [String; 3] {
+    [
+        data.cpu_fan.clone(),
+        data.chassis_fan1.clone(),
+        data.chassis_fan2.clone(),
+    ]
+}
+
+fn cpu_temp_severity(data: &SensorData, thresholds: &AlertThresholds) -> Severity {
+    thresholds
+        .cpu_temp(&data.cpu_temp_sensor)
+        .classify_text(&data.cpu_temp)
+}
+
+fn apply_gpu_severity(
+    data: &SensorData,
+    thresholds: &AlertThresholds,
+    [hotspot, edge, memory, fan]: [&Label; 4],
+    [vram, utilization]: [&ProgressBar; 2],
+) {
+    set_severity(hotspot, thresholds.gpu_hotspot.classify_text(&data.gpu_hotspot));
+    set_severity(edge, thresholds.gpu_temp(&data.gpu_name).classify_text(&data.gpu_edge));
+    set_severity(memory, thresholds.gpu_memory.classify_text(&data.gpu_memory));
+    set_severity(fan, thresholds.fan(&data.gpu_fan).classify_text(&data.gpu_fan));
+
+    let used = data.gpu_vram_used.parse::<f32>().unwrap_or(0.0);
+    let total = data.gpu_vram_total.parse::<f32>().unwrap_or(1.0).max(1.0);
+    set_severity(vram, thresholds.vram_load.classify(used / total * 100.0));
+    set_severity(utilization, thresholds.gpu_load.classify(data.gpu_utilization));
+}
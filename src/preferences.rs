@@ -0,0 +1,419 @@
+use gamer_monitor_core::config::{Config, Section, Theme};
+use gamer_monitor_core::units::{MemoryUnit, PowerUnit, TemperatureUnit};
+use gtk4::prelude::*;
+use gtk4::{
+    Align, Box, Button, CheckButton, DropDown, Entry, Grid, Label, ListBox, Notebook, Orientation,
+    ScrolledWindow, SelectionMode, SpinButton, Window,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub type ApplyFn = Rc<dyn Fn(Config)>;
+pub type SaveFn = Rc<dyn Fn()>;
+// Saves the settings in use over a configuration file that could not be loaded
+pub type ReplaceFn = Rc<dyn Fn() -> Result<(), String>>;
+
+type FanSetter = fn(&mut Config, String);
+type IntervalSetter = fn(&mut Config, u32);
+
+struct Preferences {
+    draft: RefCell<Config>,
+    apply: ApplyFn,
+    error_label: Label,
+}
+
+impl Preferences {
+    // Every change is applied live as soon as the draft is valid
+    fn edit(&self, change: impl FnOnce(&mut Config)) {
+        change(&mut self.draft.borrow_mut());
+
+        let draft = self.draft.borrow().clone();
+        match draft.validate() {
+            Ok(()) => {
+                self.error_label.set_visible(false);
+                (self.apply)(draft);
+            }
+            Err(reason) => {
+                self.error_label.set_text(&reason);
+                self.error_label.set_visible(true);
+            }
+        }
+    }
+}
+
+// With `broken`, the reason the configuration file could not be loaded:
+// changes then only last until the file is replaced on request
+pub fn show(parent: &impl IsA<Window>, config: &Config, apply: ApplyFn, save: SaveFn, broken: Option<(String, ReplaceFn)>) {
+    let error_label = Label::new(None);
+    error_label.add_css_class("error");
+    error_label.set_wrap(true);
+    error_label.set_margin_top(6);
+    error_label.set_margin_bottom(6);
+    error_label.set_visible(false);
+
+    let prefs = Rc::new(Preferences {
+        draft: RefCell::new(config.clone()),
+        apply,
+        error_label: error_label.clone(),
+    });
+
+    let window = Window::builder()
+        .title("Preferences")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(480)
+        .default_height(560)
+        .build();
+
+    let notebook = Notebook::new();
+    notebook.set_vexpand(true);
+    notebook.append_page(&create_general_page(&prefs), Some(&Label::new(Some("General"))));
+    notebook.append_page(&create_sections_page(&prefs), Some(&Label::new(Some("Sections"))));
+    notebook.append_page(&create_fans_page(&prefs), Some(&Label::new(Some("Fans"))));
+    notebook.append_page(&create_alerts_page(&prefs), Some(&Label::new(Some("Alerts"))));
+
+    let content = Box::new(Orientation::Vertical, 0);
+    if let Some((reason, replace)) = broken {
+        content.append(&create_broken_banner(&reason, replace));
+    }
+    content.append(&notebook);
+    content.append(&error_label);
+
+    // Changes are live already; the file is written once they are done
+    window.connect_close_request(move |_| {
+        save();
+        glib::Propagation::Proceed
+    });
+
+    window.set_child(Some(&content));
+    window.present();
+}
+
+fn create_broken_banner(reason: &str, replace: ReplaceFn) -> Box {
+    let banner = Box::new(Orientation::Horizontal, 10);
+    banner.set_margin_top(10);
+    banner.set_margin_start(10);
+    banner.set_margin_end(10);
+
+    let label = Label::new(Some(&format!(
+        "The configuration file could not be loaded, so the defaults are in use and changes are not saved: {}",
+        reason
+    )));
+    label.add_css_class("error");
+    label.set_wrap(true);
+    label.set_xalign(0.0);
+    label.set_hexpand(true);
+
+    let button = Button::with_label("Replace File");
+    button.set_valign(Align::Center);
+    button.set_tooltip_text(Some("Overwrite the file with the settings shown here"));
+    let banner_ref = banner.downgrade();
+    let label_ref = label.downgrade();
+    button.connect_clicked(move |_| match replace() {
+        Ok(()) => {
+            if let Some(banner) = banner_ref.upgrade() {
+                banner.set_visible(false);
+            }
+        }
+        Err(err) => {
+            if let Some(label) = label_ref.upgrade() {
+                label.set_text(&format!("Could not replace the configuration file: {}", err));
+            }
+        }
+    });
+
+    banner.append(&label);
+    banner.append(&button);
+    banner
+}
+
+fn create_page_grid() -> Grid {
+    let grid = Grid::new();
+    grid.set_margin_top(10);
+    grid.set_margin_bottom(10);
+    grid.set_margin_start(10);
+    grid.set_margin_end(10);
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(10);
+    grid
+}
+
+fn attach_row(grid: &Grid, row: i32, text: &str, widget: &impl IsA<gtk4::Widget>) {
+    let label = Label::new(Some(text));
+    label.set_halign(Align::Start);
+    label.set_hexpand(true);
+    grid.attach(&label, 0, row, 1, 1);
+    grid.attach(widget, 1, row, 1, 1);
+}
+
+fn create_interval_spin(prefs: &Rc<Preferences>, value: u32, set: IntervalSetter) -> SpinButton {
+    let spin = SpinButton::with_range(1.0, 3600.0, 1.0);
+    spin.set_value(value as f64);
+
+    let prefs = prefs.clone();
+    spin.connect_value_changed(move |spin| {
+        let seconds = spin.value_as_int() as u32;
+        prefs.edit(|config| set(config, seconds));
+    });
+    spin
+}
+
+fn create_choice<T: Copy + PartialEq + 'static>(
+    prefs: &Rc<Preferences>,
+    options: &'static [T],
+    labels: &[&str],
+    current: T,
+    set: fn(&mut Config, T),
+) -> DropDown {
+    let dropdown = DropDown::from_strings(labels);
+    let index = options.iter().position(|option| *option == current).unwrap_or(0);
+    dropdown.set_selected(index as u32);
+
+    let prefs = prefs.clone();
+    dropdown.connect_selected_notify(move |dropdown| {
+        if let Some(option) = options.get(dropdown.selected() as usize).copied() {
+            prefs.edit(|config| set(config, option));
+        }
+    });
+    dropdown
+}
+
+fn create_general_page(prefs: &Rc<Preferences>) -> Grid {
+    let grid = create_page_grid();
+    let draft = prefs.draft.borrow().clone();
+
+    // Sampling intervals
+    let intervals: [(&str, u32, IntervalSetter); 4] = [
+        ("CPU load sampling (s):", draft.sampling.cpu_load, |config, seconds| {
+            config.sampling.cpu_load = seconds
+        }),
+        ("Temperatures and fans (s):", draft.sampling.sensors, |config, seconds| {
+            config.sampling.sensors = seconds
+        }),
+        ("GPU (nvidia-smi) (s):", draft.sampling.gpu, |config, seconds| {
+            config.sampling.gpu = seconds
+        }),
+        ("Memory (s):", draft.sampling.memory, |config, seconds| {
+            config.sampling.memory = seconds
+        }),
+    ];
+
+    for (row, (text, value, set)) in intervals.into_iter().enumerate() {
+        let spin = create_interval_spin(prefs, value, set);
+        attach_row(&grid, row as i32, text, &spin);
+    }
+
+    // Units and theme
+    let temperature_labels: Vec<&str> = TemperatureUnit::ALL.iter().map(|unit| unit.symbol()).collect();
+    let temperature = create_choice(
+        prefs,
+        &TemperatureUnit::ALL,
+        &temperature_labels,
+        draft.units.temperature,
+        |config, unit| config.units.temperature = unit,
+    );
+    attach_row(&grid, 4, "Temperature unit:", &temperature);
+
+    let memory_labels: Vec<&str> = MemoryUnit::ALL.iter().map(|unit| unit.label()).collect();
+    let memory = create_choice(
+        prefs,
+        &MemoryUnit::ALL,
+        &memory_labels,
+        draft.units.memory,
+        |config, unit| config.units.memory = unit,
+    );
+    attach_row(&grid, 5, "Memory unit:", &memory);
+
+    let power_labels: Vec<&str> = PowerUnit::ALL.iter().map(|unit| unit.symbol()).collect();
+    let power = create_choice(
+        prefs,
+        &PowerUnit::ALL,
+        &power_labels,
+        draft.units.power,
+        |config, unit| config.units.power = unit,
+    );
+    attach_row(&grid, 6, "Power unit:", &power);
+
+    let theme_labels: Vec<&str> = Theme::ALL.iter().map(|theme| theme.label()).collect();
+    let theme = create_choice(
+        prefs,
+        &Theme::ALL,
+        &theme_labels,
+        draft.appearance.theme,
+        |config, theme| config.appearance.theme = theme,
+    );
+    attach_row(&grid, 7, "Theme:", &theme);
+
+    grid
+}
+
+fn create_sections_page(prefs: &Rc<Preferences>) -> Box {
+    let page = Box::new(Orientation::Vertical, 8);
+    page.set_margin_top(10);
+    page.set_margin_bottom(10);
+    page.set_margin_start(10);
+    page.set_margin_end(10);
+
+    let hint = Label::new(Some("Choose which sections appear and in which order."));
+    hint.set_halign(Align::Start);
+    hint.add_css_class("dim-label");
+    page.append(&hint);
+
+    // Visible sections first, in their configured order, then the hidden ones
+    let mut order = prefs.draft.borrow().sections.clone();
+    for section in Section::ALL {
+        if !order.contains(&section) {
+            order.push(section);
+        }
+    }
+    let order = Rc::new(RefCell::new(order));
+
+    let list = ListBox::new();
+    list.set_selection_mode(SelectionMode::None);
+    list.add_css_class("boxed-list");
+    fill_section_list(&list, prefs, &order);
+    page.append(&list);
+
+    page
+}
+
+fn fill_section_list(list: &ListBox, prefs: &Rc<Preferences>, order: &Rc<RefCell<Vec<Section>>>) {
+    while let Some(row) = list.first_child() {
+        list.remove(&row);
+    }
+
+    let sections = order.borrow().clone();
+    let visible = prefs.draft.borrow().sections.clone();
+
+    for (index, section) in sections.iter().copied().enumerate() {
+        let row = Box::new(Orientation::Horizontal, 6);
+        row.set_margin_top(4);
+        row.set_margin_bottom(4);
+        row.set_margin_start(6);
+        row.set_margin_end(6);
+
+        let check = CheckButton::with_label(section.title());
+        check.set_active(visible.contains(&section));
+        check.set_hexpand(true);
+        row.append(&check);
+
+        let up = Button::from_icon_name("go-up-symbolic");
+        up.set_sensitive(index > 0);
+        row.append(&up);
+
+        let down = Button::from_icon_name("go-down-symbolic");
+        down.set_sensitive(index + 1 < sections.len());
+        row.append(&down);
+
+        let (list_clone, prefs_clone, order_clone) = (list.clone(), prefs.clone(), order.clone());
+        check.connect_toggled(move |check| {
+            let active = check.is_active();
+            let order = order_clone.borrow().clone();
+            prefs_clone.edit(|config| {
+                config.sections = order
+                    .iter()
+                    .copied()
+                    .filter(|s| if *s == section { active } else { config.sections.contains(s) })
+                    .collect();
+            });
+            fill_section_list(&list_clone, &prefs_clone, &order_clone);
+        });
+
+        let (list_clone, prefs_clone, order_clone) = (list.clone(), prefs.clone(), order.clone());
+        up.connect_clicked(move |_| {
+            move_section(&prefs_clone, &order_clone, index, index - 1);
+            fill_section_list(&list_clone, &prefs_clone, &order_clone);
+        });
+
+        let (list_clone, prefs_clone, order_clone) = (list.clone(), prefs.clone(), order.clone());
+        down.connect_clicked(move |_| {
+            move_section(&prefs_clone, &order_clone, index, index + 1);
+            fill_section_list(&list_clone, &prefs_clone, &order_clone);
+        });
+
+        list.append(&row);
+    }
+}
+
+fn move_section(prefs: &Preferences, order: &RefCell<Vec<Section>>, from: usize, to: usize) {
+    order.borrow_mut().swap(from, to);
+
+    let order = order.borrow().clone();
+    prefs.edit(|config| {
+        config.sections = order
+            .iter()
+            .copied()
+            .filter(|section| config.sections.contains(section))
+            .collect();
+    });
+}
+
+fn create_fans_page(prefs: &Rc<Preferences>) -> Grid {
+    let grid = create_page_grid();
+    let fans = prefs.draft.borrow().fans.clone();
+
+    let rows: [(&str, String, FanSetter); 3] = [
+        ("CPU fan:", fans.cpu, |config, name| config.fans.cpu = name),
+        ("Chassis fan 1:", fans.chassis1, |config, name| config.fans.chassis1 = name),
+        ("Chassis fan 2:", fans.chassis2, |config, name| config.fans.chassis2 = name),
+    ];
+
+    for (row, (text, name, set)) in rows.into_iter().enumerate() {
+        let entry = Entry::new();
+        entry.set_text(&name);
+
+        let prefs = prefs.clone();
+        entry.connect_changed(move |entry| {
+            let name = entry.text().to_string();
+            prefs.edit(|config| set(config, name));
+        });
+        attach_row(&grid, row as i32, text, &entry);
+    }
+
+    grid
+}
+
+fn create_alerts_page(prefs: &Rc<Preferences>) -> ScrolledWindow {
+    let grid = create_page_grid();
+
+    for (column, text) in ["Warm", "Hot", "Critical"].iter().enumerate() {
+        let header = Label::new(Some(text));
+        header.add_css_class("heading");
+        grid.attach(&header, column as i32 + 1, 0, 1, 1);
+    }
+
+    let alerts = prefs.draft.borrow().alerts.clone();
+
+    for (index, (_, text, thresholds)) in alerts.named().iter().enumerate() {
+        let row = index as i32 + 1;
+        let label = Label::new(Some(text));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        grid.attach(&label, 0, row, 1, 1);
+
+        let values = [thresholds.warm, thresholds.hot, thresholds.critical];
+        for (level, value) in values.into_iter().enumerate() {
+            let spin = SpinButton::with_range(0.0, 10000.0, 1.0);
+            spin.set_value(value as f64);
+
+            let prefs = prefs.clone();
+            spin.connect_value_changed(move |spin| {
+                let value = spin.value() as f32;
+                prefs.edit(|config| {
+                    let (_, thresholds) = &mut config.alerts.named_mut()[index];
+                    match level {
+                        0 => thresholds.warm = value,
+                        1 => thresholds.hot = value,
+                        _ => thresholds.critical = value,
+                    }
+                });
+            });
+            grid.attach(&spin, level as i32 + 1, row, 1, 1);
+        }
+    }
+
+    let scrolled = ScrolledWindow::new();
+    scrolled.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+    scrolled.set_child(Some(&grid));
+    scrolled
+}
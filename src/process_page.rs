@@ -0,0 +1,251 @@
+use gamer_monitor_core::config::Config;
+use gamer_monitor_core::processes::Process;
+use gamer_monitor_core::scheduler::Publisher;
+use gtk4::prelude::*;
+use gtk4::{Align, Box, Button, Grid, Label, Orientation, ScrolledWindow, SearchEntry};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+// Enough to find what is eating the machine without drawing hundreds of
+// idle daemons every tick
+const ROWS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Pid,
+    Name,
+    Cpu,
+    Memory,
+    Gpu,
+    Vram,
+}
+
+impl Column {
+    const ALL: [Column; 6] = [
+        Column::Pid,
+        Column::Name,
+        Column::Cpu,
+        Column::Memory,
+        Column::Gpu,
+        Column::Vram,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::Name => "Name",
+            Column::Cpu => "CPU",
+            Column::Memory => "Memory",
+            Column::Gpu => "GPU",
+            Column::Vram => "VRAM",
+        }
+    }
+
+    // Usage columns start with the biggest user, the others alphabetically
+    fn descending_first(self) -> bool {
+        !matches!(self, Column::Pid | Column::Name)
+    }
+
+    fn compare(self, a: &Process, b: &Process) -> Ordering {
+        match self {
+            Column::Pid => a.pid.cmp(&b.pid),
+            Column::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            Column::Cpu => a.cpu_percent.total_cmp(&b.cpu_percent),
+            Column::Memory => a.memory_bytes.cmp(&b.memory_bytes),
+            Column::Gpu => a.gpu_percent.unwrap_or(-1.0).total_cmp(&b.gpu_percent.unwrap_or(-1.0)),
+            Column::Vram => a.vram_bytes.cmp(&b.vram_bytes),
+        }
+    }
+}
+
+struct View {
+    sort: Column,
+    descending: bool,
+    filter: String,
+    headers: Vec<(Column, Button)>,
+    // One label per column for each drawn row, created as they are needed
+    rows: Vec<[Label; 6]>,
+    count: Label,
+    grid: Grid,
+}
+
+// The Processes page: this computer's processes, refreshed with each
+// snapshot while the page is showing
+pub fn create(publisher: &Publisher, config: &Rc<RefCell<Config>>) -> Box {
+    let page = Box::new(Orientation::Vertical, 10);
+
+    let toolbar = Box::new(Orientation::Horizontal, 10);
+    let search = SearchEntry::new();
+    search.set_placeholder_text(Some("Filter by name, command or PID"));
+    search.set_hexpand(true);
+    let count = Label::new(None);
+    count.add_css_class("dim-label");
+    toolbar.append(&search);
+    toolbar.append(&count);
+    page.append(&toolbar);
+
+    let grid = Grid::new();
+    grid.set_row_spacing(4);
+    grid.set_column_spacing(16);
+    grid.set_margin_start(4);
+    grid.set_margin_end(4);
+
+    let view = Rc::new(RefCell::new(View {
+        sort: Column::Cpu,
+        descending: true,
+        filter: String::new(),
+        headers: Vec::new(),
+        rows: Vec::new(),
+        count,
+        grid: grid.clone(),
+    }));
+
+    let redraw = {
+        let view = view.clone();
+        let publisher = publisher.clone();
+        let config = config.clone();
+        let page = page.downgrade();
+        Rc::new(move || {
+            // Nobody is looking, the map handler catches up later
+            if !page.upgrade().is_some_and(|page| page.is_mapped()) {
+                return;
+            }
+            let processes = publisher.latest().map(|snapshot| snapshot.processes).unwrap_or_default();
+            draw(&mut view.borrow_mut(), &processes, &config.borrow());
+        })
+    };
+
+    for (column, &kind) in Column::ALL.iter().enumerate() {
+        let header = Button::with_label(kind.title());
+        header.add_css_class("flat");
+        header.set_halign(if column == 1 { Align::Start } else { Align::End });
+        let view_ref = view.clone();
+        let redraw = redraw.clone();
+        header.connect_clicked(move |_| {
+            {
+                let mut view = view_ref.borrow_mut();
+                if view.sort == kind {
+                    view.descending = !view.descending;
+                } else {
+                    view.sort = kind;
+                    view.descending = kind.descending_first();
+                }
+            }
+            redraw();
+        });
+        grid.attach(&header, column as i32, 0, 1, 1);
+        view.borrow_mut().headers.push((kind, header));
+    }
+
+    {
+        let view = view.clone();
+        let redraw = redraw.clone();
+        search.connect_search_changed(move |search| {
+            view.borrow_mut().filter = search.text().trim().to_lowercase();
+            redraw();
+        });
+    }
+    {
+        let redraw = redraw.clone();
+        page.connect_map(move |_| redraw());
+    }
+
+    let page_ref = page.downgrade();
+    publisher.subscribe(move |_| {
+        if page_ref.upgrade().is_none() {
+            return glib::ControlFlow::Break;
+        }
+        redraw();
+        glib::ControlFlow::Continue
+    });
+
+    let scrolled_window = ScrolledWindow::new();
+    scrolled_window.set_policy(gtk4::PolicyType::Automatic, gtk4::PolicyType::Automatic);
+    scrolled_window.set_vexpand(true);
+    scrolled_window.set_child(Some(&grid));
+    page.append(&scrolled_window);
+
+    page
+}
+
+fn draw(view: &mut View, processes: &[Process], config: &Config) {
+    let filter = view.filter.clone();
+    let mut shown: Vec<&Process> = processes
+        .iter()
+        .filter(|process| {
+            filter.is_empty()
+                || process.pid.to_string() == filter
+                || process.name.to_lowercase().contains(&filter)
+                || process.command.to_lowercase().contains(&filter)
+        })
+        .collect();
+
+    let (sort, descending) = (view.sort, view.descending);
+    shown.sort_by(|a, b| {
+        let order = sort.compare(a, b).then(a.pid.cmp(&b.pid));
+        if descending { order.reverse() } else { order }
+    });
+
+    for (kind, header) in &view.headers {
+        let arrow = match (*kind == sort, descending) {
+            (false, _) => "",
+            (true, true) => " ▼",
+            (true, false) => " ▲",
+        };
+        header.set_label(&format!("{}{}", kind.title(), arrow));
+    }
+
+    view.count.set_text(&match shown.len() {
+        total if total > ROWS => format!("{} processes, showing the first {}", total, ROWS),
+        total => format!("{} processes", total),
+    });
+
+    let units = config.units;
+    for (index, process) in shown.iter().take(ROWS).enumerate() {
+        if index == view.rows.len() {
+            let row = add_row(&view.grid, index as i32 + 1);
+            view.rows.push(row);
+        }
+        let cells = [
+            process.pid.to_string(),
+            process.name.clone(),
+            format!("{:.1}%", process.cpu_percent),
+            units.bytes(process.memory_bytes),
+            process.gpu_percent.map_or("—".to_string(), |percent| format!("{:.1}%", percent)),
+            process.vram_bytes.map_or("—".to_string(), |bytes| units.bytes(bytes)),
+        ];
+        let labels = &view.rows[index];
+        for (label, text) in labels.iter().zip(cells) {
+            label.set_text(&text);
+            label.set_visible(true);
+        }
+        // The name column shows the whole command line on hover
+        let command = (!process.command.is_empty()).then_some(process.command.as_str());
+        labels[1].set_tooltip_text(command);
+    }
+
+    for labels in view.rows.iter().skip(shown.len().min(ROWS)) {
+        for label in labels {
+            label.set_visible(false);
+        }
+    }
+}
+
+fn add_row(grid: &Grid, row: i32) -> [Label; 6] {
+    std::array::from_fn(|column| {
+        let label = Label::new(None);
+        if column == 1 {
+            label.set_halign(Align::Start);
+            label.set_hexpand(true);
+            label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+            label.set_max_width_chars(40);
+        } else {
+            label.set_halign(Align::End);
+            label.add_css_class("numeric");
+        }
+        label.set_margin_end(12);
+        grid.attach(&label, column as i32, row, 1, 1);
+        label
+    })
+}
@@ -0,0 +1,56 @@
+use gamer_monitor_core::alerts::Severity;
+use gamer_monitor_core::config::Theme;
+use gtk4::prelude::*;
+use gtk4::{CssProvider, Widget};
+use std::sync::OnceLock;
+
+// The desktop's own preference, captured before the first override
+static SYSTEM_PREFERS_DARK: OnceLock<bool> = OnceLock::new();
+
+const SEVERITY_CSS: &str = "
+label.severity-warm { color: #e5a50a; }
+label.severity-hot { color: #e66100; font-weight: bold; }
+label.severity-critical { color: #c01c28; font-weight: bold; }
+
+progressbar.severity-warm > trough > progress { background-color: #e5a50a; border-color: #e5a50a; }
+progressbar.severity-hot > trough > progress { background-color: #e66100; border-color: #e66100; }
+progressbar.severity-critical > trough > progress { background-color: #c01c28; border-color: #c01c28; }
+";
+
+pub fn load_css() {
+    let provider = CssProvider::new();
+    provider.load_from_data(SEVERITY_CSS);
+
+    if let Some(display) = gtk4::gdk::Display::default() {
+        gtk4::style_context_add_provider_for_display(
+            &display,
+            &provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+    }
+}
+
+// Replaces any previous severity class on the widget
+pub fn set_severity(widget: &impl IsA<Widget>, severity: Severity) {
+    for other in Severity::ALL {
+        if other != severity {
+            widget.remove_css_class(other.css_class());
+        }
+    }
+    widget.add_css_class(severity.css_class());
+}
+
+pub fn apply_theme(theme: Theme) {
+    let Some(settings) = gtk4::Settings::default() else {
+        return;
+    };
+
+    let system = *SYSTEM_PREFERS_DARK
+        .get_or_init(|| settings.is_gtk_application_prefer_dark_theme());
+
+    settings.set_gtk_application_prefer_dark_theme(match theme {
+        Theme::System => system,
+        Theme::Light => false,
+        Theme::Dark => true,
+    });
+}
@@ -0,0 +1,168 @@
+use crate::cli::Options;
+use gamer_monitor_core::alerts::Severity;
+use gamer_monitor_core::config::{Config, Section};
+use gamer_monitor_core::exporters;
+use gamer_monitor_core::logger::{self, CsvLogger};
+use gamer_monitor_core::sampler::{Sampler, Snapshot};
+use gamer_monitor_core::scheduler::Publisher;
+use gamer_monitor_core::sections::{section_rows, Row};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use glib::{ControlFlow, MainContext};
+use std::cell::RefCell;
+use std::io;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+// How long to wait for a key press before checking the sampler again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const LABEL_WIDTH: u16 = 20;
+
+pub fn run(options: &Options, config: &Config) -> ExitCode {
+    let source = match options.source() {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("gamer-monitor: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sampler = Sampler::new(options.sampling(&config.sampling), source);
+    let publisher = Publisher::new();
+
+    let logger = RefCell::new(options.log.clone().map(|path| CsvLogger::new(path, config)));
+    publisher.subscribe(move |snapshot| {
+        logger::record(&mut logger.borrow_mut(), snapshot);
+        ControlFlow::Continue
+    });
+    publisher.publish(sampler.sample_all());
+
+    // Before entering the alternate screen, so errors stay visible
+    let started = options.exporters(config).and_then(|enabled| exporters::start(&enabled, config, &publisher, None));
+    if let Err(err) = started {
+        eprintln!("gamer-monitor: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    let result = ratatui::try_init()
+        .and_then(|mut terminal| event_loop(&mut terminal, &mut sampler, &publisher, config));
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gamer-monitor: terminal error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// The publisher is driven by hand here instead of by the glib scheduler,
+// since the terminal has to be polled for key presses anyway
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    sampler: &mut Sampler,
+    publisher: &Publisher,
+    config: &Config,
+) -> io::Result<()> {
+    loop {
+        if let Some(snapshot) = publisher.latest() {
+            terminal.draw(|frame| draw(frame, &snapshot, config))?;
+        }
+
+        if event::poll(POLL_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(());
+            }
+        }
+
+        if let Some(snapshot) = sampler.tick(Instant::now()) {
+            publisher.publish(snapshot);
+        }
+
+        // Answers pending D-Bus calls, which glib dispatches on this thread
+        while MainContext::default().iteration(false) {}
+    }
+}
+
+fn draw(frame: &mut Frame, snapshot: &Snapshot, config: &Config) {
+    let sections: Vec<(Section, Vec<Row>)> = config
+        .sections
+        .iter()
+        .map(|&section| (section, section_rows(section, &snapshot.data, config)))
+        .collect();
+
+    let mut constraints = vec![Constraint::Length(1)];
+    constraints.extend(sections.iter().map(|(_, rows)| Constraint::Length(rows.len() as u16 + 2)));
+    constraints.push(Constraint::Min(0));
+    let areas = Layout::vertical(constraints).split(frame.area());
+
+    let title = Line::from(vec![
+        Span::styled(
+            format!("Gamer Monitor v{}", env!("CARGO_PKG_VERSION")),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("  (q to quit)", Style::default().fg(Color::DarkGray)),
+    ]);
+    frame.render_widget(Paragraph::new(title), areas[0]);
+
+    for ((section, rows), area) in sections.iter().zip(areas.iter().skip(1)) {
+        let block = Block::bordered().title(format!(" {} ", section.title()));
+        let inner = block.inner(*area);
+        frame.render_widget(block, *area);
+        draw_rows(frame, rows, inner);
+    }
+}
+
+fn draw_rows(frame: &mut Frame, rows: &[Row], area: Rect) {
+    let lines = Layout::vertical(vec![Constraint::Length(1); rows.len()]).split(area);
+
+    for (row, line) in rows.iter().zip(lines.iter()) {
+        let [label_area, value_area] =
+            Layout::horizontal([Constraint::Length(LABEL_WIDTH), Constraint::Min(0)]).areas(*line);
+
+        match row {
+            Row::Text(label, value, severity) => {
+                frame.render_widget(Paragraph::new(label.as_str()), label_area);
+                frame.render_widget(
+                    Paragraph::new(Span::styled(value.as_str(), text_style(*severity))),
+                    value_area,
+                );
+            }
+            Row::Bar(label, fraction, text, severity) => {
+                frame.render_widget(Paragraph::new(label.as_str()), label_area);
+                let gauge = Gauge::default()
+                    .gauge_style(bar_style(*severity))
+                    .ratio(fraction.clamp(0.0, 1.0))
+                    .label(text.as_str());
+                frame.render_widget(gauge, value_area);
+            }
+        }
+    }
+}
+
+// Mirrors the severity CSS in style.rs
+fn text_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Normal => Style::default(),
+        Severity::Warm => Style::default().fg(Color::Yellow),
+        Severity::Hot => Style::default().fg(Color::LightRed),
+        Severity::Critical => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    }
+}
+
+fn bar_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Normal => Style::default().fg(Color::Blue),
+        Severity::Warm => Style::default().fg(Color::Yellow),
+        Severity::Hot => Style::default().fg(Color::LightRed),
+        Severity::Critical => Style::default().fg(Color::Red),
+    }
+}